@@ -0,0 +1,53 @@
+//! Replays a trace file over a socket as if it were being traced live, for exercising the live
+//! trace support of traceview.
+//!
+//! ```sh
+//! cargo run --example replay -- tests/loop 127.0.0.1:7878
+//! cargo run --example replay -- tests/loop unix:/tmp/traceview.sock
+//! ```
+//!
+//! The instructions are sent as newline-delimited JSON, with an optional delay in milliseconds
+//! between each of them given as the third argument.
+
+use serde_json::Value;
+use std::io::{BufWriter, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let usage = "usage: replay <trace> <address> [delay in ms]";
+
+    let path = args.next().ok_or(usage)?;
+    let address = args.next().ok_or(usage)?;
+    let delay = Duration::from_millis(args.next().map_or(Ok(10), |ms| ms.parse())?);
+
+    let trace: Value = serde_json::from_slice(&std::fs::read(path)?)?;
+    let instructions = trace["instructions"]
+        .as_array()
+        .ok_or("trace has no instructions")?;
+
+    let conn: Box<dyn Write> = match address.strip_prefix("unix:") {
+        #[cfg(unix)]
+        Some(path) => Box::new(std::os::unix::net::UnixStream::connect(path)?),
+        #[cfg(not(unix))]
+        Some(_) => return Err("unix sockets are not supported on this platform".into()),
+        None => Box::new(TcpStream::connect(&address)?),
+    };
+    let mut conn = BufWriter::new(conn);
+
+    serde_json::to_writer(&mut conn, &trace["info"])?;
+    conn.write_all(b"\n")?;
+
+    for insn in instructions {
+        serde_json::to_writer(&mut conn, insn)?;
+        conn.write_all(b"\n")?;
+        conn.flush()?;
+
+        std::thread::sleep(delay);
+    }
+
+    println!("sent {0} instructions", instructions.len());
+
+    Ok(())
+}
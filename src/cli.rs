@@ -0,0 +1,271 @@
+//! Exports traces without opening the interface, for use from scripts.
+//!
+//! ```sh
+//! traceview export drcov tests/loop loop.drcov --module loop --base 0
+//! traceview export tenet tests/loop loop.tenet --cell 4
+//! traceview export folded tests/loop loop.folded
+//! traceview export mermaid tests/loop loop.mmd --function 4B
+//! traceview export png tests/loop loop.png --function sub_4B --scale 3
+//! ```
+
+use crate::export::{chrome, drcov, folded, graph, tenet};
+use crate::trace::Tracer;
+use std::error::Error;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "usage: traceview export <format> <trace> <output> [options]
+
+formats:
+  drcov   the executed basic blocks as coverage for Lighthouse and Lightkeeper
+  tenet   the register and memory changes of every instruction for Tenet
+  folded  the call stacks weighted by instructions executed, for flamegraphs
+  chrome  every call as a slice of a timeline, for Perfetto
+  dot, graphml, json, mermaid, svg, png
+          the call graph, or the control flow graph of a function
+
+options:
+  --module <name>  the module the coverage is attributed to, the name of the trace by default
+  --base <hex>     where the module was loaded, 0 by default
+  --thread <id>    the thread exported to Tenet, the first one by default
+  --pc <name>      what Tenet calls the instruction pointer, pc by default
+  --cell <bytes>   how many bytes each memory address holds, 8 by default
+  --function <f>   the function whose graph is exported, by its name or address in hex
+  --step <n>       the step whose block graphs highlight, the first one by default
+  --scale <n>      how many pixels svg and png images have per point, 2 by default
+  --strict         fail on truncated traces instead of tolerating them";
+
+/// The formats that can be exported to.
+const FORMATS: &[&str] = &[
+    "drcov", "tenet", "folded", "chrome", "dot", "graphml", "json", "mermaid", "svg", "png",
+];
+
+/// The arguments of an export, which not every format makes use of.
+struct Export {
+    format: String,
+    trace: PathBuf,
+    output: PathBuf,
+    module: Option<String>,
+    base: u64,
+    thread: Option<u32>,
+    pc: Option<String>,
+    cell: Option<usize>,
+    function: Option<String>,
+    step: Option<usize>,
+    scale: f32,
+    lenient: bool,
+}
+
+/// Runs the command given on the command line, returning whether it succeeded.
+pub fn run(args: &[String]) -> bool {
+    let result = match args.first().map(String::as_str) {
+        Some("export") => parse(&args[1..]).and_then(|export| export.run()),
+        _ => Err(USAGE.into()),
+    };
+
+    if let Err(e) = &result {
+        eprintln!("{e}");
+    }
+
+    result.is_ok()
+}
+
+fn parse(args: &[String]) -> Result<Export, Box<dyn Error>> {
+    let mut positional = Vec::new();
+    let mut module = None;
+    let mut base = 0;
+    let mut thread = None;
+    let mut pc = None;
+    let mut cell = None;
+    let mut function = None;
+    let mut step = None;
+    let mut scale = graph::DEFAULT_SCALE;
+    let mut lenient = true;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--module" => module = Some(args.next().ok_or(USAGE)?.clone()),
+            "--base" => {
+                let hex = args.next().ok_or(USAGE)?;
+                let hex = hex.strip_prefix("0x").unwrap_or(hex);
+                base = u64::from_str_radix(hex, 16)?;
+            }
+            "--thread" => thread = Some(args.next().ok_or(USAGE)?.parse()?),
+            "--pc" => pc = Some(args.next().ok_or(USAGE)?.clone()),
+            "--cell" => {
+                let bytes = args.next().ok_or(USAGE)?.parse()?;
+                if !(1..=8).contains(&bytes) {
+                    return Err("the cell must be from 1 to 8 bytes".into());
+                }
+                cell = Some(bytes);
+            }
+            "--function" => function = Some(args.next().ok_or(USAGE)?.clone()),
+            "--step" => step = Some(args.next().ok_or(USAGE)?.parse()?),
+            "--scale" => {
+                scale = args.next().ok_or(USAGE)?.parse()?;
+                if !(scale > 0.0 && scale <= graph::MAX_SCALE) {
+                    let max = graph::MAX_SCALE;
+                    return Err(format!("the scale must be above 0 and at most {max}").into());
+                }
+            }
+            "--strict" => lenient = false,
+            _ if arg.starts_with("--") => return Err(USAGE.into()),
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    let [format, trace, output] = <[String; 3]>::try_from(positional).map_err(|_| USAGE)?;
+
+    if !FORMATS.contains(&format.as_str()) {
+        return Err(format!("unknown format {format}\n\n{USAGE}").into());
+    }
+
+    Ok(Export {
+        format,
+        trace: trace.into(),
+        output: output.into(),
+        module,
+        base,
+        thread,
+        pc,
+        cell,
+        function,
+        step,
+        scale,
+        lenient,
+    })
+}
+
+impl Export {
+    fn run(self) -> Result<(), Box<dyn Error>> {
+        let mut tracer = Tracer::load(&self.trace, self.lenient)?;
+
+        if let Some(step) = self.step {
+            if step >= tracer.instructions.len() {
+                return Err(format!("the trace has no step {step}").into());
+            }
+            tracer.seek(step);
+        }
+
+        // Graphs are resolved before anything is written, so that an unknown function leaves
+        // the output alone.
+        let graph = match graph::Format::ALL
+            .into_iter()
+            .find(|graph| graph.name().eq_ignore_ascii_case(&self.format))
+        {
+            Some(format) => Some((format, self.graph(&tracer)?)),
+            None => None,
+        };
+
+        // The export is written next to the output and then moved over it, so that failing
+        // halfway through does not leave a truncated file behind.
+        let mut partial = self.output.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+
+        let written = std::fs::File::create(&partial)
+            .map_err(Into::into)
+            .and_then(|file| {
+                let mut out = BufWriter::new(file);
+                self.write(&tracer, graph, &mut out)?;
+                out.flush()?;
+                Ok(())
+            });
+
+        match written {
+            Ok(()) => std::fs::rename(&partial, &self.output)?,
+            Err(e) => {
+                let _ = std::fs::remove_file(&partial);
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The graph that is exported, which is that of the given function if any.
+    fn graph(&self, tracer: &Tracer) -> Result<graph::Graph, Box<dyn Error>> {
+        let Some(function) = &self.function else {
+            return Ok(graph::Graph::calls(tracer));
+        };
+
+        let address = tracer
+            .cfg
+            .functions()
+            .find(|f| f.name == *function)
+            .map(|f| f.start)
+            .or_else(|| u64::from_str_radix(function, 16).ok());
+
+        let graph = address
+            .and_then(|address| graph::Graph::function(tracer, address))
+            .ok_or_else(|| format!("no function {function}"))?;
+
+        Ok(graph)
+    }
+
+    /// Writes the export in its format, or the given graph in the format it was resolved for.
+    fn write(
+        &self,
+        tracer: &Tracer,
+        graph: Option<(graph::Format, graph::Graph)>,
+        out: &mut impl Write,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some((format, graph)) = graph {
+            graph.write(format, self.scale, out)?;
+            return Ok(());
+        }
+
+        match self.format.as_str() {
+            "drcov" => {
+                let module = drcov::Module {
+                    name: self
+                        .module
+                        .clone()
+                        .unwrap_or_else(|| file_stem(&self.trace)),
+                    base: self.base,
+                };
+
+                drcov::write(tracer, &module, out)?;
+            }
+            "tenet" => {
+                let mut options = tenet::Options::new(tracer);
+                options.thread = self.thread.unwrap_or(options.thread);
+                options.pc = self.pc.clone().unwrap_or(options.pc);
+                options.cell = self.cell.unwrap_or(options.cell);
+
+                tenet::write(tracer, &options, out)?;
+            }
+            "folded" => folded::write(tracer, out)?,
+            "chrome" => chrome::write(tracer, out)?,
+            _ => unreachable!("the format was checked when parsing"),
+        }
+
+        Ok(())
+    }
+}
+
+/// The name of a file without its extension.
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    fn args(cell: &str) -> Vec<String> {
+        ["tenet", "tests/loop", "loop.tenet", "--cell", cell]
+            .map(String::from)
+            .to_vec()
+    }
+
+    #[test]
+    fn cell_range() {
+        assert_eq!(parse(&args("4")).unwrap().cell, Some(4));
+        assert!(parse(&args("0")).is_err());
+        assert!(parse(&args("9")).is_err());
+    }
+}
@@ -0,0 +1,7 @@
+//! Writes what was traced out in the formats of other tools.
+
+pub mod chrome;
+pub mod drcov;
+pub mod folded;
+pub mod graph;
+pub mod tenet;
@@ -0,0 +1,147 @@
+//! Calls in the trace event format of Chrome, which Perfetto shows as a timeline with a slice
+//! for every call and a track for every thread.
+//!
+//! Steps are given as the microseconds of the format, so a call lasts as many microseconds as
+//! the instructions it executed, those of the functions it called included.
+
+use super::folded;
+use crate::trace::{Error, Tracer};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TraceFile {
+    trace_events: Vec<TraceEvent>,
+}
+
+#[derive(Serialize)]
+struct TraceEvent {
+    name: String,
+    /// What kind of event it is, `X` for a slice and `M` for the name of a thread.
+    ph: &'static str,
+    ts: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<usize>,
+    pid: u32,
+    tid: u32,
+    args: BTreeMap<&'static str, String>,
+}
+
+/// A function on the call stack, from the step it was entered at.
+struct Open {
+    function: u64,
+    step: usize,
+}
+
+/// Writes every call of every thread as a slice, from the step the function was entered at
+/// to the step after it returned.
+///
+/// Calls that were not returned from by the end of the trace end with the last instruction of
+/// their thread.
+pub fn write(tracer: &Tracer, out: &mut impl Write) -> Result<(), Error> {
+    let mut events: Vec<TraceEvent> = tracer
+        .thread_steps
+        .keys()
+        .map(|thread| TraceEvent {
+            name: String::from("thread_name"),
+            ph: "M",
+            ts: 0,
+            dur: None,
+            pid: 1,
+            tid: *thread,
+            args: BTreeMap::from([("name", format!("thread {thread}"))]),
+        })
+        .collect();
+
+    let slice = |thread: u32, open: Open, end: usize| TraceEvent {
+        name: folded::name(tracer, open.function),
+        ph: "X",
+        ts: open.step,
+        dur: Some(end - open.step),
+        pid: 1,
+        tid: thread,
+        args: BTreeMap::from([("address", format!("0x{0:X}", open.function))]),
+    };
+
+    // The call stack of every thread as of its last instruction.
+    let mut stacks: BTreeMap<u32, (Vec<Open>, usize)> = BTreeMap::new();
+
+    tracer.call_stacks(|step, insn, stack| {
+        let (open, last) = stacks.entry(insn.thread()).or_default();
+
+        let kept = open
+            .iter()
+            .zip(stack)
+            .take_while(|(open, frame)| open.function == frame.function && open.step == frame.step)
+            .count();
+
+        // Whatever is not on the stack anymore returned right before this instruction.
+        while open.len() > kept {
+            let returned = open.pop().unwrap();
+            events.push(slice(insn.thread(), returned, step));
+        }
+
+        open.extend(stack[kept..].iter().map(|frame| Open {
+            function: frame.function,
+            step: frame.step,
+        }));
+        *last = step;
+    });
+
+    for (thread, (mut open, last)) in stacks {
+        while let Some(unreturned) = open.pop() {
+            events.push(slice(thread, unreturned, last + 1));
+        }
+    }
+
+    // Calls made at the same step as their caller are nested in it when they come after it.
+    events.sort_by_key(|event| (event.tid, event.ts, std::cmp::Reverse(event.dur)));
+
+    serde_json::to_writer(
+        out,
+        &TraceFile {
+            trace_events: events,
+        },
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write;
+    use crate::trace::tests::{tracer, CALL};
+
+    #[test]
+    fn slices() {
+        let mut out = Vec::new();
+        write(&tracer(CALL), &mut out).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let events: Vec<_> = json["traceEvents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|event| {
+                (
+                    event["ph"].as_str().unwrap(),
+                    event["name"].as_str().unwrap(),
+                    event["ts"].as_u64().unwrap(),
+                    event["dur"].as_u64(),
+                )
+            })
+            .collect();
+
+        // The caller is never returned from, so it lasts until the end of the trace.
+        assert_eq!(
+            events,
+            [
+                ("X", "sub_0", 0, Some(5)),
+                ("M", "thread_name", 0, None),
+                ("X", "sub_100", 1, Some(2)),
+            ]
+        );
+    }
+}
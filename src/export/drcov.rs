@@ -0,0 +1,139 @@
+//! Coverage in the drcov format of DynamoRIO, which Lighthouse and Lightkeeper load into IDA,
+//! Binary Ninja and Ghidra.
+
+use crate::trace::{Error, Tracer};
+use byteorder::{LittleEndian, WriteBytesExt};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// The module the executed blocks are attributed to, which disassemblers match against the
+/// binary they have open by its name.
+pub struct Module {
+    pub name: String,
+    /// Where the module was loaded, the blocks are given as offsets from it.
+    pub base: u64,
+}
+
+/// Writes every executed basic block as a drcov coverage file with a single module.
+///
+/// Blocks outside of the module, before its base or too far past it for the offsets the format
+/// holds, are left out.
+pub fn write(tracer: &Tracer, module: &Module, out: &mut impl Write) -> Result<(), Error> {
+    let mut blocks: BTreeMap<u32, u16> = BTreeMap::new();
+
+    for function in tracer.cfg.functions() {
+        for block in function.blocks.values() {
+            if block.block.is_empty() {
+                continue;
+            }
+
+            let Some(offset) = block
+                .start
+                .checked_sub(module.base)
+                .and_then(|offset| u32::try_from(offset).ok())
+            else {
+                continue;
+            };
+            let size = u16::try_from(block.end - block.start).unwrap_or(u16::MAX);
+
+            let entry = blocks.entry(offset).or_default();
+            *entry = (*entry).max(size);
+        }
+    }
+
+    // Sizes given in other units than the addresses, such as bytes for word-addressed machines,
+    // would otherwise have blocks run over the ones after them.
+    let starts: Vec<u32> = blocks.keys().copied().collect();
+    for (offset, next) in starts.iter().zip(&starts[1..]) {
+        if let Some(size) = blocks.get_mut(offset) {
+            *size = (*size).min(u16::try_from(next - offset).unwrap_or(u16::MAX));
+        }
+    }
+
+    let end = blocks
+        .iter()
+        .map(|(offset, size)| module.base + *offset as u64 + *size as u64)
+        .max()
+        .unwrap_or(module.base);
+
+    writeln!(out, "DRCOV VERSION: 2")?;
+    writeln!(out, "DRCOV FLAVOR: traceview")?;
+    writeln!(out, "Module Table: version 2, count 1")?;
+    writeln!(
+        out,
+        "Columns: id, base, end, entry, checksum, timestamp, path"
+    )?;
+    writeln!(
+        out,
+        " 0, 0x{0:016x}, 0x{end:016x}, 0x0000000000000000, 0x00000000, 0x00000000, {1}",
+        module.base, module.name
+    )?;
+    writeln!(out, "BB Table: {0} bbs", blocks.len())?;
+
+    // Each block is its offset, size and module id.
+    for (offset, size) in blocks {
+        out.write_u32::<LittleEndian>(offset)?;
+        out.write_u16::<LittleEndian>(size)?;
+        out.write_u16::<LittleEndian>(0)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write, Module};
+    use crate::trace::tests::{tracer, CALL};
+
+    /// The header lines of the module and the offset, size and module of every block.
+    fn drcov(base: u64) -> (Vec<String>, Vec<(u32, u16, u16)>) {
+        let module = Module {
+            name: String::from("a.out"),
+            base,
+        };
+        let mut out = Vec::new();
+        write(&tracer(CALL), &module, &mut out).unwrap();
+
+        let table = b" bbs\n";
+        let at = out
+            .windows(table.len())
+            .position(|window| window == table)
+            .unwrap()
+            + table.len();
+        let header = String::from_utf8(out[..at].to_vec()).unwrap();
+        let blocks = out[at..]
+            .chunks(8)
+            .map(|block| {
+                (
+                    u32::from_le_bytes(block[..4].try_into().unwrap()),
+                    u16::from_le_bytes(block[4..6].try_into().unwrap()),
+                    u16::from_le_bytes(block[6..].try_into().unwrap()),
+                )
+            })
+            .collect();
+
+        (header.lines().skip(4).map(String::from).collect(), blocks)
+    }
+
+    #[test]
+    fn blocks() {
+        let (header, blocks) = drcov(0);
+        assert_eq!(
+            header,
+            [
+                " 0, 0x0000000000000000, 0x0000000000000102, 0x0000000000000000, 0x00000000, \
+                 0x00000000, a.out",
+                "BB Table: 2 bbs"
+            ]
+        );
+        // Returning from the call carries on in the block it was made from.
+        assert_eq!(blocks, [(0, 3, 0), (0x100, 2, 0)]);
+    }
+
+    #[test]
+    fn outside_module() {
+        let (header, blocks) = drcov(0x100);
+        assert_eq!(header[1], "BB Table: 1 bbs");
+        assert_eq!(blocks, [(0, 2, 0)]);
+    }
+}
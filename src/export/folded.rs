@@ -0,0 +1,75 @@
+//! Call stacks in the folded format of flamegraph.pl and inferno, weighted by how many
+//! instructions were executed with each.
+//!
+//! Every line is a call stack, outermost function first, followed by its weight:
+//!
+//! ```text
+//! sub_0;sub_4B 37
+//! ```
+
+use crate::trace::{Error, Tracer};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Writes the call stack of every instruction, counting those executed with the same stack
+/// together.
+///
+/// Traces with more than one thread have the thread as the outermost frame of every stack.
+pub fn write(tracer: &Tracer, out: &mut impl Write) -> Result<(), Error> {
+    let threads = tracer.thread_steps.len() > 1;
+    let mut stacks: BTreeMap<(u32, Vec<u64>), u64> = BTreeMap::new();
+
+    tracer.call_stacks(|_, insn, stack| {
+        let functions = stack.iter().map(|frame| frame.function).collect();
+        *stacks.entry((insn.thread(), functions)).or_default() += 1;
+    });
+
+    for ((thread, functions), count) in stacks {
+        let mut frames = Vec::with_capacity(functions.len() + 1);
+        if threads {
+            frames.push(format!("thread {thread}"));
+        }
+
+        for function in functions {
+            frames.push(name(tracer, function));
+        }
+
+        writeln!(out, "{0} {count}", frames.join(";"))?;
+    }
+
+    Ok(())
+}
+
+/// The name of a function, without the separators of the format.
+pub(super) fn name(tracer: &Tracer, function: u64) -> String {
+    match tracer.cfg.function(function) {
+        Some(function) => function.name.replace(';', ":"),
+        None => format!("sub_{function:X}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write;
+    use crate::trace::{
+        tests::{tracer, CALL},
+        Tracer,
+    };
+
+    fn folded(tracer: &Tracer) -> String {
+        let mut out = Vec::new();
+        write(tracer, &mut out).unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn stacks() {
+        let mut tracer = tracer(CALL);
+        assert_eq!(folded(&tracer), "sub_0 3\nsub_0;sub_100 2\n");
+
+        // Names keep clear of the separator of frames.
+        tracer.cfg.rename(0x100, Some("f;g"));
+        assert_eq!(folded(&tracer), "sub_0 3\nsub_0;f:g 2\n");
+    }
+}
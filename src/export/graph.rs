@@ -0,0 +1,479 @@
+//! The control flow graphs of functions and the call graph, in the formats of graph tools or
+//! drawn as images.
+
+use crate::trace::{Error, JumpKind, Tracer};
+use serde::Serialize;
+use std::io::Write;
+
+mod layout;
+mod png;
+mod svg;
+
+/// How many pixels images are drawn at for every point of their layout, which is crisp on
+/// screens with a high density and in print.
+pub const DEFAULT_SCALE: f32 = 2.0;
+pub const MAX_SCALE: f32 = 8.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Dot,
+    GraphMl,
+    Json,
+    Mermaid,
+    Svg,
+    Png,
+}
+
+impl Format {
+    pub const ALL: [Format; 6] = [
+        Format::Dot,
+        Format::GraphMl,
+        Format::Json,
+        Format::Mermaid,
+        Format::Svg,
+        Format::Png,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Dot => "DOT",
+            Format::GraphMl => "GraphML",
+            Format::Json => "JSON",
+            Format::Mermaid => "Mermaid",
+            Format::Svg => "SVG",
+            Format::Png => "PNG",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Dot => "dot",
+            Format::GraphMl => "graphml",
+            Format::Json => "json",
+            Format::Mermaid => "mmd",
+            Format::Svg => "svg",
+            Format::Png => "png",
+        }
+    }
+
+    /// Whether the graph is drawn with its own layout, at a scale.
+    pub fn is_image(&self) -> bool {
+        matches!(self, Format::Svg | Format::Png)
+    }
+}
+
+/// A graph as it is exported, independent of the format.
+#[derive(Serialize)]
+pub struct Graph {
+    pub name: String,
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    /// The node where the trace currently is, which images highlight.
+    pub current: Option<u64>,
+}
+
+/// A basic block of a function, or a function of the call graph.
+#[derive(Serialize)]
+pub struct Node {
+    pub address: u64,
+    /// The name of the function, or the label of the block when it has one.
+    pub name: Option<String>,
+    /// The instructions of a block, nothing for a function.
+    pub instructions: Vec<Line>,
+    /// How execution left a block unexpectedly, in traces that were cut short.
+    pub anomalies: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct Line {
+    pub address: u64,
+    pub label: Option<String>,
+    pub text: String,
+}
+
+#[derive(Serialize)]
+pub struct Edge {
+    pub from: u64,
+    pub to: u64,
+    pub kind: JumpKind,
+    /// How many times it was taken.
+    pub count: u64,
+    /// The address of the jump or call instruction.
+    pub site: Option<u64>,
+}
+
+impl Graph {
+    /// The control flow graph of the function starting at the given address.
+    pub fn function(tracer: &Tracer, address: u64) -> Option<Graph> {
+        let function = tracer.cfg.function(address)?;
+        let symbols = tracer.cfg.symbols();
+
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+
+        for (start, block) in &function.blocks {
+            let instructions = block
+                .iter()
+                .map(|(address, text)| Line {
+                    address: *address,
+                    label: symbols.get(*address).map(str::to_owned),
+                    text: text.clone(),
+                })
+                .collect();
+
+            nodes.push(Node {
+                address: *start,
+                name: symbols.get(*start).map(str::to_owned),
+                instructions,
+                anomalies: block
+                    .anomalies()
+                    .map(|anomaly| anomaly.to_string())
+                    .collect(),
+            });
+
+            let site = block.block.last_key_value().map(|(address, _)| *address);
+
+            for (kind, to, count) in block.edges() {
+                edges.push(Edge {
+                    from: *start,
+                    to: *to,
+                    kind: *kind,
+                    count: *count,
+                    site,
+                });
+            }
+        }
+
+        // The block holding the last instruction, if it is one of this function.
+        let current = tracer.instructions.get(tracer.step).and_then(|insn| {
+            nodes
+                .iter()
+                .find(|node| {
+                    node.instructions
+                        .iter()
+                        .any(|line| line.address == insn.addr())
+                })
+                .map(|node| node.address)
+        });
+
+        Some(Graph {
+            name: function.name.clone(),
+            nodes,
+            edges,
+            current,
+        })
+    }
+
+    /// The graph of calls between functions, with an edge for every call site.
+    pub fn calls(tracer: &Tracer) -> Graph {
+        let mut nodes: Vec<Node> = tracer
+            .cfg
+            .functions()
+            .map(|function| Node {
+                address: function.start,
+                name: Some(function.name.clone()),
+                instructions: Vec::new(),
+                anomalies: Vec::new(),
+            })
+            .collect();
+        nodes.sort_by_key(|node| node.address);
+
+        let edges = tracer
+            .cfg
+            .calls()
+            .map(|(call, count)| Edge {
+                from: call.caller,
+                to: call.callee,
+                kind: JumpKind::Call,
+                count: *count,
+                site: Some(call.site),
+            })
+            .collect();
+
+        // The function the thread of the last instruction is in.
+        let current = tracer
+            .threads
+            .get(&tracer.thread())
+            .and_then(|thread| thread.call_stack.last())
+            .map(|frame| frame.function);
+
+        Graph {
+            name: String::from(crate::trace::CALL_GRAPH),
+            nodes,
+            edges,
+            current,
+        }
+    }
+
+    /// Writes the graph in a format, with images drawn at the given number of pixels per point.
+    pub fn write(&self, format: Format, scale: f32, out: &mut impl Write) -> Result<(), Error> {
+        match format {
+            Format::Dot => self.write_dot(out),
+            Format::GraphMl => self.write_graphml(out),
+            Format::Json => Ok(serde_json::to_writer_pretty(out, self)?),
+            Format::Mermaid => self.write_mermaid(out),
+            Format::Svg => svg::write(self, &layout::Layout::new(self), scale, out),
+            Format::Png => png::write(self, &layout::Layout::new(self), scale, out),
+        }
+    }
+
+    fn write_dot(&self, out: &mut impl Write) -> Result<(), Error> {
+        writeln!(out, "digraph \"{0}\" {{", escape_dot(&self.name))?;
+
+        for node in &self.nodes {
+            writeln!(
+                out,
+                "\tnode_{0} [shape=box, label=\"{1}\"]",
+                node.address,
+                escape_dot(&node.label())
+            )?;
+        }
+
+        for edge in &self.edges {
+            let color = match edge.kind {
+                JumpKind::Conditional => "red",
+                _ => "blue",
+            };
+
+            writeln!(
+                out,
+                "\tnode_{0} -> node_{1} [label=\"{2}\", color={color}]",
+                edge.from,
+                edge.to,
+                edge.label()
+            )?;
+        }
+
+        writeln!(out, "}}")?;
+
+        Ok(())
+    }
+
+    fn write_graphml(&self, out: &mut impl Write) -> Result<(), Error> {
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="label" for="node" attr.name="label" attr.type="string"/>"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="address" for="node" attr.name="address" attr.type="long"/>"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="kind" for="edge" attr.name="kind" attr.type="string"/>"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="count" for="edge" attr.name="count" attr.type="long"/>"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="site" for="edge" attr.name="site" attr.type="long"/>"#
+        )?;
+        writeln!(
+            out,
+            r#"  <graph id="{0}" edgedefault="directed">"#,
+            escape_xml(&self.name)
+        )?;
+
+        for node in &self.nodes {
+            writeln!(out, r#"    <node id="n{0}">"#, node.address)?;
+            writeln!(
+                out,
+                r#"      <data key="label">{0}</data>"#,
+                escape_xml(&node.label())
+            )?;
+            writeln!(out, r#"      <data key="address">{0}</data>"#, node.address)?;
+            writeln!(out, "    </node>")?;
+        }
+
+        for edge in &self.edges {
+            writeln!(
+                out,
+                r#"    <edge source="n{0}" target="n{1}">"#,
+                edge.from, edge.to
+            )?;
+            writeln!(out, r#"      <data key="kind">{0:?}</data>"#, edge.kind)?;
+            writeln!(out, r#"      <data key="count">{0}</data>"#, edge.count)?;
+            if let Some(site) = edge.site {
+                writeln!(out, r#"      <data key="site">{site}</data>"#)?;
+            }
+            writeln!(out, "    </edge>")?;
+        }
+
+        writeln!(out, "  </graph>")?;
+        writeln!(out, "</graphml>")?;
+
+        Ok(())
+    }
+
+    fn write_mermaid(&self, out: &mut impl Write) -> Result<(), Error> {
+        writeln!(out, "flowchart TD")?;
+
+        for node in &self.nodes {
+            let label: Vec<String> = node.label().lines().map(escape_mermaid).collect();
+            writeln!(out, "    n{0}[\"{1}\"]", node.address, label.join("<br/>"))?;
+        }
+
+        for edge in &self.edges {
+            let arrow = match edge.kind {
+                JumpKind::Conditional => "-.->",
+                _ => "-->",
+            };
+
+            writeln!(
+                out,
+                "    n{0} {arrow}|\"{1}\"| n{2}",
+                edge.from,
+                escape_mermaid(&edge.label()),
+                edge.to
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// What a line of the label of a node shows.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Text {
+    Name,
+    Instruction,
+    Anomaly,
+}
+
+impl Node {
+    /// The name of a function, or the instructions of a block with their labels and anomalies,
+    /// one per line.
+    fn label(&self) -> String {
+        let lines: Vec<String> = self.lines().into_iter().map(|(_, line)| line).collect();
+        lines.join("\n")
+    }
+
+    fn lines(&self) -> Vec<(Text, String)> {
+        if self.instructions.is_empty() {
+            let name = self
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("0x{0:X}", self.address));
+
+            return vec![(Text::Name, name)];
+        }
+
+        let mut lines = Vec::new();
+
+        for line in &self.instructions {
+            if let Some(label) = &line.label {
+                lines.push((Text::Name, format!("{label}:")));
+            }
+            lines.push((
+                Text::Instruction,
+                format!("0x{0:0>4X} {1}", line.address, line.text),
+            ));
+        }
+
+        lines.extend(
+            self.anomalies
+                .iter()
+                .map(|anomaly| (Text::Anomaly, format!("! {anomaly}"))),
+        );
+        lines
+    }
+}
+
+impl Edge {
+    /// How many times a jump was taken, and where from for calls.
+    fn label(&self) -> String {
+        match (self.kind, self.site) {
+            (JumpKind::Call, Some(site)) => format!("0x{site:X} ({0})", self.count),
+            _ => self.count.to_string(),
+        }
+    }
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escapes the characters Mermaid would take as markup, with its own entity codes.
+fn escape_mermaid(text: &str) -> String {
+    text.replace('#', "#35;")
+        .replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Format, Graph, DEFAULT_SCALE};
+    use crate::trace::{
+        tests::{tracer, CALL},
+        JumpKind,
+    };
+
+    fn written(graph: &Graph, format: Format) -> String {
+        let mut out = Vec::new();
+        graph.write(format, DEFAULT_SCALE, &mut out).unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn call_graph() {
+        let mut tracer = tracer(CALL);
+        tracer.cfg.rename(0x100, Some("<f>"));
+        let graph = Graph::calls(&tracer);
+
+        let dot = written(&graph, Format::Dot);
+        assert!(dot.contains("\tnode_256 [shape=box, label=\"<f>\"]"));
+        assert!(dot.contains("\tnode_0 -> node_256 [label=\"0x0 (1)\", color=blue]"));
+
+        let graphml = written(&graph, Format::GraphMl);
+        assert!(graphml.contains("<data key=\"label\">&lt;f&gt;</data>"));
+        assert!(graphml.contains("<edge source=\"n0\" target=\"n256\">"));
+        assert!(graphml.contains("<data key=\"kind\">Call</data>"));
+
+        let mermaid = written(&graph, Format::Mermaid);
+        assert!(mermaid.starts_with("flowchart TD\n"));
+        assert!(mermaid.contains("    n0 -->|\"0x0 (1)\"| n256"));
+    }
+
+    #[test]
+    fn function_json() {
+        let tracer = tracer(CALL);
+        let graph = Graph::function(&tracer, 0x100).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&written(&graph, Format::Json)).unwrap();
+        let addresses: Vec<u64> = json["nodes"][0]["instructions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|line| line["address"].as_u64().unwrap())
+            .collect();
+        assert_eq!(json["name"], "sub_100");
+        assert_eq!(addresses, [0x100, 0x101]);
+        assert_eq!(json["edges"].as_array().map(Vec::len), Some(0));
+
+        let calls = Graph::calls(&tracer);
+        let json: serde_json::Value = serde_json::from_str(&written(&calls, Format::Json)).unwrap();
+        assert_eq!(
+            json["edges"][0]["kind"],
+            serde_json::to_value(JumpKind::Call).unwrap()
+        );
+        assert_eq!(json["edges"][0]["count"], 1);
+    }
+}
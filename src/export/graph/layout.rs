@@ -0,0 +1,607 @@
+//! Places the nodes of a graph in layers going down from where it starts, the way Graphviz's
+//! dot does, so that graphs can be drawn without it.
+//!
+//! Edges going back up are turned around to rank the nodes, those spanning several layers pass
+//! through a point on every layer in between, and the order of every layer is improved by
+//! moving nodes towards the average position of their neighbours.
+
+use super::{Graph, JumpKind, Text};
+use std::collections::HashMap;
+
+/// The size of the text, in points.
+pub const FONT_SIZE: f32 = 12.0;
+/// How wide every character is, as the text is monospaced.
+pub const CHAR_WIDTH: f32 = FONT_SIZE * 0.6;
+pub const LINE_HEIGHT: f32 = FONT_SIZE * 1.25;
+/// Between the text of a node and its border.
+pub const PADDING: f32 = 6.0;
+pub const BORDER_WIDTH: f32 = 1.5;
+/// The border of the node where the trace currently is.
+pub const CURRENT_BORDER_WIDTH: f32 = 3.0;
+pub const EDGE_WIDTH: f32 = 1.5;
+pub const ARROW_LENGTH: f32 = 8.0;
+pub const ARROW_WIDTH: f32 = 7.0;
+
+pub const BACKGROUND: [u8; 3] = [0xFF, 0xFF, 0xFF];
+pub const BORDER: [u8; 3] = [0x00, 0x00, 0x00];
+pub const CURRENT_FILL: [u8; 3] = [0xFF, 0xF0, 0xA8];
+
+const NODE_GAP: f32 = 24.0;
+/// Between the points edges pass through, and between those and nodes.
+const EDGE_GAP: f32 = 12.0;
+const LAYER_GAP: f32 = 48.0;
+const MARGIN: f32 = 16.0;
+/// How far the loop of an edge from a node to itself sticks out of its right side.
+const LOOP_WIDTH: f32 = 20.0;
+/// Between an edge and its label.
+const LABEL_GAP: f32 = 3.0;
+
+const ORDERING_PASSES: usize = 12;
+const POSITIONING_PASSES: usize = 8;
+
+pub type Point = (f32, f32);
+
+#[derive(Clone, Copy)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+pub struct Layout {
+    pub width: f32,
+    pub height: f32,
+    /// Where each node of the graph is, in the same order.
+    pub nodes: Vec<Rect>,
+    /// The points each edge of the graph goes through, in the same order, ending at the node it
+    /// points to. Edges to nodes which are not in the graph have none.
+    pub edges: Vec<Vec<Point>>,
+    /// Where the label of each edge starts, vertically centered on the point.
+    pub labels: Vec<Point>,
+}
+
+/// A node of the graph, or a point an edge passes through on a layer it spans.
+struct Vertex {
+    layer: usize,
+    width: f32,
+    height: f32,
+    /// How much more room it takes up to its right, for the loop of an edge to itself.
+    extra: f32,
+    /// Where its center is horizontally.
+    x: f32,
+    ups: Vec<usize>,
+    downs: Vec<usize>,
+}
+
+impl Vertex {
+    fn new(layer: usize, width: f32, height: f32) -> Vertex {
+        Vertex {
+            layer,
+            width,
+            height,
+            extra: 0.0,
+            x: 0.0,
+            ups: Vec::new(),
+            downs: Vec::new(),
+        }
+    }
+}
+
+impl Layout {
+    pub fn new(graph: &Graph) -> Layout {
+        let count = graph.nodes.len();
+        let index: HashMap<u64, usize> = graph
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.address, i))
+            .collect();
+        let links: Vec<Option<(usize, usize)>> = graph
+            .edges
+            .iter()
+            .map(|edge| Some((*index.get(&edge.from)?, *index.get(&edge.to)?)))
+            .collect();
+
+        let reversed = back_edges(count, &links);
+        let ranks = rank(count, &links, &reversed);
+
+        let mut vertices: Vec<Vertex> = graph
+            .nodes
+            .iter()
+            .zip(&ranks)
+            .map(|(node, layer)| {
+                let lines = node.lines();
+                let columns = lines
+                    .iter()
+                    .map(|(_, line)| line.chars().count())
+                    .max()
+                    .unwrap_or(0);
+
+                Vertex::new(
+                    *layer,
+                    columns as f32 * CHAR_WIDTH + 2.0 * PADDING,
+                    lines.len() as f32 * LINE_HEIGHT + 2.0 * PADDING,
+                )
+            })
+            .collect();
+
+        // The vertices every edge passes through, from the top down.
+        let mut chains = Vec::new();
+
+        for (i, link) in links.iter().enumerate() {
+            let chain = match *link {
+                Some((from, to)) if from == to => {
+                    let label = graph.edges[i].label().chars().count() as f32 * CHAR_WIDTH;
+                    let vertex = &mut vertices[from];
+                    vertex.extra = vertex.extra.max(LOOP_WIDTH + LABEL_GAP + label);
+                    Vec::new()
+                }
+                Some((from, to)) => {
+                    let (top, bottom) = if reversed[i] { (to, from) } else { (from, to) };
+                    let mut chain = vec![top];
+
+                    for layer in vertices[top].layer + 1..vertices[bottom].layer {
+                        vertices.push(Vertex::new(layer, 0.0, 0.0));
+                        chain.push(vertices.len() - 1);
+                    }
+                    chain.push(bottom);
+
+                    for pair in chain.windows(2) {
+                        vertices[pair[0]].downs.push(pair[1]);
+                        vertices[pair[1]].ups.push(pair[0]);
+                    }
+
+                    chain
+                }
+                None => Vec::new(),
+            };
+
+            chains.push(chain);
+        }
+
+        let depth = vertices
+            .iter()
+            .map(|vertex| vertex.layer + 1)
+            .max()
+            .unwrap_or(0);
+        let mut layers = vec![Vec::new(); depth];
+        for (v, vertex) in vertices.iter().enumerate() {
+            layers[vertex.layer].push(v);
+        }
+
+        order(&mut layers, &vertices);
+        position(&layers, &mut vertices, count);
+
+        let heights: Vec<f32> = layers
+            .iter()
+            .map(|layer| {
+                layer
+                    .iter()
+                    .map(|v| vertices[*v].height)
+                    .fold(0.0, f32::max)
+            })
+            .collect();
+        let mut tops = Vec::new();
+        let mut y = MARGIN;
+        for height in &heights {
+            tops.push(y);
+            y += height + LAYER_GAP;
+        }
+
+        let mut nodes: Vec<Rect> = vertices[..count]
+            .iter()
+            .map(|vertex| Rect {
+                x: vertex.x - vertex.width / 2.0,
+                y: tops[vertex.layer],
+                width: vertex.width,
+                height: vertex.height,
+            })
+            .collect();
+
+        let ports = ports(&chains, &vertices, &nodes);
+        let mut edges = Vec::new();
+        let mut labels = Vec::new();
+
+        for (i, chain) in chains.iter().enumerate() {
+            let mut points = Vec::new();
+
+            if let Some((top, bottom)) = chain.first().zip(chain.last()) {
+                let node = nodes[*top];
+                points.push((ports[&(i, *top)], node.y + node.height));
+
+                for v in &chain[1..chain.len() - 1] {
+                    let vertex = &vertices[*v];
+                    points.push((vertex.x, tops[vertex.layer]));
+                    points.push((vertex.x, tops[vertex.layer] + heights[vertex.layer]));
+                }
+
+                points.push((ports[&(i, *bottom)], nodes[*bottom].y));
+
+                if reversed[i] {
+                    points.reverse();
+                }
+            } else if let Some((from, _)) = links[i].filter(|(from, to)| from == to) {
+                let node = nodes[from];
+                let right = node.x + node.width;
+                let middle = node.y + node.height / 2.0;
+
+                points = vec![
+                    (right, middle - LINE_HEIGHT / 2.0),
+                    (right + LOOP_WIDTH, middle - LINE_HEIGHT / 2.0),
+                    (right + LOOP_WIDTH, middle + LINE_HEIGHT / 2.0),
+                    (right, middle + LINE_HEIGHT / 2.0),
+                ];
+            }
+
+            // Halfway along the segment in the middle, on the outer side of edges going off to
+            // the left so that those fanning out of a node keep their labels apart.
+            let label = match points.len() {
+                0 => (0.0, 0.0),
+                1 => points[0],
+                len => {
+                    let (a, b) = (points[(len - 1) / 2], points[(len - 1) / 2 + 1]);
+                    let middle = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+
+                    if b.0 < a.0 {
+                        let width = graph.edges[i].label().chars().count() as f32 * CHAR_WIDTH;
+                        (middle.0 - LABEL_GAP - width, middle.1)
+                    } else {
+                        (middle.0 + LABEL_GAP, middle.1)
+                    }
+                }
+            };
+
+            edges.push(points);
+            labels.push(label);
+        }
+
+        // Labels to the left of edges may stick out of the margin.
+        let shift = labels
+            .iter()
+            .zip(&edges)
+            .filter(|(_, points)| !points.is_empty())
+            .map(|(label, _)| MARGIN - label.0)
+            .fold(0.0, f32::max);
+        for node in &mut nodes {
+            node.x += shift;
+        }
+        for point in edges.iter_mut().flatten().chain(&mut labels) {
+            point.0 += shift;
+        }
+
+        let mut width = nodes
+            .iter()
+            .zip(&vertices)
+            .map(|(node, vertex)| node.x + node.width + vertex.extra)
+            .fold(MARGIN, f32::max);
+        for (edge, label) in graph.edges.iter().zip(&labels) {
+            width = width.max(label.0 + edge.label().chars().count() as f32 * CHAR_WIDTH);
+        }
+
+        let bottom = tops
+            .last()
+            .zip(heights.last())
+            .map_or(MARGIN, |(top, height)| top + height);
+
+        Layout {
+            width: width + MARGIN,
+            height: bottom + MARGIN,
+            nodes,
+            edges,
+            labels,
+        }
+    }
+}
+
+/// The color an edge is drawn in, by the jump it is.
+pub fn edge_color(kind: JumpKind) -> [u8; 3] {
+    match kind {
+        JumpKind::Conditional => [0xD0, 0x20, 0x20],
+        _ => [0x20, 0x40, 0xD0],
+    }
+}
+
+pub fn text_color(text: Text) -> [u8; 3] {
+    match text {
+        Text::Name => [0x20, 0x60, 0xA0],
+        Text::Instruction => [0x00, 0x00, 0x00],
+        Text::Anomaly => [0xD0, 0x20, 0x20],
+    }
+}
+
+/// Where the text of a line is written from, for a line vertically centered on the given point.
+pub fn baseline(center: f32) -> f32 {
+    center + FONT_SIZE * 0.35
+}
+
+/// Where the line of an edge stops, and the corners of the arrow head at its end.
+pub fn arrow(points: &[Point]) -> (Point, [Point; 3]) {
+    let (tip, from) = (points[points.len() - 1], points[points.len() - 2]);
+    let (dx, dy) = (tip.0 - from.0, tip.1 - from.1);
+    let length = (dx * dx + dy * dy).sqrt().max(f32::EPSILON);
+    let (dx, dy) = (dx / length, dy / length);
+
+    let base = (tip.0 - dx * ARROW_LENGTH, tip.1 - dy * ARROW_LENGTH);
+    let (nx, ny) = (-dy * ARROW_WIDTH / 2.0, dx * ARROW_WIDTH / 2.0);
+
+    (
+        base,
+        [tip, (base.0 + nx, base.1 + ny), (base.0 - nx, base.1 - ny)],
+    )
+}
+
+/// The edges to turn around so that none go back up, those going back to where a depth-first
+/// search of the graph from the nodes nothing points to came from.
+fn back_edges(count: usize, links: &[Option<(usize, usize)>]) -> Vec<bool> {
+    let mut outgoing = vec![Vec::new(); count];
+    let mut incoming = vec![0; count];
+
+    for (i, link) in links.iter().enumerate() {
+        if let Some((from, to)) = link.filter(|(from, to)| from != to) {
+            outgoing[from].push((i, to));
+            incoming[to] += 1;
+        }
+    }
+
+    // Not yet visited, being visited, and visited.
+    let mut state = vec![0u8; count];
+    let mut reversed = vec![false; links.len()];
+    let starts = (0..count).filter(|v| incoming[*v] == 0).chain(0..count);
+
+    for start in starts {
+        if state[start] != 0 {
+            continue;
+        }
+
+        state[start] = 1;
+        let mut stack = vec![(start, 0)];
+
+        while let Some((v, next)) = stack.last_mut() {
+            let Some(&(edge, to)) = outgoing[*v].get(*next) else {
+                state[*v] = 2;
+                stack.pop();
+                continue;
+            };
+
+            *next += 1;
+
+            match state[to] {
+                0 => {
+                    state[to] = 1;
+                    stack.push((to, 0));
+                }
+                1 => reversed[edge] = true,
+                _ => {}
+            }
+        }
+    }
+
+    reversed
+}
+
+/// The layer of every node, as far down as the longest path to it.
+fn rank(count: usize, links: &[Option<(usize, usize)>], reversed: &[bool]) -> Vec<usize> {
+    let mut downs = vec![Vec::new(); count];
+    let mut incoming = vec![0; count];
+
+    for (i, link) in links.iter().enumerate() {
+        if let Some((from, to)) = link.filter(|(from, to)| from != to) {
+            let (top, bottom) = if reversed[i] { (to, from) } else { (from, to) };
+            downs[top].push(bottom);
+            incoming[bottom] += 1;
+        }
+    }
+
+    let mut layers = vec![0; count];
+    let mut ready: Vec<usize> = (0..count).filter(|v| incoming[*v] == 0).collect();
+
+    while let Some(v) = ready.pop() {
+        for down in &downs[v] {
+            layers[*down] = layers[*down].max(layers[v] + 1);
+            incoming[*down] -= 1;
+
+            if incoming[*down] == 0 {
+                ready.push(*down);
+            }
+        }
+    }
+
+    layers
+}
+
+/// Orders the layers by sorting every one of them by where the neighbours of its vertices are,
+/// going down and then up, keeping whichever order had the fewest crossing edges.
+fn order(layers: &mut [Vec<usize>], vertices: &[Vertex]) {
+    let mut positions = vec![0; vertices.len()];
+    let place = |layer: &[usize], positions: &mut [usize]| {
+        for (i, v) in layer.iter().enumerate() {
+            positions[*v] = i;
+        }
+    };
+
+    for layer in layers.iter() {
+        place(layer, &mut positions);
+    }
+
+    let mut best = layers.to_vec();
+    let mut fewest = crossings(layers, vertices, &positions);
+
+    for pass in 0..ORDERING_PASSES {
+        let down = pass % 2 == 0;
+        let sweep: Vec<usize> = if down {
+            (1..layers.len()).collect()
+        } else {
+            (0..layers.len().saturating_sub(1)).rev().collect()
+        };
+
+        for l in sweep {
+            let keys: HashMap<usize, f32> = layers[l]
+                .iter()
+                .map(|v| {
+                    let vertex = &vertices[*v];
+                    let neighbours = if down { &vertex.ups } else { &vertex.downs };
+                    let key = match neighbours.len() {
+                        0 => positions[*v] as f32,
+                        len => {
+                            neighbours.iter().map(|n| positions[*n]).sum::<usize>() as f32
+                                / len as f32
+                        }
+                    };
+
+                    (*v, key)
+                })
+                .collect();
+
+            layers[l].sort_by(|a, b| keys[a].total_cmp(&keys[b]));
+            place(&layers[l], &mut positions);
+        }
+
+        let count = crossings(layers, vertices, &positions);
+        if count < fewest {
+            fewest = count;
+            best = layers.to_vec();
+        }
+    }
+
+    layers.clone_from_slice(&best);
+}
+
+/// How many pairs of edges cross between the layers.
+fn crossings(layers: &[Vec<usize>], vertices: &[Vertex], positions: &[usize]) -> usize {
+    let mut count = 0;
+
+    for layer in layers {
+        let edges: Vec<(usize, usize)> = layer
+            .iter()
+            .flat_map(|v| {
+                vertices[*v]
+                    .downs
+                    .iter()
+                    .map(|down| (positions[*v], positions[*down]))
+            })
+            .collect();
+
+        for (i, a) in edges.iter().enumerate() {
+            count += edges[i + 1..]
+                .iter()
+                .filter(|b| (a.0 < b.0 && a.1 > b.1) || (a.0 > b.0 && a.1 < b.1))
+                .count();
+        }
+    }
+
+    count
+}
+
+/// Places the vertices of every layer as close to the average of their neighbours as they can
+/// be without overlapping, going down and then up, and moves everything into the margin.
+fn position(layers: &[Vec<usize>], vertices: &mut [Vertex], nodes: usize) {
+    let separation = |vertices: &[Vertex], left: usize, right: usize| {
+        let gap = if left < nodes && right < nodes {
+            NODE_GAP
+        } else {
+            EDGE_GAP
+        };
+        vertices[left].width / 2.0 + vertices[left].extra + gap + vertices[right].width / 2.0
+    };
+
+    for layer in layers {
+        let mut x = 0.0;
+        for (i, v) in layer.iter().enumerate() {
+            if i > 0 {
+                x += separation(vertices, layer[i - 1], *v);
+            }
+            vertices[*v].x = x;
+        }
+    }
+
+    for pass in 0..POSITIONING_PASSES {
+        let down = pass % 2 == 0;
+        let sweep: Vec<&Vec<usize>> = if down {
+            layers.iter().collect()
+        } else {
+            layers.iter().rev().collect()
+        };
+
+        for layer in sweep {
+            let wanted: Vec<f32> = layer
+                .iter()
+                .map(|v| {
+                    let vertex = &vertices[*v];
+                    let neighbours = if down { &vertex.ups } else { &vertex.downs };
+                    match neighbours.len() {
+                        0 => vertex.x,
+                        len => neighbours.iter().map(|n| vertices[*n].x).sum::<f32>() / len as f32,
+                    }
+                })
+                .collect();
+
+            // Pushing the vertices apart to the right and to the left both keep them from
+            // overlapping, and so does the average of the two.
+            let mut right = wanted.clone();
+            for i in 1..layer.len() {
+                right[i] =
+                    right[i].max(right[i - 1] + separation(vertices, layer[i - 1], layer[i]));
+            }
+            let mut left = wanted;
+            for i in (1..layer.len()).rev() {
+                left[i - 1] =
+                    left[i - 1].min(left[i] - separation(vertices, layer[i - 1], layer[i]));
+            }
+
+            for (i, v) in layer.iter().enumerate() {
+                vertices[*v].x = (left[i] + right[i]) / 2.0;
+            }
+        }
+    }
+
+    let min = vertices
+        .iter()
+        .map(|vertex| vertex.x - vertex.width / 2.0)
+        .fold(f32::INFINITY, f32::min);
+    for vertex in vertices.iter_mut() {
+        vertex.x += MARGIN - min;
+    }
+}
+
+/// Where every edge meets the nodes at either end of it, by the edge and the node, spread
+/// along the bottom and top sides in the order of where the edges go.
+fn ports(
+    chains: &[Vec<usize>],
+    vertices: &[Vertex],
+    nodes: &[Rect],
+) -> HashMap<(usize, usize), f32> {
+    // The edges along either side of every node, by the node and whether it is the top side,
+    // along with where they go.
+    let mut sides: HashMap<(usize, bool), Vec<(f32, usize)>> = HashMap::new();
+
+    for (i, chain) in chains.iter().enumerate() {
+        if let [top, next, ..] = chain[..] {
+            sides
+                .entry((top, false))
+                .or_default()
+                .push((vertices[next].x, i));
+        }
+        if let [.., previous, bottom] = chain[..] {
+            sides
+                .entry((bottom, true))
+                .or_default()
+                .push((vertices[previous].x, i));
+        }
+    }
+
+    let mut ports = HashMap::new();
+
+    for ((node, _), mut edges) in sides {
+        edges.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let rect = nodes[node];
+        let spacing = rect.width / (edges.len() + 1) as f32;
+
+        for (k, (_, edge)) in edges.into_iter().enumerate() {
+            ports.insert((edge, node), rect.x + spacing * (k + 1) as f32);
+        }
+    }
+
+    ports
+}
@@ -0,0 +1,293 @@
+//! Graphs drawn as PNG, rasterized with the monospaced font the interface uses.
+
+use super::{
+    layout::{
+        arrow, baseline, edge_color, text_color, Layout, Point, Rect, BACKGROUND, BORDER,
+        BORDER_WIDTH, CHAR_WIDTH, CURRENT_BORDER_WIDTH, CURRENT_FILL, EDGE_WIDTH, FONT_SIZE,
+        LINE_HEIGHT, PADDING,
+    },
+    Graph,
+};
+use crate::trace::Error;
+use ab_glyph::{Font, FontRef};
+use image::{codecs::png::PngEncoder, ColorType, ImageEncoder, Rgb, RgbImage};
+use std::io::Write;
+
+/// The font of the interface which is monospaced.
+const FONT: &str = "Hack";
+
+/// How many samples are taken along each side of a pixel to fill shapes.
+const SAMPLES: usize = 4;
+
+/// The most pixels an image is drawn with, which already takes up 192 MiB.
+const MAX_PIXELS: u64 = 1 << 26;
+
+pub fn write(
+    graph: &Graph,
+    layout: &Layout,
+    scale: f32,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    let fonts = egui::FontDefinitions::default();
+    let data = fonts.font_data.get(FONT).ok_or(Error::MissingFont)?;
+    let font = FontRef::try_from_slice_and_index(&data.font, data.index)
+        .map_err(|_| Error::MissingFont)?;
+
+    let width = (layout.width * scale).ceil() as u64;
+    let height = (layout.height * scale).ceil() as u64;
+    if width.max(1).saturating_mul(height.max(1)) > MAX_PIXELS {
+        return Err(Error::ImageTooLarge);
+    }
+
+    let mut canvas = Canvas {
+        image: RgbImage::from_pixel(width as u32, height as u32, Rgb(BACKGROUND)),
+        scale,
+        font,
+    };
+
+    for ((edge, points), label) in graph.edges.iter().zip(&layout.edges).zip(&layout.labels) {
+        if points.len() < 2 {
+            continue;
+        }
+
+        let color = edge_color(edge.kind);
+        let (end, head) = arrow(points);
+
+        // The last segment stops at the arrow head.
+        for (i, pair) in points.windows(2).enumerate() {
+            let to = if i == points.len() - 2 { end } else { pair[1] };
+            canvas.line(pair[0], to, EDGE_WIDTH, color);
+        }
+
+        canvas.triangle(head, color);
+        canvas.text(*label, &edge.label(), color);
+    }
+
+    for (node, rect) in graph.nodes.iter().zip(&layout.nodes) {
+        let (fill, border) = if graph.current == Some(node.address) {
+            (CURRENT_FILL, CURRENT_BORDER_WIDTH)
+        } else {
+            (BACKGROUND, BORDER_WIDTH)
+        };
+
+        canvas.rect(*rect, fill);
+        canvas.border(*rect, border, BORDER);
+
+        for (i, (text, line)) in node.lines().into_iter().enumerate() {
+            let start = (
+                rect.x + PADDING,
+                rect.y + PADDING + LINE_HEIGHT * (i as f32 + 0.5),
+            );
+            canvas.text(start, &line, text_color(text));
+        }
+    }
+
+    let image = canvas.image;
+    PngEncoder::new(out).write_image(&image, image.width(), image.height(), ColorType::Rgb8)?;
+
+    Ok(())
+}
+
+/// An image being drawn on, in points which are scaled to pixels.
+struct Canvas<'a> {
+    image: RgbImage,
+    scale: f32,
+    font: FontRef<'a>,
+}
+
+impl Canvas<'_> {
+    /// Blends a color into a pixel, by how much of the pixel it covers.
+    fn blend(&mut self, x: i64, y: i64, color: [u8; 3], coverage: f32) {
+        if x < 0 || y < 0 || x >= self.image.width() as i64 || y >= self.image.height() as i64 {
+            return;
+        }
+
+        let coverage = coverage.clamp(0.0, 1.0);
+        let pixel = self.image.get_pixel_mut(x as u32, y as u32);
+
+        for (channel, color) in pixel.0.iter_mut().zip(color) {
+            *channel = (*channel as f32 * (1.0 - coverage) + color as f32 * coverage).round() as u8;
+        }
+    }
+
+    fn rect(&mut self, rect: Rect, color: [u8; 3]) {
+        let (left, top) = (rect.x * self.scale, rect.y * self.scale);
+        let (right, bottom) = (
+            left + rect.width * self.scale,
+            top + rect.height * self.scale,
+        );
+
+        for y in top.floor() as i64..bottom.ceil() as i64 {
+            let vertical = (bottom.min(y as f32 + 1.0) - top.max(y as f32)).max(0.0);
+
+            for x in left.floor() as i64..right.ceil() as i64 {
+                let horizontal = (right.min(x as f32 + 1.0) - left.max(x as f32)).max(0.0);
+                self.blend(x, y, color, horizontal * vertical);
+            }
+        }
+    }
+
+    /// Draws a border of the given width centered on the sides of a rectangle.
+    fn border(&mut self, rect: Rect, width: f32, color: [u8; 3]) {
+        let (x, y) = (rect.x - width / 2.0, rect.y - width / 2.0);
+        let (across, down) = (rect.width + width, rect.height - width);
+
+        for side in [
+            Rect {
+                x,
+                y,
+                width: across,
+                height: width,
+            },
+            Rect {
+                x,
+                y: y + rect.height,
+                width: across,
+                height: width,
+            },
+            Rect {
+                x,
+                y: y + width,
+                width,
+                height: down,
+            },
+            Rect {
+                x: x + rect.width,
+                y: y + width,
+                width,
+                height: down,
+            },
+        ] {
+            self.rect(side, color);
+        }
+    }
+
+    /// Draws a line with round ends, shaded by how far every pixel is from it.
+    fn line(&mut self, from: Point, to: Point, width: f32, color: [u8; 3]) {
+        let (ax, ay) = (from.0 * self.scale, from.1 * self.scale);
+        let (bx, by) = (to.0 * self.scale, to.1 * self.scale);
+        let radius = width * self.scale / 2.0;
+
+        let (dx, dy) = (bx - ax, by - ay);
+        let length = (dx * dx + dy * dy).max(f32::EPSILON);
+
+        let (left, right) = (ax.min(bx) - radius - 1.0, ax.max(bx) + radius + 1.0);
+        let (top, bottom) = (ay.min(by) - radius - 1.0, ay.max(by) + radius + 1.0);
+
+        for y in top.floor() as i64..bottom.ceil() as i64 {
+            for x in left.floor() as i64..right.ceil() as i64 {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let t = (((px - ax) * dx + (py - ay) * dy) / length).clamp(0.0, 1.0);
+                let (cx, cy) = (ax + dx * t - px, ay + dy * t - py);
+                let distance = (cx * cx + cy * cy).sqrt();
+
+                self.blend(x, y, color, radius + 0.5 - distance);
+            }
+        }
+    }
+
+    /// Fills a triangle, by how many samples of every pixel fall within it.
+    fn triangle(&mut self, corners: [Point; 3], color: [u8; 3]) {
+        let corners = corners.map(|(x, y)| (x * self.scale, y * self.scale));
+        let edge = |a: (f32, f32), b: (f32, f32), p: (f32, f32)| {
+            (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+        };
+        let inside = |p: (f32, f32)| {
+            let sides = [
+                edge(corners[0], corners[1], p),
+                edge(corners[1], corners[2], p),
+                edge(corners[2], corners[0], p),
+            ];
+            sides.iter().all(|side| *side >= 0.0) || sides.iter().all(|side| *side <= 0.0)
+        };
+
+        let xs = corners.map(|(x, _)| x);
+        let ys = corners.map(|(_, y)| y);
+        let left = xs.into_iter().fold(f32::INFINITY, f32::min);
+        let right = xs.into_iter().fold(f32::NEG_INFINITY, f32::max);
+        let top = ys.into_iter().fold(f32::INFINITY, f32::min);
+        let bottom = ys.into_iter().fold(f32::NEG_INFINITY, f32::max);
+
+        for y in top.floor() as i64..bottom.ceil() as i64 {
+            for x in left.floor() as i64..right.ceil() as i64 {
+                let mut covered = 0;
+
+                for sy in 0..SAMPLES {
+                    for sx in 0..SAMPLES {
+                        let p = (
+                            x as f32 + (sx as f32 + 0.5) / SAMPLES as f32,
+                            y as f32 + (sy as f32 + 0.5) / SAMPLES as f32,
+                        );
+                        covered += inside(p) as usize;
+                    }
+                }
+
+                self.blend(x, y, color, covered as f32 / (SAMPLES * SAMPLES) as f32);
+            }
+        }
+    }
+
+    /// Writes a line of text from a point, vertically centered on it.
+    fn text(&mut self, start: Point, text: &str, color: [u8; 3]) {
+        // Font sizes are the size of the em, while glyphs are scaled by the height of the font.
+        let units_per_em = self.font.units_per_em().unwrap_or(1.0);
+        let size = FONT_SIZE * self.scale * self.font.height_unscaled() / units_per_em;
+        let y = baseline(start.1) * self.scale;
+
+        for (i, c) in text.chars().enumerate() {
+            let x = (start.0 + CHAR_WIDTH * i as f32) * self.scale;
+            let glyph = self
+                .font
+                .glyph_id(c)
+                .with_scale_and_position(size, ab_glyph::point(x, y));
+
+            let Some(outline) = self.font.outline_glyph(glyph) else {
+                continue;
+            };
+
+            let bounds = outline.px_bounds();
+            let mut coverage = Vec::new();
+            outline.draw(|gx, gy, c| coverage.push((gx as i64, gy as i64, c)));
+
+            for (gx, gy, c) in coverage {
+                self.blend(bounds.min.x as i64 + gx, bounds.min.y as i64 + gy, color, c);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write, Layout};
+    use crate::{export::graph::Graph, trace::Error};
+
+    fn draw(width: f32, height: f32, scale: f32) -> Result<Vec<u8>, Error> {
+        let graph = Graph {
+            name: String::from("empty"),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            current: None,
+        };
+        let layout = Layout {
+            width,
+            height,
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            labels: Vec::new(),
+        };
+
+        let mut out = Vec::new();
+        write(&graph, &layout, scale, &mut out).map(|_| out)
+    }
+
+    #[test]
+    fn too_large() {
+        let png = image::load_from_memory(&draw(100.0, 50.5, 2.0).unwrap()).unwrap();
+        assert_eq!((png.width(), png.height()), (200, 101));
+        assert!(matches!(
+            draw(20_000.0, 20_000.0, 1.0),
+            Err(Error::ImageTooLarge)
+        ));
+        assert!(matches!(draw(1e30, 0.0, 8.0), Err(Error::ImageTooLarge)));
+    }
+}
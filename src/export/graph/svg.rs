@@ -0,0 +1,153 @@
+//! Graphs drawn as SVG, which keeps the text of the nodes as text.
+
+use super::{
+    escape_xml,
+    layout::{
+        arrow, baseline, edge_color, text_color, Layout, Point, BACKGROUND, BORDER, BORDER_WIDTH,
+        CURRENT_BORDER_WIDTH, CURRENT_FILL, EDGE_WIDTH, FONT_SIZE, LINE_HEIGHT, PADDING,
+    },
+    Graph,
+};
+use crate::trace::Error;
+use std::io::Write;
+
+/// Monospaced fonts as wide as the one the layout is measured with.
+const FONT_FAMILY: &str = "Hack, 'DejaVu Sans Mono', Menlo, Consolas, monospace";
+
+pub fn write(
+    graph: &Graph,
+    layout: &Layout,
+    scale: f32,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {2} {3}" font-family="{FONT_FAMILY}" font-size="{FONT_SIZE}" style="white-space: pre">"#,
+        number(layout.width * scale),
+        number(layout.height * scale),
+        number(layout.width),
+        number(layout.height),
+    )?;
+    writeln!(out, "  <title>{0}</title>", escape_xml(&graph.name))?;
+    writeln!(
+        out,
+        r#"  <rect width="100%" height="100%" fill="{0}"/>"#,
+        hex(BACKGROUND)
+    )?;
+
+    for ((edge, points), label) in graph.edges.iter().zip(&layout.edges).zip(&layout.labels) {
+        if points.len() < 2 {
+            continue;
+        }
+
+        let color = hex(edge_color(edge.kind));
+        let (end, head) = arrow(points);
+        let mut line = points[..points.len() - 1].to_vec();
+        line.push(end);
+
+        writeln!(
+            out,
+            r#"  <polyline points="{0}" fill="none" stroke="{color}" stroke-width="{EDGE_WIDTH}"/>"#,
+            join(&line)
+        )?;
+        writeln!(
+            out,
+            r#"  <polygon points="{0}" fill="{color}"/>"#,
+            join(&head)
+        )?;
+        writeln!(
+            out,
+            r#"  <text x="{0}" y="{1}" fill="{color}">{2}</text>"#,
+            number(label.0),
+            number(baseline(label.1)),
+            escape_xml(&edge.label())
+        )?;
+    }
+
+    for (node, rect) in graph.nodes.iter().zip(&layout.nodes) {
+        let (fill, border) = if graph.current == Some(node.address) {
+            (CURRENT_FILL, CURRENT_BORDER_WIDTH)
+        } else {
+            (BACKGROUND, BORDER_WIDTH)
+        };
+
+        writeln!(
+            out,
+            r#"  <rect x="{0}" y="{1}" width="{2}" height="{3}" fill="{4}" stroke="{5}" stroke-width="{border}"/>"#,
+            number(rect.x),
+            number(rect.y),
+            number(rect.width),
+            number(rect.height),
+            hex(fill),
+            hex(BORDER)
+        )?;
+
+        for (i, (text, line)) in node.lines().into_iter().enumerate() {
+            writeln!(
+                out,
+                r#"  <text x="{0}" y="{1}" fill="{2}">{3}</text>"#,
+                number(rect.x + PADDING),
+                number(baseline(rect.y + PADDING + LINE_HEIGHT * (i as f32 + 0.5))),
+                hex(text_color(text)),
+                escape_xml(&line)
+            )?;
+        }
+    }
+
+    writeln!(out, "</svg>")?;
+
+    Ok(())
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02X}{g:02X}{b:02X}")
+}
+
+fn join(points: &[Point]) -> String {
+    let points: Vec<String> = points
+        .iter()
+        .map(|(x, y)| format!("{0},{1}", number(*x), number(*y)))
+        .collect();
+    points.join(" ")
+}
+
+/// A coordinate rounded to a hundredth of a point, which is as precise as anything is drawn.
+fn number(value: f32) -> f32 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hex, write, CURRENT_FILL};
+    use crate::{
+        export::graph::{layout::Layout, Graph},
+        trace::tests::{tracer, CALL},
+    };
+
+    #[test]
+    fn call_graph() {
+        let mut tracer = tracer(CALL);
+        tracer.cfg.rename(0x100, Some("<f>"));
+        let graph = Graph::calls(&tracer);
+        let layout = Layout::new(&graph);
+
+        let mut out = Vec::new();
+        write(&graph, &layout, 2.0, &mut out).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+
+        assert!(svg.starts_with(&format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}""#,
+            layout.width * 2.0,
+            layout.height * 2.0
+        )));
+        assert_eq!(svg.matches("<polyline").count(), 1);
+        assert!(svg.contains(">0x0 (1)</text>"));
+        assert!(svg.contains(">sub_0</text>"));
+        assert!(svg.contains(">&lt;f&gt;</text>"));
+        assert_eq!(
+            svg.matches(&hex(CURRENT_FILL)).count(),
+            usize::from(graph.current.is_some())
+        );
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+}
@@ -0,0 +1,156 @@
+//! Traces in the text format of Tenet, which explores them in IDA.
+//!
+//! Every line is an executed instruction, given as the registers which changed since the line
+//! before it along with the instruction pointer, followed by the memory it accessed:
+//!
+//! ```text
+//! pc=0x8,acc=0x2,mr=0x9:02000000
+//! ```
+
+use crate::trace::{Error, Event, Tracer};
+use std::io::Write;
+
+/// How a trace is written out.
+pub struct Options {
+    /// The thread that is exported, as Tenet only follows a single one.
+    pub thread: u32,
+    /// What the instruction pointer is called, a register of the trace with the same name is
+    /// replaced by the address of every instruction.
+    ///
+    /// Register names are written in lowercase.
+    pub pc: String,
+    /// How many bytes each memory address holds.
+    pub cell: usize,
+}
+
+impl Options {
+    /// The first thread, with each address holding a whole memory value of the trace.
+    pub fn new(tracer: &Tracer) -> Options {
+        Options {
+            thread: tracer.thread_steps.keys().next().copied().unwrap_or(0),
+            pc: String::from("pc"),
+            cell: 8,
+        }
+    }
+}
+
+/// Writes the instructions of a thread with the registers and memory they changed.
+///
+/// Memory is shared between threads, so the reads of the exported thread see what the others
+/// wrote to it.
+pub fn write(tracer: &Tracer, options: &Options, out: &mut impl Write) -> Result<(), Error> {
+    let pc = options.pc.to_lowercase();
+    let cell = options.cell.clamp(1, 8);
+    let mut registers = tracer.initial_registers().clone();
+    let mut memory = tracer.initial_memory.clone();
+
+    // What every register was last written out as, nothing at first so that the first line has
+    // the whole initial state.
+    let mut last: Vec<Option<Vec<u8>>> = vec![None; registers.iter().count()];
+
+    for insn in &tracer.instructions {
+        let exported = insn.thread() == options.thread;
+        let mut fields = Vec::new();
+
+        if exported {
+            for (register, last) in registers.iter().zip(&mut last) {
+                let name = register.name().to_lowercase();
+
+                if name != pc && last.as_deref() != Some(register.value()) {
+                    fields.push(format!("{name}={0}", hex_value(register.value())));
+                    *last = Some(register.value().to_vec());
+                }
+            }
+
+            fields.push(format!("{pc}={0:#x}", insn.addr()));
+        }
+
+        for event in insn.events() {
+            match event {
+                Event::RegWrite(register, value) if exported => {
+                    registers.write(*register as usize, value.clone());
+                }
+                Event::MemRead(address) if exported => {
+                    let value = memory.get(address).copied().unwrap_or(0);
+                    fields.push(format!("mr={address:#x}:{0}", hex_bytes(value, cell)));
+                }
+                Event::MemWrite(address, value) => {
+                    memory.insert(*address, *value);
+
+                    if exported {
+                        fields.push(format!("mw={address:#x}:{0}", hex_bytes(*value, cell)));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if exported {
+            writeln!(out, "{0}", fields.join(","))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A little-endian register value as a hexadecimal number.
+fn hex_value(bytes: &[u8]) -> String {
+    let digits: String = bytes
+        .iter()
+        .rev()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    match digits.trim_start_matches('0') {
+        "" => String::from("0x0"),
+        digits => format!("0x{digits}"),
+    }
+}
+
+/// The first bytes of a memory value in memory order, little-endian.
+fn hex_bytes(value: u64, cell: usize) -> String {
+    value.to_le_bytes()[..cell]
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write, Options};
+    use crate::trace::tests::tracer;
+
+    const TRACE: &str = "\
+rax=0x1,rip=0x10,mr=0x100:05
+rax=0x2,rip=0x11,mw=0x108:ff
+rip=0x12,mr=0x108:ff
+";
+
+    fn tenet(cell: usize) -> String {
+        let options = Options {
+            thread: 0,
+            pc: String::from("RIP"),
+            cell,
+        };
+        let mut out = Vec::new();
+        write(&tracer(TRACE), &options, &mut out).unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(
+            tenet(1),
+            "rax=0x1,rip=0x10,mr=0x100:05\nrax=0x2,rip=0x11,mw=0x108:FF\nrip=0x12,mr=0x108:FF\n"
+        );
+    }
+
+    #[test]
+    fn cells() {
+        assert_eq!(
+            tenet(2).lines().nth(1),
+            Some("rax=0x2,rip=0x11,mw=0x108:FF00")
+        );
+    }
+}
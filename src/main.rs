@@ -2,6 +2,9 @@
 
 use eframe::egui::{Style, Visuals};
 
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
+mod export;
 mod trace;
 mod ui;
 
@@ -10,6 +13,12 @@ mod ui;
 fn main() -> eframe::Result<()> {
     env_logger::init();
 
+    // Given a command, traceview runs it headlessly instead of opening the interface.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(if cli::run(&args) { 0 } else { 1 });
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([400.0, 300.0])
@@ -1,64 +1,460 @@
-use self::{cfg::ControlFlowGraph, register::Registers};
-use serde::Deserialize;
+use self::{
+    cfg::ControlFlowGraph, instructions::Instructions, register::Registers, thread::Thread,
+};
+pub use arch::architectures;
+pub use binary::Binary;
+pub use cfg::Changes;
+pub use defuse::{DefUse, MAX_DEF_USE_DEPTH};
+pub use profile::Profile;
+use serde::{Deserialize, Serialize};
+pub use slice::Slice;
 use std::collections::BTreeMap;
+use std::sync::OnceLock;
+pub use symbols::Symbols;
+pub use taint::{Location, Taint};
 use thiserror::Error;
 
+mod arch;
+mod binary;
 mod block;
+mod call;
 mod cfg;
+mod defuse;
+mod dominators;
+mod elf;
 mod function;
+mod graphviz;
+mod history;
+mod import;
+#[cfg(not(target_arch = "wasm32"))]
+mod index;
+mod instructions;
+mod loops;
+mod profile;
 mod register;
+mod slice;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod stream;
+mod symbols;
+mod taint;
+mod thread;
 
+/// How many steps apart the register and memory checkpoints are taken.
+pub const CHECKPOINT_INTERVAL: usize = 1024;
+
+/// The name of the graph of calls between functions.
+pub const CALL_GRAPH: &str = "Call graph";
+
+/// Which graph a [`GraphFile`] is, as function names can be changed and need not be unique.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum GraphId {
+    /// The graph of calls between functions.
+    Calls,
+    /// The control flow graph of the function starting at an address.
+    Function(u64),
+    /// The def-use chains of the instruction at a step, followed some levels deep.
+    DefUse { step: usize, depth: usize },
+}
+
+/// The DOT source of a graph, written out for graphviz to render.
+pub struct DotFile {
+    pub path: String,
+}
+
+impl DotFile {
+    /// Renders the graph with graphviz, which takes a while for large functions.
+    pub fn render(&self) -> Result<GraphFile, Error> {
+        let out = format!("{0}.jpeg", self.path);
+        let nodes = graphviz::render(&self.path, &out)?;
+
+        Ok(GraphFile { path: out, nodes })
+    }
+}
+
+/// A rendered graph.
 pub struct GraphFile {
-    pub address: u64,
-    pub name: String,
     pub path: String,
+    /// Where the nodes are in the rendered image.
+    pub nodes: Vec<GraphNode>,
+}
+
+/// A node of a rendered graph, positioned by its center with everything given as fractions of
+/// the image size, measured from the bottom left corner.
+#[derive(Clone, Copy, Debug)]
+pub struct GraphNode {
+    /// The block or function the node stands for.
+    pub address: u64,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl GraphNode {
+    /// Whether a point, given the same way as the node, is inside of it.
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        (x - self.x).abs() <= self.width / 2.0 && (y - self.y).abs() <= self.height / 2.0
+    }
 }
 
 pub struct Tracer {
     pub step: usize,
-    pub registers: Registers,
+    /// The registers and call stack of every thread which has started so far.
+    pub threads: BTreeMap<u32, Thread>,
     pub memory: BTreeMap<u64, u64>,
-    pub graphs: Vec<GraphFile>,
-    pub instructions: Vec<Instruction>,
+    pub instructions: Instructions,
+    /// Every step at which a given address was executed, in ascending order.
+    pub steps: BTreeMap<u64, Vec<usize>>,
+    /// Every step at which a given thread executed, in ascending order.
+    pub thread_steps: BTreeMap<u32, Vec<usize>>,
+    /// The calls and jumps that went to a given address.
+    pub xrefs: BTreeMap<u64, Vec<Xref>>,
+    /// The threads as they were every [`CHECKPOINT_INTERVAL`] steps, and how memory changed
+    /// in between.
+    pub checkpoints: Vec<Checkpoint>,
+    /// The memory before the first instruction.
+    pub initial_memory: BTreeMap<u64, u64>,
+    pub cfg: ControlFlowGraph,
+    /// The registers every thread starts out with.
+    registers: Registers,
+    /// The threads and memory after the last instruction, which the checkpoints of appended
+    /// instructions are taken from. Traces loaded from an index replay them once extended.
+    end: Option<State>,
+    /// Every access to every location, for following def-use chains.
+    accesses: OnceLock<defuse::Accesses>,
+}
+
+/// A call or jump from one address to another.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Xref {
+    /// The address of the call or jump instruction.
+    pub from: u64,
+    pub kind: JumpKind,
+    /// Every step at which it was taken, in ascending order.
+    pub steps: Vec<usize>,
+}
+
+/// The threads at the start of an interval of [`CHECKPOINT_INTERVAL`] steps, and the memory
+/// cells written during it, used to seek without replaying the whole trace.
+///
+/// Memory is kept as what changed rather than snapshots, so seeking goes through every
+/// interval in between, undoing or redoing their writes.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Checkpoint {
+    pub threads: BTreeMap<u32, Thread>,
+    /// What the cells held at the start of the interval, `None` for those not there yet.
+    pub before: BTreeMap<u64, Option<u64>>,
+    /// What the cells held at the end of the interval.
+    pub after: BTreeMap<u64, u64>,
+}
+
+/// The threads and memory as of some step.
+struct State {
+    threads: BTreeMap<u32, Thread>,
+    memory: BTreeMap<u64, u64>,
 }
 
 impl Tracer {
-    pub fn new(trace: TraceFile) -> Result<Tracer, Error> {
-        let registers = Registers::new(&trace.info.registers);
-        let memory: BTreeMap<u64, u64> = BTreeMap::from_iter(trace.info.memory.into_iter());
+    /// Builds a tracer from a whole trace.
+    ///
+    /// When lenient, anomalies from a trace that was cut short, such as it ending on a jump or
+    /// returning without a call, are marked in the graphs and recorded as warnings instead of
+    /// failing the whole load.
+    ///
+    /// Functions and addresses are named after the given symbols.
+    ///
+    /// The graphs are generated as they are needed, see [`Tracer::dot`].
+    pub fn new(trace: TraceFile, lenient: bool, symbols: Symbols) -> Result<Tracer, Error> {
+        if trace.instructions.is_empty() {
+            return Err(Error::MissingInstruction);
+        }
 
-        let first = trace.instructions.first().unwrap();
-        let mut graph = ControlFlowGraph::new(first.addr);
-        graph.construct(&trace.instructions)?;
-        let graphs = graph.gen_graphs()?;
+        let mut tracer = Tracer::empty(trace.info, lenient);
 
-        Ok(Self {
+        tracer.cfg.set_symbols(symbols);
+        tracer.extend(trace.instructions)?;
+        tracer.cfg.finish()?;
+
+        Ok(tracer)
+    }
+
+    /// Writes out the DOT source of a graph as the trace is now.
+    pub fn dot(&self, id: GraphId) -> Result<DotFile, Error> {
+        match id {
+            GraphId::Calls => self.cfg.gen_call_graph(),
+            GraphId::Function(start) => self.cfg.gen_graph(start),
+            GraphId::DefUse { step, depth } => self.def_use_graph(step, depth),
+        }
+    }
+
+    /// Creates a tracer without any instructions, for traces which arrive over time.
+    pub fn empty(info: ArchInfo, lenient: bool) -> Tracer {
+        let memory: BTreeMap<u64, u64> = BTreeMap::from_iter(info.memory);
+
+        Self {
             step: 0,
-            registers,
+            threads: BTreeMap::new(),
+            checkpoints: vec![Checkpoint {
+                threads: BTreeMap::new(),
+                before: BTreeMap::new(),
+                after: BTreeMap::new(),
+            }],
+            initial_memory: memory.clone(),
+            end: Some(State {
+                threads: BTreeMap::new(),
+                memory: memory.clone(),
+            }),
+            accesses: OnceLock::new(),
             memory,
-            graphs,
-            instructions: trace.instructions,
+            instructions: Instructions::default(),
+            steps: BTreeMap::new(),
+            thread_steps: BTreeMap::new(),
+            xrefs: BTreeMap::new(),
+            cfg: ControlFlowGraph::new(lenient),
+            registers: Registers::new(&info.registers),
+        }
+    }
+
+    /// Appends instructions to the end of the trace, growing the control flow graph with them
+    /// and returning what in it changed, which is whose graphs are out of date.
+    pub fn extend(&mut self, insns: Vec<Instruction>) -> Result<Changes, Error> {
+        let start = self.instructions.len();
+        self.instructions.extend(insns);
+        self.index(start);
+        self.accesses = OnceLock::new();
+
+        for insn in self.instructions.iter().skip(start) {
+            self.cfg.push(insn)?;
+        }
+
+        Ok(self.cfg.take_changes())
+    }
+
+    /// Called once no more instructions will arrive, returning what that changed.
+    pub fn finish(&mut self) -> Result<Changes, Error> {
+        self.cfg.finish()?;
+
+        Ok(self.cfg.take_changes())
+    }
+
+    /// Opens a trace from disk, reusing its sidecar index when the trace has not changed since
+    /// it was written, and writing a fresh one otherwise.
+    ///
+    /// The names saved in the project sidecar of the trace are applied, see [`Symbols::load`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &std::path::Path, lenient: bool) -> Result<Tracer, Error> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: the trace is only ever read, and is not expected to change while it is open.
+        let bytes = unsafe { memmap2::Mmap::map(&file)? };
+        let hash = index::content_hash(&bytes);
+        let sidecar = index::sidecar_path(path);
+
+        let cached = match index::TraceIndex::load(&sidecar, hash) {
+            // An index built leniently with warnings would not have been built at all otherwise.
+            Ok(Some(cached)) if lenient || cached.cfg.warnings().is_empty() => Some(cached),
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!("ignoring unreadable index {}: {e}", sidecar.display());
+                None
+            }
+        };
+
+        let symbols = Symbols::load(path)?.unwrap_or_default();
+
+        if let Some(cached) = cached {
+            let mut tracer = Tracer::from_index(cached)?;
+
+            tracer.cfg.set_symbols(symbols);
+            tracer.cfg.take_changes();
+
+            return Ok(tracer);
+        }
+
+        let tracer = Tracer::new(TraceFile::parse(&bytes)?, lenient, symbols)?;
+
+        if let Err(e) = index::TraceIndex::write(&sidecar, hash, &tracer) {
+            log::warn!("failed to write index {}: {e}", sidecar.display());
+        }
+
+        Ok(tracer)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn from_index(cached: index::TraceIndex) -> Result<Tracer, Error> {
+        let threads = cached
+            .checkpoints
+            .first()
+            .ok_or(Error::MissingCheckpoint)?
+            .threads
+            .clone();
+
+        Ok(Self {
+            step: 0,
+            threads,
+            memory: cached.initial_memory.clone(),
+            instructions: cached.instructions,
+            steps: cached.steps,
+            thread_steps: cached.thread_steps,
+            xrefs: cached.xrefs,
+            checkpoints: cached.checkpoints,
+            initial_memory: cached.initial_memory,
+            cfg: cached.cfg,
+            registers: cached.registers,
+            end: None,
+            accesses: OnceLock::new(),
         })
     }
 
-    pub fn step_forward(&mut self) {
-        let events = &self.instructions[self.step].events;
+    /// Records the steps each address and thread executed at along with the cross references,
+    /// and takes the checkpoints for every instruction from the given step onward, without
+    /// moving the tracer.
+    fn index(&mut self, start: usize) {
+        for (step, insn) in self.instructions.iter().enumerate().skip(start) {
+            self.steps.entry(insn.addr).or_default().push(step);
+
+            let thread_steps = self.thread_steps.entry(insn.thread()).or_default();
+
+            // Where a jump went is the next instruction of the same thread. Returns go back to
+            // wherever they were called from, so they are not references.
+            if let Some(&previous) = thread_steps.last() {
+                let jump = &self.instructions[previous];
+
+                if let Some(kind) = jump.kind.filter(|kind| *kind != JumpKind::Return) {
+                    let xrefs = self.xrefs.entry(insn.addr).or_default();
+
+                    match xrefs
+                        .iter_mut()
+                        .find(|xref| xref.from == jump.addr && xref.kind == kind)
+                    {
+                        Some(xref) => xref.steps.push(previous),
+                        None => xrefs.push(Xref {
+                            from: jump.addr,
+                            kind,
+                            steps: vec![previous],
+                        }),
+                    }
+                }
+            }
+
+            thread_steps.push(step);
+        }
 
-        for event in events {
-            match event {
-                Event::RegWrite(reg, val) => {
-                    self.registers.write(*reg as usize, val.clone());
+        let (
+            State {
+                mut threads,
+                mut memory,
+            },
+            start,
+        ) = match self.end.take() {
+            Some(end) => (end, start),
+            None => {
+                // The last interval is replayed from its start, as the memory is not kept.
+                let last = self.checkpoints.len() - 1;
+                let mut memory = self.initial_memory.clone();
+                for checkpoint in &self.checkpoints[..last] {
+                    memory.extend(&checkpoint.after);
                 }
-                Event::MemWrite(addr, val) => {
-                    self.memory
-                        .entry(*addr)
-                        .and_modify(|v| *v = *val)
-                        .or_insert(*val);
+
+                let checkpoint = &mut self.checkpoints[last];
+                checkpoint.before.clear();
+                checkpoint.after.clear();
+
+                let threads = checkpoint.threads.clone();
+                (State { threads, memory }, last * CHECKPOINT_INTERVAL)
+            }
+        };
+
+        for step in start..self.instructions.len() {
+            if step == self.checkpoints.len() * CHECKPOINT_INTERVAL {
+                self.checkpoints.push(Checkpoint {
+                    threads: threads.clone(),
+                    before: BTreeMap::new(),
+                    after: BTreeMap::new(),
+                });
+            }
+
+            let insn = &self.instructions[step];
+            let checkpoint = self.checkpoints.last_mut().unwrap();
+
+            for event in insn.events() {
+                if let Event::MemWrite(address, value) = event {
+                    checkpoint
+                        .before
+                        .entry(*address)
+                        .or_insert_with(|| memory.get(address).copied());
+                    checkpoint.after.insert(*address, *value);
+                }
+            }
+
+            apply(&mut threads, &mut memory, &self.registers, insn, step);
+        }
+
+        self.end = Some(State { threads, memory });
+    }
+
+    /// The thread which executes the instruction at the current step.
+    pub fn thread(&self) -> u32 {
+        self.instructions[self.step].thread()
+    }
+
+    /// The registers of a thread, which are the initial ones if it has not started yet.
+    pub fn registers(&self, thread: u32) -> &Registers {
+        self.threads
+            .get(&thread)
+            .map_or(&self.registers, |thread| &thread.registers)
+    }
+
+    /// The registers every thread starts out with.
+    pub fn initial_registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    /// The registers of a thread right before each of the given steps, which are in ascending
+    /// order, replayed from the checkpoint before the first of them.
+    pub fn registers_at(&self, thread: u32, steps: &[usize]) -> Vec<Registers> {
+        let Some(first) = steps.first() else {
+            return Vec::new();
+        };
+
+        let checkpoint = (first / CHECKPOINT_INTERVAL).min(self.checkpoints.len() - 1);
+        let mut registers = self.checkpoints[checkpoint]
+            .threads
+            .get(&thread)
+            .map_or(&self.registers, |thread| &thread.registers)
+            .clone();
+        let mut step = checkpoint * CHECKPOINT_INTERVAL;
+        let mut history = Vec::with_capacity(steps.len());
+
+        for target in steps {
+            while step < *target {
+                let insn = &self.instructions[step];
+
+                if insn.thread() == thread {
+                    for event in insn.events() {
+                        if let Event::RegWrite(reg, val) = event {
+                            registers.write(*reg as usize, val.clone());
+                        }
+                    }
                 }
-                _ => {}
+
+                step += 1;
             }
+
+            history.push(registers.clone());
         }
 
+        history
+    }
+
+    pub fn step_forward(&mut self) {
+        apply(
+            &mut self.threads,
+            &mut self.memory,
+            &self.registers,
+            &self.instructions[self.step],
+            self.step,
+        );
+
         if self.step < self.instructions.len() {
             self.step += 1;
         }
@@ -69,27 +465,88 @@ impl Tracer {
             return;
         }
 
-        let events = &self.instructions[self.step].events;
+        self.seek(self.step - 1);
+    }
+
+    /// Moves forward to the next instruction of the given thread, skipping over the others.
+    pub fn step_forward_in(&mut self, thread: u32) {
+        let Some(steps) = self.thread_steps.get(&thread) else {
+            return;
+        };
+
+        if let Some(step) = steps.get(steps.partition_point(|step| *step <= self.step)) {
+            self.seek(*step);
+        }
+    }
+
+    /// Moves backward to the previous instruction of the given thread, skipping over the others.
+    pub fn step_backward_in(&mut self, thread: u32) {
+        let Some(steps) = self.thread_steps.get(&thread) else {
+            return;
+        };
 
-        for event in events.iter().rev() {
-            match event {
-                Event::RegWrite(reg, val) => {
-                    self.registers.write(*reg as usize, val.clone());
+        let idx = steps.partition_point(|step| *step < self.step);
+        if idx > 0 {
+            self.seek(steps[idx - 1]);
+        }
+    }
+
+    /// Moves the tracer to the given step, starting over from the nearest checkpoint when the
+    /// step is behind the current one or past the next checkpoint.
+    pub fn seek(&mut self, step: usize) {
+        let step = step.min(self.instructions.len().saturating_sub(1));
+        let checkpoint = step / CHECKPOINT_INTERVAL;
+        // The end of a trace that fills its last interval has no checkpoint after it.
+        let current = (self.step / CHECKPOINT_INTERVAL).min(self.checkpoints.len() - 1);
+
+        if step < self.step || checkpoint > current {
+            if checkpoint <= current {
+                for interval in self.checkpoints[checkpoint..=current].iter().rev() {
+                    for (address, value) in &interval.before {
+                        match value {
+                            Some(value) => self.memory.insert(*address, *value),
+                            None => self.memory.remove(address),
+                        };
+                    }
                 }
-                Event::MemWrite(addr, val) => {
-                    self.memory
-                        .entry(*addr)
-                        .and_modify(|v| *v = *val)
-                        .or_insert(*val);
+            } else {
+                for interval in &self.checkpoints[current..checkpoint] {
+                    self.memory.extend(&interval.after);
                 }
-                _ => {}
             }
+
+            self.threads = self.checkpoints[checkpoint].threads.clone();
+            self.step = checkpoint * CHECKPOINT_INTERVAL;
         }
 
-        self.step -= 1;
+        while self.step < step {
+            apply(
+                &mut self.threads,
+                &mut self.memory,
+                &self.registers,
+                &self.instructions[self.step],
+                self.step,
+            );
+            self.step += 1;
+        }
     }
 }
 
+/// Applies an instruction executed at the given step to its thread, starting the thread with
+/// the initial registers if this is its first instruction.
+fn apply(
+    threads: &mut BTreeMap<u32, Thread>,
+    memory: &mut BTreeMap<u64, u64>,
+    registers: &Registers,
+    insn: &Instruction,
+    step: usize,
+) {
+    threads
+        .entry(insn.thread())
+        .or_insert_with(|| Thread::new(registers.clone(), insn.addr, step))
+        .execute(insn, step, memory);
+}
+
 #[derive(Deserialize)]
 pub struct TraceFile {
     info: ArchInfo,
@@ -108,18 +565,40 @@ pub struct RegisterInfo {
     register: u32,
     full_register: Option<u32>,
     size: u16,
+    /// The value before the trace, little-endian, which is zero unless an imported trace gave
+    /// it. It is not read from traces, which would change the records of live traces.
+    #[serde(skip)]
+    value: Option<Vec<u8>>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Instruction {
     addr: u64,
     pub insn: String,
     size: Option<u16>,
     kind: Option<JumpKind>,
     events: Vec<Event>,
+    /// The thread which executed the instruction, traces of single threaded programs leave it
+    /// out.
+    thread: Option<u32>,
+}
+
+impl Instruction {
+    pub fn addr(&self) -> u64 {
+        self.addr
+    }
+
+    /// The thread which executed the instruction, the first thread if there is only one.
+    pub fn thread(&self) -> u32 {
+        self.thread.unwrap_or(0)
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub enum Event {
     RegRead(u32),
     RegWrite(u32, Box<[u8]>),
@@ -127,7 +606,7 @@ pub enum Event {
     MemWrite(u64, u64),
 }
 
-#[derive(Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum JumpKind {
     Call,
     Return,
@@ -138,12 +617,15 @@ pub enum JumpKind {
 /// All of the error types associated with the library.
 #[derive(Error, Debug)]
 pub enum Error {
-    /// The block that was queried for in the function does not exist.
-    #[error("The block that was attempted to be found does not exist.")]
-    MissingBlock,
+    /// The function that was queried for does not exist in the control flow graph.
+    #[error("The function that was attempted to be found does not exist.")]
+    MissingFunction,
     /// The current block is somehow missing from the underlying map.
     #[error("The current basic block does not exist.")]
     MissingCurrentBlock,
+    /// A return was taken without there being a call to return to.
+    #[error("A return was taken without a matching call.")]
+    UnmatchedReturn,
     /// The current function is somehow missing from the underlying map.
     #[error("The current function does not exist inside the control flow graph.")]
     MissingCurrentFunction,
@@ -153,4 +635,185 @@ pub enum Error {
     MissingInstruction,
     #[error("There was an error loading an image.")]
     Image(#[from] image::error::ImageError),
+    /// The trace file is not valid JSON or does not follow the trace format.
+    #[error("The trace could not be parsed.")]
+    Json(#[from] serde_json::Error),
+    /// The project sidecar holding the names given in the trace is not valid JSON.
+    #[error("The project file (.tvproj) next to the trace could not be parsed.")]
+    Project(#[source] serde_json::Error),
+    /// The sidecar index could not be encoded or decoded.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[error("The trace index could not be encoded or decoded.")]
+    Index(#[from] bincode::Error),
+    /// The sidecar index was written by an incompatible version or is truncated.
+    #[error("The trace index is malformed.")]
+    MalformedIndex,
+    /// A live trace did not start with the architecture info or was not in a known format.
+    #[error("The live trace stream is malformed.")]
+    MalformedStream,
+    /// There is no checkpoint to restore the machine state from.
+    #[error("The trace index has no initial checkpoint.")]
+    MissingCheckpoint,
+    /// The symbols to import were in neither an ELF nor a text file, or there were none.
+    #[error("No symbols could be found in the file.")]
+    MalformedSymbols,
+    /// An ELF file was cut short or its headers point outside of it.
+    #[error("The ELF file is malformed.")]
+    MalformedElf,
+    /// The trace is neither JSON nor a QEMU log or Tenet trace.
+    #[error("The trace is in none of the known formats.")]
+    UnknownFormat,
+    /// A QEMU log executed a block it never disassembled, or a line of it could not be parsed.
+    #[error("The QEMU log is malformed, it needs to be logged with -d exec,cpu,in_asm.")]
+    MalformedQemuLog,
+    /// A Tenet trace has a line without the instruction pointer, or a malformed value.
+    #[error("The Tenet trace is malformed.")]
+    MalformedTenet,
+    /// The font the text of images is drawn with is not among the fonts of the interface.
+    #[error("The font to draw text with could not be loaded.")]
+    MissingFont,
+    /// A graph would be drawn as an image with more pixels than can be kept in memory.
+    #[error("The graph is too large to be drawn at this scale.")]
+    ImageTooLarge,
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{TraceFile, Tracer, CHECKPOINT_INTERVAL};
+
+    /// A Tenet trace going around a loop, counting in a register and writing the count to one
+    /// of sixteen memory cells and to a cell first written every hundred steps, which takes a
+    /// checkpoint every [`CHECKPOINT_INTERVAL`] steps.
+    pub fn counting(steps: usize) -> String {
+        (0..steps)
+            .map(|step| {
+                let cell = 0x100 + (step % 16) * 8;
+                let value: String = (step as u64)
+                    .to_le_bytes()
+                    .iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect();
+
+                let fresh = 0x1000 + step / 100 * 8;
+
+                format!(
+                    "rax={step:#x},rip={0:#x},mr={cell:#x}:00,mw={cell:#x}:{value},\
+                     mw={fresh:#x}:{value}\n",
+                    0x10 + step % 4
+                )
+            })
+            .collect()
+    }
+
+    /// A Tenet trace copying 0x100 to 0x108 and then on to 0x118, after which 0x108 is
+    /// overwritten from 0x300 and 0x118 read.
+    pub const COPIES: &str = "\
+rip=0x10,mr=0x100:01,mw=0x108:01
+rip=0x11,mr=0x200:02,mw=0x110:02
+rip=0x12,mr=0x108:01,mw=0x118:01
+rip=0x13,mr=0x300:03,mw=0x108:03
+rip=0x14,mr=0x118:01
+";
+
+    /// A Tenet trace where 0x0 calls 0x100, which returns right after the call.
+    pub const CALL: &str = "rip=0x0\nrip=0x100\nrip=0x101\nrip=0x1\nrip=0x2\n";
+
+    pub fn tracer(text: &str) -> Tracer {
+        let trace = TraceFile::parse(text.as_bytes()).unwrap();
+        Tracer::new(trace, true, Default::default()).unwrap()
+    }
+
+    /// The registers of the only thread and the memory.
+    type Snapshot = (Vec<Vec<u8>>, Vec<(u64, u64)>);
+
+    /// The registers of the only thread and the memory, as they are at the current step.
+    fn state(tracer: &Tracer) -> Snapshot {
+        let registers = tracer
+            .registers(0)
+            .iter()
+            .map(|register| register.value().to_vec())
+            .collect();
+        let memory = tracer.memory.iter().map(|(a, v)| (*a, *v)).collect();
+
+        (registers, memory)
+    }
+
+    /// Every state stepping through the whole trace goes through.
+    fn stepped(text: &str) -> Vec<Snapshot> {
+        let mut tracer = tracer(text);
+        let mut states = vec![state(&tracer)];
+        for _ in 1..tracer.instructions.len() {
+            tracer.step_forward();
+            states.push(state(&tracer));
+        }
+
+        states
+    }
+
+    /// Seeks back and forth across checkpoints, checking against stepping there one at a time.
+    fn check_seeks(tracer: &mut Tracer, states: &[Snapshot]) {
+        let len = states.len();
+        let targets = [
+            len - 1,
+            0,
+            CHECKPOINT_INTERVAL,
+            CHECKPOINT_INTERVAL - 1,
+            2 * CHECKPOINT_INTERVAL + 7,
+            5,
+            len / 2,
+            len - 2,
+            CHECKPOINT_INTERVAL + 1,
+        ];
+
+        for step in targets {
+            tracer.seek(step);
+            assert_eq!(tracer.step, step);
+            assert!(state(tracer) == states[step], "seeking to {step}");
+        }
+    }
+
+    #[test]
+    fn seek_matches_stepping() {
+        let text = counting(CHECKPOINT_INTERVAL * 3 + 10);
+        let states = stepped(&text);
+
+        check_seeks(&mut tracer(&text), &states);
+    }
+
+    #[test]
+    fn seek_after_extending() {
+        let text = counting(CHECKPOINT_INTERVAL * 3 + 10);
+        let states = stepped(&text);
+
+        // Arriving in pieces, as live traces do, takes the same checkpoints.
+        let mut instructions = TraceFile::parse(text.as_bytes()).unwrap();
+        let mut rest = instructions.instructions.split_off(100);
+        let mut tracer = Tracer::empty(instructions.info, true);
+        tracer.extend(instructions.instructions).unwrap();
+        while !rest.is_empty() {
+            let after = rest.split_off(rest.len().min(CHECKPOINT_INTERVAL - 3));
+            tracer.extend(rest).unwrap();
+            rest = after;
+        }
+
+        assert_eq!(tracer.checkpoints.len(), 4);
+        check_seeks(&mut tracer, &states);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn seek_from_index() {
+        let text = counting(CHECKPOINT_INTERVAL * 3 + 10);
+        let states = stepped(&text);
+
+        let path = std::env::temp_dir().join(format!("traceview-seek-{0}", std::process::id()));
+        let sidecar = super::index::sidecar_path(&path);
+        std::fs::write(&path, &text).unwrap();
+        Tracer::load(&path, true).unwrap();
+        let mut loaded = Tracer::load(&path, true).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&sidecar).unwrap();
+
+        check_seeks(&mut loaded, &states);
+    }
 }
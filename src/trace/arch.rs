@@ -0,0 +1,35 @@
+use super::JumpKind;
+
+mod rscpu;
+
+/// An instruction decoded from a binary.
+pub struct Decoded {
+    pub size: u64,
+    /// The instruction as it would appear in a trace.
+    pub text: String,
+    pub kind: Option<JumpKind>,
+    /// Where a jump or call goes, when it is known without executing it.
+    pub target: Option<u64>,
+    /// Whether execution can carry on to the next instruction, which it cannot after an
+    /// unconditional jump, a return or halting.
+    pub falls_through: bool,
+}
+
+/// Decodes the instructions of a processor, so that code which never executed can be shown.
+pub trait Architecture {
+    fn name(&self) -> &'static str;
+
+    /// How many bytes of the binary each address holds, one for byte addressed processors.
+    fn word_size(&self) -> u64 {
+        1
+    }
+
+    /// Decodes the instruction at the start of the bytes, which is at the given address, or
+    /// nothing if they are not a valid instruction.
+    fn decode(&self, address: u64, bytes: &[u8]) -> Option<Decoded>;
+}
+
+/// Every architecture that binaries can be decoded as.
+pub fn architectures() -> Vec<Box<dyn Architecture>> {
+    vec![Box::new(rscpu::RelativelySimpleCpu)]
+}
@@ -0,0 +1,67 @@
+use super::{Architecture, Decoded};
+use crate::trace::JumpKind;
+use byteorder::{ByteOrder, LittleEndian};
+
+/// The Relatively Simple CPU as traced by ctrlflow, where memory is addressed by 32-bit little
+/// endian words and every instruction is an opcode word followed by an address word for those
+/// which take one.
+pub struct RelativelySimpleCpu;
+
+/// The mnemonic of every opcode, whether it takes an address and how it jumps.
+const OPCODES: [(&str, bool, Option<JumpKind>); 18] = [
+    ("HALT", false, None),
+    ("LDAC", true, None),
+    ("STAC", true, None),
+    ("MVAC", false, None),
+    ("MOVR", false, None),
+    ("JMP", true, Some(JumpKind::Unconditional)),
+    ("JMPZ", true, Some(JumpKind::Conditional)),
+    ("OUT", false, None),
+    ("SUB", false, None),
+    ("ADD", false, None),
+    ("INC", false, None),
+    ("CLAC", false, None),
+    ("AND", false, None),
+    ("OR", false, None),
+    ("ASHR", false, None),
+    ("NOT", false, None),
+    ("CALL", true, Some(JumpKind::Call)),
+    ("RET", false, Some(JumpKind::Return)),
+];
+
+impl Architecture for RelativelySimpleCpu {
+    fn name(&self) -> &'static str {
+        "Relatively Simple CPU"
+    }
+
+    fn word_size(&self) -> u64 {
+        4
+    }
+
+    fn decode(&self, _address: u64, bytes: &[u8]) -> Option<Decoded> {
+        let word = |i: usize| bytes.get(i * 4..i * 4 + 4).map(LittleEndian::read_u32);
+        let (mnemonic, takes_address, kind) = OPCODES.get(word(0)? as usize)?;
+
+        let (size, text, target) = if *takes_address {
+            let address = word(1)?;
+            let target = kind.is_some().then_some(address as u64);
+
+            (2, format!("{mnemonic}   {address}"), target)
+        } else {
+            (1, mnemonic.to_string(), None)
+        };
+
+        let falls_through = !matches!(
+            (*mnemonic, kind),
+            ("HALT", _) | (_, Some(JumpKind::Unconditional | JumpKind::Return))
+        );
+
+        Some(Decoded {
+            size,
+            text,
+            kind: *kind,
+            target,
+            falls_through,
+        })
+    }
+}
@@ -0,0 +1,197 @@
+use super::{
+    arch::{Architecture, Decoded},
+    elf::Elf,
+    function::Function,
+    Error, JumpKind,
+};
+use std::collections::BTreeMap;
+
+/// How many instructions an unexecuted block is decoded up to, as code that never ran may just
+/// as well be data.
+const MAX_INSTRUCTIONS: usize = 64;
+
+/// The code of the traced binary, decoded to find what never executed.
+pub struct Binary {
+    /// The bytes of every executable region, by their starting address.
+    ///
+    /// Each address holds as many bytes as a word of the architecture.
+    regions: BTreeMap<u64, Vec<u8>>,
+    arch: Box<dyn Architecture>,
+}
+
+/// A block of code that never executed, decoded from the binary.
+pub struct StaticBlock {
+    pub instructions: Vec<(u64, String)>,
+}
+
+/// What of a function never executed, the blocks along with the edges to them and out of them.
+#[derive(Default)]
+pub struct Unexecuted {
+    pub blocks: BTreeMap<u64, StaticBlock>,
+    /// The start of the block each edge leaves and enters, either of which may have executed.
+    pub edges: Vec<(u64, u64)>,
+}
+
+impl Binary {
+    /// Loads the executable segments of an ELF file, or a raw binary in its entirety, at the
+    /// given address, which ELF segments are offset by.
+    ///
+    /// The addresses of ELF segments are in bytes, and are converted to the words of the
+    /// architecture.
+    pub fn load(bytes: &[u8], base: u64, arch: Box<dyn Architecture>) -> Result<Binary, Error> {
+        let mut regions = BTreeMap::new();
+
+        if bytes.starts_with(b"\x7fELF") {
+            let elf = Elf::parse(bytes)?;
+
+            for segment in elf.segments()?.iter().filter(|segment| segment.executable) {
+                let code = elf.get(segment.offset, segment.size)?;
+                let address = segment.address / arch.word_size();
+                regions.insert(base.wrapping_add(address), code.to_vec());
+            }
+        } else {
+            regions.insert(base, bytes.to_vec());
+        }
+
+        Ok(Binary { regions, arch })
+    }
+
+    pub fn arch(&self) -> &dyn Architecture {
+        self.arch.as_ref()
+    }
+
+    /// Decodes the instruction at the given address, if it is in the binary.
+    pub fn decode(&self, address: u64) -> Option<Decoded> {
+        let (start, bytes) = self.regions.range(..=address).next_back()?;
+        let offset = (address - start).checked_mul(self.arch.word_size())?;
+        let offset = usize::try_from(offset).ok()?;
+
+        self.arch.decode(address, bytes.get(offset..)?)
+    }
+
+    /// Finds the successors of the jumps ending the executed blocks of a function that were
+    /// never taken, and decodes the blocks they lead to.
+    pub fn unexecuted(&self, function: &Function) -> Unexecuted {
+        let mut unexecuted = Unexecuted::default();
+
+        for (start, block) in &function.blocks {
+            let Some((&last, _)) = block.block.last_key_value() else {
+                continue;
+            };
+            let Some(jump) = self.decode(last) else {
+                continue;
+            };
+
+            let mut successors = Vec::new();
+            match jump.kind {
+                Some(JumpKind::Conditional) => {
+                    successors.extend(jump.target);
+                    successors.push(last + jump.size);
+                }
+                Some(JumpKind::Unconditional) => successors.extend(jump.target),
+                _ => continue,
+            }
+
+            for successor in successors {
+                if block.edges().any(|(_, edge, _)| *edge == successor) {
+                    continue;
+                }
+
+                unexecuted.edges.push((*start, successor));
+
+                if !function.blocks.contains_key(&successor)
+                    && !unexecuted.blocks.contains_key(&successor)
+                {
+                    let (static_block, next) = self.decode_block(function, successor);
+
+                    unexecuted.blocks.insert(successor, static_block);
+                    unexecuted.edges.extend(next.map(|next| (successor, next)));
+                }
+            }
+        }
+
+        unexecuted
+    }
+
+    /// Decodes a block that never executed up to the first jump, or until it runs into code of
+    /// the function that did, returning the executed block it continues or jumps into if it
+    /// does.
+    fn decode_block(&self, function: &Function, start: u64) -> (StaticBlock, Option<u64>) {
+        let mut instructions = Vec::new();
+        let mut address = start;
+
+        while instructions.len() < MAX_INSTRUCTIONS {
+            let Some(decoded) = self.decode(address) else {
+                break;
+            };
+
+            instructions.push((address, decoded.text));
+
+            let ends_block = decoded.kind.is_some_and(|kind| kind != JumpKind::Call);
+            if ends_block || !decoded.falls_through {
+                let next = decoded
+                    .target
+                    .filter(|target| ends_block && function.blocks.contains_key(target));
+
+                return (StaticBlock { instructions }, next);
+            }
+
+            address += decoded.size;
+
+            if function.blocks.contains_key(&address) {
+                return (StaticBlock { instructions }, Some(address));
+            }
+
+            if function.contains(address) {
+                break;
+            }
+        }
+
+        (StaticBlock { instructions }, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Binary;
+    use crate::trace::arch::architectures;
+
+    /// A 32-bit little endian ELF file with an executable segment for each pair of a byte
+    /// address and the words of its code.
+    fn elf(segments: &[(u32, &[u32])]) -> Vec<u8> {
+        let mut bytes = vec![0; 52];
+        bytes[..6].copy_from_slice(b"\x7fELF\x01\x01");
+        bytes[0x1C..0x20].copy_from_slice(&52u32.to_le_bytes());
+        bytes[0x2A..0x2C].copy_from_slice(&32u16.to_le_bytes());
+        bytes[0x2C..0x2E].copy_from_slice(&(segments.len() as u16).to_le_bytes());
+
+        let mut offset = 52 + 32 * segments.len() as u32;
+        for (address, code) in segments {
+            let size = 4 * code.len() as u32;
+            for field in [1, offset, *address, 0, size, size, 1, 4] {
+                bytes.extend(field.to_le_bytes());
+            }
+            offset += size;
+        }
+
+        for (_, code) in segments {
+            bytes.extend(code.iter().flat_map(|word| word.to_le_bytes()));
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn segments_in_words() {
+        // A jump over to the second segment, which is at 0x80 bytes and so at word 0x20.
+        let bytes = elf(&[(0, &[5, 0x20]), (0x80, &[10, 0])]);
+        let arch = architectures().remove(0);
+        let binary = Binary::load(&bytes, 0x100, arch).unwrap();
+
+        let text = |address| binary.decode(address).map(|decoded| decoded.text);
+        assert_eq!(text(0x100).as_deref(), Some("JMP   32"));
+        assert_eq!(text(0x120).as_deref(), Some("INC"));
+        assert_eq!(text(0x121).as_deref(), Some("HALT"));
+        assert_eq!(text(0x102), None);
+    }
+}
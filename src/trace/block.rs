@@ -1,6 +1,27 @@
-use super::{Instruction, JumpKind};
+use super::{symbols::Symbols, Instruction, JumpKind};
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::fmt;
 
+/// Something unexpected about how execution left a block, as seen in truncated traces.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anomaly {
+    /// The trace ended right after a jump, so where it went is unknown.
+    DanglingJump(JumpKind),
+    /// A return was taken without there being a call to return to.
+    UnmatchedReturn,
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Anomaly::DanglingJump(kind) => write!(f, "trace ends after {kind:?} jump"),
+            Anomaly::UnmatchedReturn => write!(f, "unmatched return"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct BasicBlock {
     pub(crate) start: u64,
     pub(crate) end: u64,
@@ -8,6 +29,7 @@ pub struct BasicBlock {
     // The first item indicates if it is Unconditional or Conditional, the second is the address,
     // the third is how many traverses
     edges: Vec<(JumpKind, u64, u64)>,
+    anomalies: Vec<Anomaly>,
 }
 
 impl BasicBlock {
@@ -18,35 +40,33 @@ impl BasicBlock {
             end: start,
             block: BTreeMap::new(),
             edges: Vec::new(),
+            anomalies: Vec::new(),
         }
     }
 
-    /// Appends an instruction to the basic block and sets the end address to the one given.
+    /// Appends an instruction to the basic block and moves the end address past it, counting
+    /// instructions without a size as taking up a single address.
     pub fn execute(&mut self, insn: &Instruction) {
         let _ = self.block.insert(insn.addr, insn.insn.clone());
 
-        if let Some(size) = insn.size {
-            self.end += insn.addr + size as u64
-        } else {
-            self.end = insn.addr
-        }
+        let end = insn.addr + insn.size.map_or(1, u64::from);
+        self.end = self.end.max(end);
     }
 
-    /// Get a string representation of the block.
-    pub fn to_string(&self) -> String {
+    /// Get a string representation of the block, with the labelled addresses preceded by their
+    /// label.
+    pub fn to_string(&self, symbols: &Symbols) -> String {
         let mut output = String::new();
         for (address, instruction) in &self.block {
+            if let Some(name) = symbols.get(*address) {
+                output.push_str(&format!("{name}:\\n"));
+            }
             output.push_str(&format!("0x{address:0>4X}\t{0}", instruction));
             output.push_str("\\n");
         }
         output
     }
 
-    /// Checks if the given address is **currently** in the range of this basic block.
-    pub fn in_range(&self, address: u64) -> bool {
-        self.start >= address && address <= self.end
-    }
-
     /// Returns an iterator of the address/instruction pairs inside the underlying HashMap.
     pub fn iter(&self) -> impl Iterator<Item = (&u64, &String)> {
         self.block.iter()
@@ -57,11 +77,6 @@ impl BasicBlock {
         self.edges.iter()
     }
 
-    /// Identifies if a given edge already exists for the block.
-    pub fn contains_edge(&self, edge: u64) -> bool {
-        self.edges.iter().any(|(_, e, _)| *e == edge)
-    }
-
     /// Adds a new edge if it cannot find it, otherwise increments the edge counter depending on if it was traversed or not.
     pub fn add_edge(&mut self, edge: u64, kind: JumpKind) {
         if let Some((_, _, count)) = self.edges.iter_mut().find(|(_, e, _)| *e == edge) {
@@ -70,4 +85,16 @@ impl BasicBlock {
             self.edges.push((kind, edge, 1));
         }
     }
+
+    /// Returns an iterator of the anomalies in how execution left the block.
+    pub fn anomalies(&self) -> impl Iterator<Item = &Anomaly> {
+        self.anomalies.iter()
+    }
+
+    /// Marks the block with an anomaly, unless it already has it.
+    pub fn mark(&mut self, anomaly: Anomaly) {
+        if !self.anomalies.contains(&anomaly) {
+            self.anomalies.push(anomaly);
+        }
+    }
 }
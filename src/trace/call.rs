@@ -0,0 +1,69 @@
+use super::{function::Function, DotFile, Error};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::Write;
+
+/// A call from one function to another, made by the instruction at the call site.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Call {
+    /// The starting address of the calling function.
+    pub caller: u64,
+    /// The address of the call instruction.
+    pub site: u64,
+    /// The starting address of the called function.
+    pub callee: u64,
+}
+
+/// Every call between functions that was taken in the trace.
+#[derive(Serialize, Deserialize, Default)]
+pub struct CallGraph {
+    /// How many times each call was taken.
+    calls: BTreeMap<Call, u64>,
+}
+
+impl CallGraph {
+    /// Records that a call was taken once more.
+    pub fn record(&mut self, call: Call) {
+        *self.calls.entry(call).or_insert(0) += 1;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Call, &u64)> {
+        self.calls.iter()
+    }
+
+    /// Generates the graph with a node for every function and an edge for every call site.
+    pub fn dot(&self, functions: &HashMap<u64, Function>) -> Result<DotFile, Error> {
+        let filename = String::from("/tmp/graph_calls");
+        let mut fd = File::create(&filename)?;
+
+        fd.write_all(b"digraph {\n")?;
+
+        let mut starts: Vec<_> = functions.keys().collect();
+        starts.sort();
+
+        for start in starts {
+            fd.write_all(
+                format!(
+                    "\tnode_{0} [shape=box][label=\"{1}\"][color=\"gray0\"][penwidth=2]\n",
+                    start, functions[start].name
+                )
+                .as_bytes(),
+            )?;
+        }
+
+        for (call, count) in &self.calls {
+            fd.write_all(
+                format!(
+                    "\tnode_{0} -> node_{1} [label=\"0x{2:X} ({3})\"][color=\"blue\"]\n",
+                    call.caller, call.callee, call.site, count
+                )
+                .as_bytes(),
+            )?;
+        }
+
+        fd.write_all(b"}")?;
+
+        Ok(DotFile { path: filename })
+    }
+}
@@ -1,67 +1,422 @@
-use super::{function::Function, Error, GraphFile, Instruction, JumpKind};
-use std::collections::HashMap;
+use super::{
+    binary::Binary,
+    block::Anomaly,
+    call::{Call, CallGraph},
+    function::Function,
+    symbols::Symbols,
+    DotFile, Error, Instruction, JumpKind,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap};
 
+#[derive(Serialize, Deserialize)]
 pub struct ControlFlowGraph {
     functions: HashMap<u64, Function>,
-    current_function: u64,
-    call_stack: Vec<u64>,
+    calls: CallGraph,
+    /// Where each thread is in the graph.
+    cursors: BTreeMap<u32, Cursor>,
+    /// Whether anomalies of truncated traces are recorded as warnings rather than errors.
+    lenient: bool,
+    /// How many instructions have been pushed, which is the step of the next one.
+    pushed: usize,
+    warnings: Vec<Warning>,
+    /// The names given to functions and labelled addresses, which are kept in a project of
+    /// their own rather than the index.
+    #[serde(skip)]
+    symbols: Symbols,
+    /// The traced binary, which shows the code that never executed in the graphs.
+    #[serde(skip)]
+    binary: Option<Binary>,
+    #[serde(skip)]
+    changes: Changes,
+}
+
+/// Where a thread is in the graph as it is being built.
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+    function: u64,
+    block: u64,
+    /// The function and block of every call that was not yet returned from.
+    call_stack: Vec<(u64, u64)>,
+    /// How many frames of the call stack every function on it has.
+    frames: BTreeMap<u64, usize>,
+    /// The jump taken by the last instruction of the thread along with its step and address,
+    /// which is followed once the next one arrives.
+    pending: Option<(JumpKind, usize, u64)>,
+}
+
+/// An anomaly which was tolerated while leniently building the graph.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Warning {
+    /// The step of the jump the anomaly is about.
+    pub step: usize,
+    pub thread: u32,
+    /// The function and block the jump was taken from.
+    pub function: u64,
+    pub block: u64,
+    pub anomaly: Anomaly,
+}
+
+impl Warning {
+    /// Describes the anomaly, with the function named after the given symbols.
+    pub fn describe(&self, symbols: &Symbols) -> String {
+        let function = match symbols.get(self.function) {
+            Some(name) => name.to_owned(),
+            None => Function::default_name(self.function),
+        };
+
+        format!(
+            "step {0}: {1} in block 0x{2:X} of {function}",
+            self.step, self.anomaly, self.block
+        )
+    }
+}
+
+/// The functions and blocks which were added or modified since the changes were last taken.
+#[derive(Default)]
+pub struct Changes {
+    /// The starting addresses of the changed functions.
+    pub functions: BTreeSet<u64>,
+    /// The starting addresses of the changed blocks, along with the function they are in.
+    pub blocks: BTreeSet<(u64, u64)>,
+    /// Whether a function was added or a call was taken, which changes the call graph.
+    pub calls: bool,
+    /// Whether instructions were pushed, which changes the def-use chains of those before.
+    pub steps: bool,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty() && !self.calls && !self.steps
+    }
+
+    /// Combines changes taken at different times.
+    pub fn merge(&mut self, other: Changes) {
+        self.functions.extend(other.functions);
+        self.blocks.extend(other.blocks);
+        self.calls |= other.calls;
+        self.steps |= other.steps;
+    }
+
+    fn block(&mut self, function: u64, block: u64) {
+        self.functions.insert(function);
+        self.blocks.insert((function, block));
+    }
 }
 
 impl ControlFlowGraph {
-    pub fn new(entry_point: u64) -> Self {
+    pub fn new(lenient: bool) -> Self {
         ControlFlowGraph {
-            functions: HashMap::from([(entry_point, Function::new(entry_point))]),
-            current_function: entry_point,
-            call_stack: Vec::new(),
+            functions: HashMap::new(),
+            calls: CallGraph::default(),
+            cursors: BTreeMap::new(),
+            lenient,
+            pushed: 0,
+            warnings: Vec::new(),
+            symbols: Symbols::default(),
+            binary: None,
+            changes: Changes::default(),
         }
     }
 
-    pub fn construct(&mut self, insns: &[Instruction]) -> Result<(), Error> {
-        let mut iter = insns.iter().peekable();
+    /// Adds the next executed instruction to the graph.
+    ///
+    /// Where a jump went is only known once the next instruction of the same thread is pushed,
+    /// so it is held as pending until then. Building can be resumed at any point by pushing more
+    /// instructions, and every thread starts out in a function of its own.
+    pub fn push(&mut self, insn: &Instruction) -> Result<(), Error> {
+        let step = self.pushed;
+        let thread = insn.thread();
 
-        while let Some(insn) = iter.next() {
-            let current_function = self
-                .functions
-                .get_mut(&self.current_function)
-                .ok_or(Error::MissingCurrentFunction)?;
+        if !self.cursors.contains_key(&thread) {
+            self.add_function(insn.addr);
+            self.cursors.insert(
+                thread,
+                Cursor {
+                    function: insn.addr,
+                    block: insn.addr,
+                    call_stack: Vec::new(),
+                    frames: BTreeMap::new(),
+                    pending: None,
+                },
+            );
+        }
 
-            let jump_type = &insn.kind;
+        if let Some((kind, jump_step, site)) = self.cursors[&thread].pending {
+            self.follow(thread, kind, jump_step, site, insn.addr)?;
+        }
 
-            let next_insn = iter.peek();
+        let cursor = self
+            .cursors
+            .get_mut(&thread)
+            .ok_or(Error::MissingCurrentFunction)?;
+        let current_function = self
+            .functions
+            .get_mut(&cursor.function)
+            .ok_or(Error::MissingCurrentFunction)?;
 
-            current_function.execute(insn, next_insn)?;
+        if current_function.execute(cursor.block, insn)? {
+            self.changes.block(cursor.function, cursor.block);
+        }
 
-            if let Some(jump_type) = jump_type {
-                let next_insn = next_insn.ok_or_else(|| Error::MissingInstruction)?;
+        let stats = &mut current_function.stats;
+        stats.exclusive += 1;
+        stats.inclusive += 1;
+        stats.first_step.get_or_insert(step);
+        stats.last_step = Some(step);
 
-                match jump_type {
-                    JumpKind::Call => {
-                        self.call_stack.push(current_function.start);
+        // Recursive functions are on the call stack more than once, but only execute this once.
+        for caller in cursor.frames.keys() {
+            if *caller == cursor.function {
+                continue;
+            }
+            if let Some(caller) = self.functions.get_mut(caller) {
+                caller.stats.inclusive += 1;
+            }
+        }
 
-                        self.add_function(next_insn.addr);
+        cursor.pending = insn.kind.map(|kind| (kind, step, insn.addr));
+        self.pushed += 1;
+        self.changes.steps = true;
 
-                        self.current_function = next_insn.addr;
-                    }
-                    JumpKind::Return => {
-                        self.current_function = self.call_stack.pop().unwrap();
-                    }
-                    _ => {}
+        Ok(())
+    }
+
+    /// Called once no more instructions will be pushed, marking jumps that are still pending as
+    /// dangling when building leniently.
+    pub fn finish(&mut self) -> Result<(), Error> {
+        let pending: Vec<_> = self
+            .cursors
+            .iter_mut()
+            .filter_map(|(thread, cursor)| {
+                let (kind, step, _) = cursor.pending.take()?;
+                Some((*thread, kind, step))
+            })
+            .collect();
+
+        for (thread, kind, step) in pending {
+            if !self.lenient {
+                return Err(Error::MissingInstruction);
+            }
+
+            self.warn(thread, step, Anomaly::DanglingJump(kind))?;
+        }
+
+        Ok(())
+    }
+
+    /// The anomalies that were tolerated so far.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Returns everything that changed since the last time this was called.
+    pub fn take_changes(&mut self) -> Changes {
+        std::mem::take(&mut self.changes)
+    }
+
+    pub fn symbols(&self) -> &Symbols {
+        &self.symbols
+    }
+
+    /// Replaces the names of every function and labelled address, and marks them all as
+    /// changed.
+    pub fn set_symbols(&mut self, symbols: Symbols) {
+        self.symbols = symbols;
+
+        for function in self.functions.values_mut() {
+            function.name = match self.symbols.get(function.start) {
+                Some(name) => name.to_owned(),
+                None => Function::default_name(function.start),
+            };
+
+            self.changes.block(function.start, function.start);
+        }
+
+        self.changes.calls = true;
+    }
+
+    /// Names an address, or goes back to the default name when there is none, and marks the
+    /// functions it shows up in as changed.
+    pub fn rename(&mut self, address: u64, name: Option<&str>) {
+        match name {
+            Some(name) => self.symbols.set(address, name),
+            None => self.symbols.remove(address),
+        }
+
+        if let Some(function) = self.functions.get_mut(&address) {
+            function.name = match self.symbols.get(address) {
+                Some(name) => name.to_owned(),
+                None => Function::default_name(address),
+            };
+
+            self.changes.calls = true;
+        }
+
+        for function in self.functions.values() {
+            if function.start == address || function.contains(address) {
+                self.changes.block(function.start, function.start);
+            }
+        }
+    }
+
+    pub fn binary(&self) -> Option<&Binary> {
+        self.binary.as_ref()
+    }
+
+    /// Replaces the traced binary, or removes it, and marks every function as changed.
+    pub fn set_binary(&mut self, binary: Option<Binary>) {
+        self.binary = binary;
+
+        for function in self.functions.values() {
+            self.changes.block(function.start, function.start);
+        }
+    }
+
+    /// Every function in the graph, in no particular order.
+    pub fn functions(&self) -> impl Iterator<Item = &Function> {
+        self.functions.values()
+    }
+
+    /// The function starting at the given address, if one does.
+    pub fn function(&self, address: u64) -> Option<&Function> {
+        self.functions.get(&address)
+    }
+
+    /// Every call between functions that was taken, with how many times it was.
+    pub fn calls(&self) -> impl Iterator<Item = (&Call, &u64)> {
+        self.calls.iter()
+    }
+
+    /// Generates the graph of calls between the functions.
+    pub fn gen_call_graph(&self) -> Result<DotFile, Error> {
+        self.calls.dot(&self.functions)
+    }
+
+    /// Generates the graph of the function starting at the given address.
+    pub fn gen_graph(&self, function: u64) -> Result<DotFile, Error> {
+        self.functions
+            .get(&function)
+            .ok_or(Error::MissingFunction)?
+            .dot(&self.symbols, self.binary.as_ref())
+    }
+
+    /// Follows the jump a thread took at the given step and address to the given target.
+    fn follow(
+        &mut self,
+        thread: u32,
+        kind: JumpKind,
+        step: usize,
+        site: u64,
+        target: u64,
+    ) -> Result<(), Error> {
+        if kind == JumpKind::Call {
+            self.add_function(target);
+        }
+
+        let cursor = self
+            .cursors
+            .get_mut(&thread)
+            .ok_or(Error::MissingCurrentFunction)?;
+
+        match kind {
+            JumpKind::Conditional | JumpKind::Unconditional => {
+                self.functions
+                    .get_mut(&cursor.function)
+                    .ok_or(Error::MissingCurrentFunction)?
+                    .jump(cursor.block, kind, target)?;
+
+                self.changes.block(cursor.function, cursor.block);
+                self.changes.block(cursor.function, target);
+
+                cursor.block = target;
+            }
+            JumpKind::Call => {
+                self.calls.record(Call {
+                    caller: cursor.function,
+                    site,
+                    callee: target,
+                });
+                self.changes.calls = true;
+
+                if let Some(callee) = self.functions.get_mut(&target) {
+                    callee.stats.calls += 1;
                 }
+
+                cursor.call_stack.push((cursor.function, cursor.block));
+                *cursor.frames.entry(cursor.function).or_default() += 1;
+
+                cursor.function = target;
+                cursor.block = target;
             }
+            JumpKind::Return => match cursor.call_stack.pop() {
+                Some((function, block)) => {
+                    if let Some(frames) = cursor.frames.get_mut(&function) {
+                        *frames -= 1;
+                        if *frames == 0 {
+                            cursor.frames.remove(&function);
+                        }
+                    }
+
+                    cursor.function = function;
+                    cursor.block = block;
+                }
+                None if self.lenient => {
+                    self.warn(thread, step, Anomaly::UnmatchedReturn)?;
+
+                    // Whoever was returned to is unknown, so treat it as a function of its own.
+                    self.add_function(target);
+
+                    let cursor = self
+                        .cursors
+                        .get_mut(&thread)
+                        .ok_or(Error::MissingCurrentFunction)?;
+                    cursor.function = target;
+                    cursor.block = target;
+                }
+                None => return Err(Error::UnmatchedReturn),
+            },
         }
 
         Ok(())
     }
 
-    /// Generates the graphs of the control flow graph.
-    pub fn gen_graphs(&self) -> Result<Vec<GraphFile>, Error> {
-        self.functions.iter().map(|(_, func)| func.dot()).collect()
+    /// Marks where a thread currently is with an anomaly in the jump it took at the given step,
+    /// and records it as a warning.
+    fn warn(&mut self, thread: u32, step: usize, anomaly: Anomaly) -> Result<(), Error> {
+        let cursor = self
+            .cursors
+            .get(&thread)
+            .ok_or(Error::MissingCurrentFunction)?;
+
+        self.functions
+            .get_mut(&cursor.function)
+            .ok_or(Error::MissingCurrentFunction)?
+            .mark(cursor.block, anomaly)?;
+
+        self.changes.block(cursor.function, cursor.block);
+        self.warnings.push(Warning {
+            step,
+            thread,
+            function: cursor.function,
+            block: cursor.block,
+            anomaly,
+        });
+
+        Ok(())
     }
 
-    /// Adds a function with the given start address if needed, name is auto-generated.
+    /// Adds a function with the given start address if needed, name is auto-generated unless
+    /// there is a symbol for it.
     fn add_function(&mut self, starting_address: u64) {
-        self.functions
-            .entry(starting_address)
-            .or_insert(Function::new(starting_address));
+        if let Entry::Vacant(entry) = self.functions.entry(starting_address) {
+            let function = entry.insert(Function::new(starting_address));
+            if let Some(name) = self.symbols.get(starting_address) {
+                function.name = name.to_owned();
+            }
+
+            self.changes.block(starting_address, starting_address);
+            self.changes.calls = true;
+        }
     }
 }
@@ -0,0 +1,307 @@
+use super::{taint::Location, DotFile, Error, Tracer};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::Write;
+
+/// The most readers kept of a value, past which one read over and over would fill the graph.
+pub const MAX_CONSUMERS: usize = 16;
+/// How many levels of producers and consumers the graph can be expanded to.
+pub const MAX_DEF_USE_DEPTH: usize = 4;
+
+/// Every read and write of every location, so that def-use chains are found without going
+/// through the trace.
+pub struct Accesses {
+    /// The steps at which each location was written, in ascending order.
+    writes: BTreeMap<Location, Vec<usize>>,
+    /// The reads and writes of each location in the order they happened, by step and whether
+    /// it was a write.
+    events: BTreeMap<Location, Vec<(usize, bool)>>,
+}
+
+/// Where the values an instruction read came from, and where those it wrote went.
+pub struct DefUse {
+    pub step: usize,
+    /// Every location the instruction read before writing to it, with the step of the
+    /// instruction which wrote the value, none when it was there before the trace began.
+    pub producers: Vec<(Location, Option<usize>)>,
+    /// Every location the instruction wrote, with the steps of the instructions which read the
+    /// value before it was overwritten.
+    pub consumers: Vec<(Location, Vec<usize>)>,
+}
+
+impl Tracer {
+    /// The def-use chains of the instruction at a step.
+    ///
+    /// Reads of what the instruction itself wrote before are left out, as are writes it
+    /// overwrote itself.
+    pub fn def_use(&self, step: usize) -> DefUse {
+        let insn = &self.instructions[step];
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+
+        for event in insn.events() {
+            let (location, write) = self.access(insn, event);
+
+            if outputs.contains(&location) {
+                continue;
+            }
+
+            if write {
+                outputs.push(location);
+            } else if !inputs.contains(&location) {
+                inputs.push(location);
+            }
+        }
+
+        let accesses = self.accesses();
+
+        // The last write before the step to each of the inputs.
+        let producers = inputs
+            .into_iter()
+            .map(|location| {
+                let writer = accesses.writes.get(&location).and_then(|writes| {
+                    let before = writes.partition_point(|write| *write < step);
+                    before.checked_sub(1).map(|i| writes[i])
+                });
+
+                (location, writer)
+            })
+            .collect();
+
+        // The reads after the step of each of the outputs, until it is written again.
+        let consumers = outputs
+            .into_iter()
+            .map(|location| {
+                let mut steps = Vec::new();
+                let events = accesses
+                    .events
+                    .get(&location)
+                    .map_or(&[][..], Vec::as_slice);
+                let after = events.partition_point(|(at, _)| *at <= step);
+
+                for (next, write) in &events[after..] {
+                    if *write || steps.len() == MAX_CONSUMERS {
+                        break;
+                    }
+
+                    if steps.last() != Some(next) {
+                        steps.push(*next);
+                    }
+                }
+
+                (location, steps)
+            })
+            .collect();
+
+        DefUse {
+            step,
+            producers,
+            consumers,
+        }
+    }
+
+    /// Every read and write of every location, gathered the first time they are needed since
+    /// the trace last grew.
+    fn accesses(&self) -> &Accesses {
+        self.accesses.get_or_init(|| {
+            let mut accesses = Accesses {
+                writes: BTreeMap::new(),
+                events: BTreeMap::new(),
+            };
+
+            for (step, insn) in self.instructions.iter().enumerate() {
+                for event in insn.events() {
+                    let (location, write) = self.access(insn, event);
+
+                    if write && accesses.writes.get(&location).and_then(|w| w.last()) != Some(&step)
+                    {
+                        accesses.writes.entry(location).or_default().push(step);
+                    }
+                    accesses
+                        .events
+                        .entry(location)
+                        .or_default()
+                        .push((step, write));
+                }
+            }
+
+            accesses
+        })
+    }
+
+    /// Generates the graph of where the values read by the instruction at a step came from and
+    /// where those it wrote went, following producers and consumers the given number of levels.
+    pub fn def_use_graph(&self, step: usize, depth: usize) -> Result<DotFile, Error> {
+        let mut steps = BTreeSet::from([step]);
+        let mut edges = BTreeSet::new();
+        // Values which were there before the trace began.
+        let mut initial = BTreeSet::new();
+
+        let mut producers = vec![step];
+        let mut consumers = vec![step];
+
+        for _ in 0..depth.min(MAX_DEF_USE_DEPTH) {
+            let mut next = Vec::new();
+
+            for step in producers {
+                for (location, producer) in self.def_use(step).producers {
+                    match producer {
+                        Some(producer) => {
+                            edges.insert((producer, step, location));
+                            if steps.insert(producer) {
+                                next.push(producer);
+                            }
+                        }
+                        None => {
+                            initial.insert((step, location));
+                        }
+                    }
+                }
+            }
+
+            producers = next;
+            let mut next = Vec::new();
+
+            for step in consumers {
+                for (location, readers) in self.def_use(step).consumers {
+                    for reader in readers {
+                        edges.insert((step, reader, location));
+                        if steps.insert(reader) {
+                            next.push(reader);
+                        }
+                    }
+                }
+            }
+
+            consumers = next;
+        }
+
+        let filename = format!("/tmp/graph_defuse_{step}_{depth}");
+        let mut fd = File::create(&filename)?;
+
+        fd.write_all(b"digraph {\n")?;
+
+        for node in &steps {
+            let insn = &self.instructions[*node];
+            let address = match self.cfg.symbols().get(insn.addr()) {
+                Some(name) => name.to_owned(),
+                None => format!("0x{0:X}", insn.addr()),
+            };
+            let fill = if *node == step {
+                "[style=filled][fillcolor=\"#FFF0A8\"]"
+            } else {
+                ""
+            };
+
+            fd.write_all(
+                format!(
+                    "\tnode_{0} [shape=box][label=\"{0}: {1}\\n{2}\"][color=\"gray0\"][penwidth=2]{3}\n",
+                    node, address, insn.insn, fill
+                )
+                .as_bytes(),
+            )?;
+        }
+
+        if !initial.is_empty() {
+            fd.write_all(
+                b"\tinitial [shape=plaintext][label=\"before the trace\"][fontcolor=\"gray40\"]\n",
+            )?;
+        }
+
+        for (to, location) in &initial {
+            fd.write_all(
+                format!(
+                    "\tinitial -> node_{0} [label=\"{1}\"][color=\"gray40\"][style=dashed]\n",
+                    to,
+                    self.location_name(*location)
+                )
+                .as_bytes(),
+            )?;
+        }
+
+        for (from, to, location) in &edges {
+            let color = match location {
+                Location::Register { .. } => "blue",
+                Location::Memory(_) => "red",
+            };
+
+            fd.write_all(
+                format!(
+                    "\tnode_{0} -> node_{1} [label=\"{2}\"][color=\"{3}\"]\n",
+                    from,
+                    to,
+                    self.location_name(*location),
+                    color
+                )
+                .as_bytes(),
+            )?;
+        }
+
+        fd.write_all(b"}")?;
+
+        Ok(DotFile { path: filename })
+    }
+
+    /// The name of a register, or the address of memory.
+    fn location_name(&self, location: Location) -> String {
+        match location {
+            Location::Register { register, .. } => self.registers.iter().nth(register).map_or_else(
+                || register.to_string(),
+                |register| register.name().to_owned(),
+            ),
+            Location::Memory(address) => format!("0x{address:X}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trace::{
+        taint::Location,
+        tests::{tracer, COPIES},
+    };
+
+    /// The producers or consumers of memory, leaving out the instruction pointer.
+    fn memory<T: Clone>(chains: &[(Location, T)]) -> Vec<(u64, T)> {
+        chains
+            .iter()
+            .filter_map(|(location, steps)| match location {
+                Location::Memory(address) => Some((*address, steps.clone())),
+                Location::Register { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn producers_and_consumers() {
+        let tracer = tracer(COPIES);
+
+        let def_use = tracer.def_use(0);
+        assert_eq!(memory(&def_use.producers), [(0x100, None)]);
+        // Read once before being overwritten.
+        assert_eq!(memory(&def_use.consumers), [(0x108, vec![2])]);
+
+        let def_use = tracer.def_use(2);
+        assert_eq!(memory(&def_use.producers), [(0x108, Some(0))]);
+        assert_eq!(memory(&def_use.consumers), [(0x118, vec![4])]);
+
+        let def_use = tracer.def_use(3);
+        assert_eq!(memory(&def_use.consumers), [(0x108, vec![])]);
+    }
+
+    #[test]
+    fn graph() {
+        let tracer = tracer(COPIES);
+
+        let dot = tracer.def_use_graph(2, 1).unwrap();
+        let text = std::fs::read_to_string(&dot.path).unwrap();
+        assert!(text.contains("node_0 -> node_2 [label=\"0x108\"][color=\"red\"]"));
+        assert!(text.contains("node_2 -> node_4 [label=\"0x118\"][color=\"red\"]"));
+        assert!(!text.contains("initial"));
+
+        // A level deeper reaches what was there before the trace.
+        let dot = tracer.def_use_graph(2, 2).unwrap();
+        let text = std::fs::read_to_string(&dot.path).unwrap();
+        assert!(text.contains("initial -> node_0 [label=\"0x100\"]"));
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Which blocks every path from where a function starts goes through to reach each of its
+/// blocks, or which every path from a block to where the function ends goes through when the
+/// edges are turned around.
+pub struct Dominators {
+    /// The closest block dominating every block reached, none for those only the roots do.
+    immediate: BTreeMap<u64, Option<u64>>,
+}
+
+impl Dominators {
+    /// Finds the dominators of the blocks reached from the roots by following the given edges,
+    /// with the iterative algorithm of Cooper, Harvey and Kennedy.
+    ///
+    /// There may be several roots, as a function can end in several places, which are taken as
+    /// being dominated by a root of their own.
+    pub fn new(roots: &[u64], successors: &BTreeMap<u64, Vec<u64>>) -> Dominators {
+        // The blocks in reverse postorder, after the root of the roots.
+        let order = reverse_postorder(roots, successors);
+        let number: HashMap<u64, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(i, block)| (*block, i + 1))
+            .collect();
+
+        let mut predecessors = vec![Vec::new(); order.len() + 1];
+        for root in roots {
+            predecessors[number[root]].push(0);
+        }
+        for (from, tos) in successors {
+            let Some(from) = number.get(from) else {
+                continue;
+            };
+
+            for to in tos {
+                if let Some(to) = number.get(to) {
+                    predecessors[*to].push(*from);
+                }
+            }
+        }
+
+        let mut dominators: Vec<Option<usize>> = vec![None; order.len() + 1];
+        dominators[0] = Some(0);
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for block in 1..=order.len() {
+                let mut processed = predecessors[block]
+                    .iter()
+                    .filter(|predecessor| dominators[**predecessor].is_some());
+
+                let Some(first) = processed.next() else {
+                    continue;
+                };
+
+                let immediate = processed.fold(*first, |a, b| intersect(&dominators, a, *b));
+
+                if dominators[block] != Some(immediate) {
+                    dominators[block] = Some(immediate);
+                    changed = true;
+                }
+            }
+        }
+
+        let immediate = order
+            .iter()
+            .enumerate()
+            .map(|(i, block)| {
+                let immediate = dominators[i + 1]
+                    .filter(|immediate| *immediate != 0)
+                    .map(|immediate| order[immediate - 1]);
+
+                (*block, immediate)
+            })
+            .collect();
+
+        Dominators { immediate }
+    }
+
+    /// Whether the block was reached from the roots.
+    pub fn contains(&self, block: u64) -> bool {
+        self.immediate.contains_key(&block)
+    }
+
+    /// The closest block dominating the given one, other than itself.
+    pub fn immediate(&self, block: u64) -> Option<u64> {
+        self.immediate.get(&block).copied().flatten()
+    }
+
+    /// Whether every path to the second block goes through the first, which every block does
+    /// to itself.
+    pub fn dominates(&self, dominator: u64, block: u64) -> bool {
+        if !self.contains(block) {
+            return false;
+        }
+
+        let mut current = Some(block);
+        while let Some(block) = current {
+            if block == dominator {
+                return true;
+            }
+            current = self.immediate(block);
+        }
+
+        false
+    }
+}
+
+/// Walks up from two blocks to where their dominators meet, by their reverse postorder.
+fn intersect(dominators: &[Option<usize>], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while a > b {
+            a = dominators[a].unwrap_or(0);
+        }
+        while b > a {
+            b = dominators[b].unwrap_or(0);
+        }
+    }
+
+    a
+}
+
+fn reverse_postorder(roots: &[u64], successors: &BTreeMap<u64, Vec<u64>>) -> Vec<u64> {
+    let mut visited = HashSet::new();
+    let mut postorder = Vec::new();
+
+    for root in roots {
+        if !visited.insert(*root) {
+            continue;
+        }
+
+        let mut stack = vec![(*root, 0)];
+
+        while let Some((block, next)) = stack.last_mut() {
+            let to = successors
+                .get(block)
+                .and_then(|successors| successors.get(*next))
+                .copied();
+
+            match to {
+                Some(to) => {
+                    *next += 1;
+                    if visited.insert(to) {
+                        stack.push((to, 0));
+                    }
+                }
+                None => {
+                    postorder.push(*block);
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    postorder.reverse();
+    postorder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dominators;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn single_root() {
+        // 1 branches to 2 and 3, which meet again at 4.
+        let successors = BTreeMap::from([(1, vec![2, 3]), (2, vec![4]), (3, vec![4])]);
+        let dominators = Dominators::new(&[1], &successors);
+
+        assert_eq!(dominators.immediate(1), None);
+        assert_eq!(dominators.immediate(2), Some(1));
+        assert_eq!(dominators.immediate(3), Some(1));
+        assert_eq!(dominators.immediate(4), Some(1));
+        assert!(dominators.dominates(1, 4));
+        assert!(!dominators.dominates(2, 4));
+        assert!(dominators.dominates(4, 4));
+    }
+
+    #[test]
+    fn multiple_roots() {
+        // Both roots lead to 3, so neither dominates it.
+        let successors = BTreeMap::from([(1, vec![3]), (2, vec![3]), (3, vec![4])]);
+        let dominators = Dominators::new(&[1, 2], &successors);
+
+        assert_eq!(dominators.immediate(1), None);
+        assert_eq!(dominators.immediate(2), None);
+        assert_eq!(dominators.immediate(3), None);
+        assert_eq!(dominators.immediate(4), Some(3));
+        assert!(!dominators.dominates(1, 3));
+        assert!(!dominators.dominates(2, 4));
+        assert!(dominators.dominates(3, 4));
+    }
+
+    #[test]
+    fn unreached() {
+        let successors = BTreeMap::from([(1, vec![2]), (5, vec![2])]);
+        let dominators = Dominators::new(&[1], &successors);
+
+        assert!(dominators.contains(2));
+        assert!(!dominators.contains(5));
+        assert!(!dominators.dominates(5, 2));
+        assert_eq!(dominators.immediate(2), Some(1));
+    }
+}
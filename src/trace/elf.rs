@@ -0,0 +1,260 @@
+use super::Error;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+/// The section types of symbol tables.
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_DYNSYM: u32 = 11;
+/// The symbol type of functions.
+pub const STT_FUNC: u8 = 2;
+/// The segment type of those loaded into memory, and the flag of those that are executable.
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1;
+
+/// An ELF file of either class and byte order, read just enough to find its symbols and code.
+pub struct Elf<'a> {
+    bytes: &'a [u8],
+    /// Whether it is 64-bit, where addresses and offsets are twice as wide.
+    wide: bool,
+    big_endian: bool,
+}
+
+pub struct Section {
+    pub kind: u32,
+    pub offset: u64,
+    pub size: u64,
+    /// The index of the section this one refers to, the string table of a symbol table.
+    pub link: u32,
+    pub entsize: u64,
+}
+
+/// A loaded segment, with where its bytes are in the file.
+pub struct Segment {
+    pub address: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub executable: bool,
+}
+
+/// A symbol, with the offset of its name in the string table of its symbol table.
+pub struct Symbol {
+    pub name: u32,
+    pub info: u8,
+    pub value: u64,
+}
+
+impl<'a> Elf<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Elf<'a>, Error> {
+        if !bytes.starts_with(b"\x7fELF") {
+            return Err(Error::MalformedElf);
+        }
+
+        Ok(Elf {
+            bytes,
+            wide: *bytes.get(4).ok_or(Error::MalformedElf)? == 2,
+            big_endian: *bytes.get(5).ok_or(Error::MalformedElf)? == 2,
+        })
+    }
+
+    pub fn sections(&self) -> Result<Vec<Section>, Error> {
+        let (shoff, shentsize, shnum) = if self.wide {
+            (self.word(0x28)?, self.half(0x3A)?, self.half(0x3C)?)
+        } else {
+            (self.word(0x20)?, self.half(0x2E)?, self.half(0x30)?)
+        };
+
+        (0..shnum as u64)
+            .map(|index| {
+                let header = self.entry(shoff, index, shentsize)?;
+
+                Ok(if self.wide {
+                    Section {
+                        kind: self.u32(header + 4)?,
+                        offset: self.word(header + 0x18)?,
+                        size: self.word(header + 0x20)?,
+                        link: self.u32(header + 0x28)?,
+                        entsize: self.word(header + 0x38)?,
+                    }
+                } else {
+                    Section {
+                        kind: self.u32(header + 4)?,
+                        offset: self.word(header + 0x10)?,
+                        size: self.word(header + 0x14)?,
+                        link: self.u32(header + 0x18)?,
+                        entsize: self.word(header + 0x24)?,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// The segments which are loaded into memory.
+    pub fn segments(&self) -> Result<Vec<Segment>, Error> {
+        let (phoff, phentsize, phnum) = if self.wide {
+            (self.word(0x20)?, self.half(0x36)?, self.half(0x38)?)
+        } else {
+            (self.word(0x1C)?, self.half(0x2A)?, self.half(0x2C)?)
+        };
+
+        let mut segments = Vec::new();
+
+        for index in 0..phnum as u64 {
+            let header = self.entry(phoff, index, phentsize)?;
+
+            if self.u32(header)? != PT_LOAD {
+                continue;
+            }
+
+            let (flags, offset, address, size) = if self.wide {
+                (
+                    self.u32(header + 4)?,
+                    self.word(header + 8)?,
+                    self.word(header + 0x10)?,
+                    self.word(header + 0x20)?,
+                )
+            } else {
+                (
+                    self.u32(header + 0x18)?,
+                    self.word(header + 4)?,
+                    self.word(header + 8)?,
+                    self.word(header + 0x10)?,
+                )
+            };
+
+            segments.push(Segment {
+                address,
+                offset,
+                size,
+                executable: flags & PF_X != 0,
+            });
+        }
+
+        Ok(segments)
+    }
+
+    /// The symbols in a symbol table section.
+    pub fn symbols(&self, table: &Section) -> Result<Vec<Symbol>, Error> {
+        if table.entsize == 0 {
+            return Err(Error::MalformedElf);
+        }
+
+        let end = table
+            .offset
+            .checked_add(table.size)
+            .ok_or(Error::MalformedElf)?;
+
+        (table.offset..end)
+            .step_by(table.entsize as usize)
+            .map(|symbol| {
+                Ok(if self.wide {
+                    Symbol {
+                        name: self.u32(symbol)?,
+                        info: self.u8(symbol + 4)?,
+                        value: self.word(symbol + 8)?,
+                    }
+                } else {
+                    Symbol {
+                        name: self.u32(symbol)?,
+                        info: self.u8(symbol + 12)?,
+                        value: self.word(symbol + 4)?,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// The offset of an entry of a header table, which has to lie within the file.
+    fn entry(&self, table: u64, index: u64, size: u16) -> Result<u64, Error> {
+        let offset = index
+            .checked_mul(size as u64)
+            .and_then(|offset| offset.checked_add(table))
+            .ok_or(Error::MalformedElf)?;
+
+        self.get(offset, size as u64)?;
+
+        Ok(offset)
+    }
+
+    pub fn get(&self, offset: u64, len: u64) -> Result<&'a [u8], Error> {
+        let start = usize::try_from(offset).map_err(|_| Error::MalformedElf)?;
+        let len = usize::try_from(len).map_err(|_| Error::MalformedElf)?;
+
+        self.bytes
+            .get(start..start.saturating_add(len))
+            .ok_or(Error::MalformedElf)
+    }
+
+    fn u8(&self, offset: u64) -> Result<u8, Error> {
+        Ok(self.get(offset, 1)?[0])
+    }
+
+    fn half(&self, offset: u64) -> Result<u16, Error> {
+        let bytes = self.get(offset, 2)?;
+
+        Ok(if self.big_endian {
+            BigEndian::read_u16(bytes)
+        } else {
+            LittleEndian::read_u16(bytes)
+        })
+    }
+
+    fn u32(&self, offset: u64) -> Result<u32, Error> {
+        let bytes = self.get(offset, 4)?;
+
+        Ok(if self.big_endian {
+            BigEndian::read_u32(bytes)
+        } else {
+            LittleEndian::read_u32(bytes)
+        })
+    }
+
+    /// An address, offset or size, which are as wide as the class of the file.
+    fn word(&self, offset: u64) -> Result<u64, Error> {
+        if !self.wide {
+            return Ok(self.u32(offset)? as u64);
+        }
+
+        let bytes = self.get(offset, 8)?;
+
+        Ok(if self.big_endian {
+            BigEndian::read_u64(bytes)
+        } else {
+            LittleEndian::read_u64(bytes)
+        })
+    }
+
+    /// A NUL terminated string.
+    pub fn string(&self, offset: u64) -> Result<String, Error> {
+        let start = usize::try_from(offset).map_err(|_| Error::MalformedElf)?;
+        let bytes = self.bytes.get(start..).ok_or(Error::MalformedElf)?;
+        let end = bytes
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or(Error::MalformedElf)?;
+
+        Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Elf;
+    use crate::trace::Error;
+
+    #[test]
+    fn overflowing_offsets() {
+        // A 64-bit file whose program and section headers are said to be at the very end of
+        // the address space.
+        let mut bytes = vec![0; 64];
+        bytes[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        bytes[0x20..0x28].copy_from_slice(&u64::MAX.to_le_bytes());
+        bytes[0x28..0x30].copy_from_slice(&u64::MAX.to_le_bytes());
+        bytes[0x36..0x38].copy_from_slice(&0x38u16.to_le_bytes());
+        bytes[0x38..0x3A].copy_from_slice(&2u16.to_le_bytes());
+        bytes[0x3A..0x3C].copy_from_slice(&0x40u16.to_le_bytes());
+        bytes[0x3C..0x3E].copy_from_slice(&2u16.to_le_bytes());
+
+        let elf = Elf::parse(&bytes).unwrap();
+        assert!(matches!(elf.segments(), Err(Error::MalformedElf)));
+        assert!(matches!(elf.sections(), Err(Error::MalformedElf)));
+    }
+}
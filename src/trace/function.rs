@@ -1,84 +1,144 @@
-use super::{block::BasicBlock, Error, GraphFile, Instruction, JumpKind};
+use super::{
+    binary::Binary,
+    block::{Anomaly, BasicBlock},
+    loops,
+    symbols::Symbols,
+    DotFile, Error, Instruction, JumpKind,
+};
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Write;
-use std::process::Command;
 
+/// The colors of blocks in loops, deeper loops being darker.
+const LOOP_COLORS: [&str; 3] = ["#E4EEFF", "#C8DCFF", "#AAC8FF"];
+
+#[derive(Serialize, Deserialize)]
 pub struct Function {
-    /// The name of the function which follows a `sub_[address]` format with a given starting
-    /// address.
-    ///
-    /// The only exception to this rule is the `start` function.
+    /// The name of the function, which follows a `sub_[address]` format with the starting
+    /// address in hexadecimal unless it was given one.
     pub(crate) name: String,
     /// The starting address of the function.
     pub(crate) start: u64,
     /// All of the basic blocks which make up the function.
     pub(crate) blocks: BTreeMap<u64, BasicBlock>,
-    /// The current block which the function is at.
-    ///
-    /// Interally used for constructing the function as its executed.
-    current_block: u64,
+    pub(crate) stats: Stats,
+}
+
+/// How the function was executed throughout the trace.
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+pub struct Stats {
+    /// How many times the function was called.
+    pub calls: u64,
+    /// How many instructions were executed while the function was on the call stack, including
+    /// those of the functions it called.
+    pub inclusive: u64,
+    /// How many instructions of the function itself were executed.
+    pub exclusive: u64,
+    /// The first and last steps at which an instruction of the function itself was executed.
+    pub first_step: Option<usize>,
+    pub last_step: Option<usize>,
 }
 
 impl Function {
     /// Creates a function following the `sub_[address]` format with the given starting address.
     pub fn new(address: u64) -> Self {
         Function {
-            name: format!("sub_{address}"),
+            name: Function::default_name(address),
             start: address,
             blocks: BTreeMap::from([(address, BasicBlock::new(address))]),
-            current_block: address,
+            stats: Stats::default(),
         }
     }
 
-    pub fn execute(
-        &mut self,
-        insn: &Instruction,
-        next_insn: Option<&&Instruction>,
-    ) -> Result<(), Error> {
+    /// The name of a function that was not given one.
+    pub fn default_name(address: u64) -> String {
+        format!("sub_{address:X}")
+    }
+
+    /// Whether an instruction at the given address was executed as part of the function.
+    pub fn contains(&self, address: u64) -> bool {
+        self.blocks
+            .values()
+            .any(|block| block.block.contains_key(&address))
+    }
+
+    /// How many edges there are between the blocks of the function.
+    pub fn edges(&self) -> usize {
+        self.blocks
+            .values()
+            .map(|block| block.edges().count())
+            .sum()
+    }
+
+    /// Adds an instruction to the given block, returning whether it was not already in it.
+    pub fn execute(&mut self, block: u64, insn: &Instruction) -> Result<bool, Error> {
         let current_block = self
             .blocks
-            .get_mut(&self.current_block)
+            .get_mut(&block)
             .ok_or(Error::MissingCurrentBlock)?;
 
-        if !current_block.block.contains_key(&insn.addr) {
-            current_block.execute(insn);
+        if current_block.block.contains_key(&insn.addr) {
+            return Ok(false);
         }
 
-        let jump_type = &insn.kind;
+        current_block.execute(insn);
 
-        if let Some(jump_type) = jump_type {
-            let next_insn = next_insn.ok_or_else(|| Error::MissingInstruction)?;
+        Ok(true)
+    }
 
-            match jump_type {
-                JumpKind::Conditional | JumpKind::Unconditional => {
-                    current_block.add_edge(next_insn.addr, *jump_type);
+    /// Follows a jump out of the given block to the block starting at the given address.
+    pub fn jump(&mut self, block: u64, kind: JumpKind, target: u64) -> Result<(), Error> {
+        let current_block = self
+            .blocks
+            .get_mut(&block)
+            .ok_or(Error::MissingCurrentBlock)?;
 
-                    self.blocks
-                        .entry(next_insn.addr)
-                        .or_insert(BasicBlock::new(next_insn.addr));
+        current_block.add_edge(target, kind);
 
-                    self.current_block = next_insn.addr;
-                }
-                _ => {}
-            }
-        }
+        self.blocks.entry(target).or_insert(BasicBlock::new(target));
+
+        Ok(())
+    }
+
+    /// Marks the given block with an anomaly in how execution left it.
+    pub fn mark(&mut self, block: u64, anomaly: Anomaly) -> Result<(), Error> {
+        self.blocks
+            .get_mut(&block)
+            .ok_or(Error::MissingCurrentBlock)?
+            .mark(anomaly);
 
         Ok(())
     }
 
-    pub fn dot(&self) -> Result<GraphFile, Error> {
+    /// Generates the graph of the function, with the blocks labelled by the given symbols.
+    ///
+    /// When the traced binary is given, the blocks that never executed but could have are shown
+    /// greyed out.
+    pub fn dot(&self, symbols: &Symbols, binary: Option<&Binary>) -> Result<DotFile, Error> {
         let filename = format!("/tmp/graph_{0}", self.start);
         let mut fd = File::create(&filename)?;
 
-        fd.write(b"digraph {\n")?;
+        fd.write_all(b"digraph {\n")?;
+
+        let loops = self.loops();
+        let depths = loops::depths(&loops);
 
         for (address, block) in &self.blocks {
-            fd.write(
+            let shade = match depths.get(address) {
+                Some(depth) => format!(
+                    "[style=filled][fillcolor=\"{0}\"]",
+                    LOOP_COLORS[(*depth).min(LOOP_COLORS.len()) - 1]
+                ),
+                None => String::new(),
+            };
+
+            fd.write_all(
                 format!(
-                    "\tnode_{0} [shape=box][label=\"{1}\"][color=\"gray0\"][penwidth=2]\n",
+                    "\tnode_{0} [shape=box][label=\"{1}\"][color=\"gray0\"][penwidth=2]{2}\n",
                     address,
-                    block.to_string()
+                    block.to_string(symbols),
+                    shade
                 )
                 .as_bytes(),
             )?;
@@ -92,33 +152,78 @@ impl Function {
                     _ => unreachable!(),
                 };
 
-                fd.write(
+                // Jumps back to the header of a loop are drawn thicker.
+                let back = loops.iter().any(|natural| {
+                    natural.header == *edge
+                        && natural.back_edges.iter().any(|(from, _)| from == address)
+                });
+                let width = if back { 3 } else { 1 };
+
+                fd.write_all(
                     format!(
-                        "\tnode_{0} -> node_{1} [label=\"{2}\"][color=\"{3}\", headport=n, tailport=s]\n",
-                        address, edge, count, color
+                        "\tnode_{0} -> node_{1} [label=\"{2}\"][color=\"{3}\", penwidth={4}, headport=n, tailport=s]\n",
+                        address, edge, count, color, width
                     )
                     .as_bytes(),
                 )?;
             }
         }
 
-        fd.write(b"}")?;
+        for (address, block) in &self.blocks {
+            for (i, anomaly) in block.anomalies().enumerate() {
+                fd.write_all(
+                    format!(
+                        "\tanomaly_{0}_{1} [shape=plaintext][label=\"{2}\"][fontcolor=\"red\"]\n\tnode_{0} -> anomaly_{0}_{1} [style=dashed][color=\"red\", headport=n, tailport=s]\n",
+                        address, i, anomaly
+                    )
+                    .as_bytes(),
+                )?;
+            }
+        }
+
+        if let Some(binary) = binary {
+            let unexecuted = binary.unexecuted(self);
+
+            for (address, block) in &unexecuted.blocks {
+                let mut label = String::new();
+                for (address, instruction) in &block.instructions {
+                    if let Some(name) = symbols.get(*address) {
+                        label.push_str(&format!("{name}:\\n"));
+                    }
+                    label.push_str(&format!("0x{address:0>4X}\t{instruction}\\n"));
+                }
+
+                fd.write_all(
+                    format!(
+                        "\tstatic_{0} [shape=box][label=\"{1}\"][color=\"gray60\"][fontcolor=\"gray60\"][style=dashed]\n",
+                        address, label
+                    )
+                    .as_bytes(),
+                )?;
+            }
+
+            let node = |address: &u64| {
+                if self.blocks.contains_key(address) {
+                    format!("node_{address}")
+                } else {
+                    format!("static_{address}")
+                }
+            };
 
-        let out = format!("/tmp/graph_{0}.jpeg", self.start);
+            for (from, to) in &unexecuted.edges {
+                fd.write_all(
+                    format!(
+                        "\t{0} -> {1} [style=dashed][color=\"gray60\", headport=n, tailport=s]\n",
+                        node(from),
+                        node(to)
+                    )
+                    .as_bytes(),
+                )?;
+            }
+        }
 
-        Command::new("dot")
-            .arg("-Tjpg")
-            .arg("-Gdpi=300")
-            .arg(&filename)
-            .arg("-o")
-            .arg(&out)
-            .spawn()
-            .expect("failed to create graph");
+        fd.write_all(b"}")?;
 
-        Ok(GraphFile {
-            address: self.start,
-            name: self.name.clone(),
-            path: out,
-        })
+        Ok(DotFile { path: filename })
     }
 }
@@ -0,0 +1,66 @@
+use super::{Error, GraphNode};
+use std::process::Command;
+
+/// Renders a DOT file to an image with graphviz and returns where its nodes were laid out.
+///
+/// Only nodes named `node_[address]` are returned. The padding around the graph is removed so
+/// that the positions line up with the edges of the image.
+pub fn render(source: &str, out: &str) -> Result<Vec<GraphNode>, Error> {
+    Command::new("dot")
+        .arg("-Tjpg")
+        .arg("-Gdpi=300")
+        .arg("-Gpad=0")
+        .arg(source)
+        .arg("-o")
+        .arg(out)
+        .status()?;
+
+    let layout = Command::new("dot")
+        .arg("-Tplain")
+        .arg("-Gpad=0")
+        .arg(source)
+        .output()?;
+
+    Ok(parse_plain(&String::from_utf8_lossy(&layout.stdout)))
+}
+
+/// Parses the nodes out of graphviz's plain output, where positions are the centers of the
+/// nodes in inches from the bottom left corner.
+fn parse_plain(plain: &str) -> Vec<GraphNode> {
+    let mut size = None;
+    let mut nodes = Vec::new();
+
+    for line in plain.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        match fields.as_slice() {
+            ["graph", _, width, height, ..] => {
+                size = width.parse::<f32>().ok().zip(height.parse::<f32>().ok());
+            }
+            ["node", name, x, y, width, height, ..] => {
+                let Some((graph_width, graph_height)) = size else {
+                    continue;
+                };
+                let Some(address) = name.strip_prefix("node_").and_then(|a| a.parse().ok()) else {
+                    continue;
+                };
+                let [Ok(x), Ok(y), Ok(width), Ok(height)] =
+                    [x, y, width, height].map(|field| field.parse::<f32>())
+                else {
+                    continue;
+                };
+
+                nodes.push(GraphNode {
+                    address,
+                    x: x / graph_width,
+                    y: y / graph_height,
+                    width: width / graph_width,
+                    height: height / graph_height,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    nodes
+}
@@ -0,0 +1,70 @@
+use super::{taint::Location, Event, Tracer};
+
+impl Tracer {
+    /// The values a location held throughout the trace, each with the step from which it held
+    /// it, starting with what it held before the trace began.
+    ///
+    /// Registers are read as little-endian numbers, of which only the low 64 bits are kept.
+    pub fn history(&self, location: Location) -> Vec<(usize, u64)> {
+        let initial = match location {
+            Location::Register { register, .. } => self
+                .registers
+                .iter()
+                .nth(register)
+                .map_or(0, |register| number(register.value())),
+            Location::Memory(address) => self.initial_memory.get(&address).copied().unwrap_or(0),
+        };
+
+        let mut history = vec![(0, initial)];
+
+        for (step, insn) in self.instructions.iter().enumerate() {
+            let mut written = None;
+
+            for event in insn.events() {
+                match (location, event) {
+                    (Location::Register { thread, register }, Event::RegWrite(reg, value))
+                        if insn.thread() == thread
+                            && self.registers.index(*reg as usize) == register =>
+                    {
+                        written = Some(number(value));
+                    }
+                    (Location::Memory(address), Event::MemWrite(addr, value))
+                        if *addr == address =>
+                    {
+                        written = Some(*value);
+                    }
+                    _ => {}
+                }
+            }
+
+            // Writes of the same value again are not changes.
+            if let Some(value) = written.filter(|value| history.last().unwrap().1 != *value) {
+                history.push((step + 1, value));
+            }
+        }
+
+        history
+    }
+
+    /// The steps of every instruction which read or wrote the memory at an address.
+    pub fn memory_accesses(&self, address: u64) -> Vec<usize> {
+        self.instructions
+            .iter()
+            .enumerate()
+            .filter(|(_, insn)| {
+                insn.events().iter().any(|event| {
+                    matches!(event, Event::MemRead(addr) | Event::MemWrite(addr, _) if *addr == address)
+                })
+            })
+            .map(|(step, _)| step)
+            .collect()
+    }
+}
+
+fn number(bytes: &[u8]) -> u64 {
+    let mut low = [0; 8];
+    let len = bytes.len().min(8);
+    low[..len].copy_from_slice(&bytes[..len]);
+
+    u64::from_le_bytes(low)
+}
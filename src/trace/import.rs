@@ -0,0 +1,345 @@
+use super::{ArchInfo, Error, Event, Instruction, JumpKind, RegisterInfo, TraceFile};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+mod qemu;
+mod tenet;
+
+/// How far apart two instructions may be for the second to be taken as following the first,
+/// when the size of the first is unknown.
+const MAX_INSTRUCTION_SIZE: u64 = 15;
+
+/// How many jumps are kept per thread to match returns against.
+const MAX_CALL_DEPTH: usize = 256;
+
+/// The register sizes that are supported, in bytes.
+const REGISTER_SIZES: [u16; 5] = [1, 2, 4, 8, 16];
+
+impl TraceFile {
+    /// Parses a trace written by ctrlflow, or imports a QEMU log or a Tenet trace.
+    pub fn parse(bytes: &[u8]) -> Result<TraceFile, Error> {
+        let start = bytes.iter().position(|byte| !byte.is_ascii_whitespace());
+
+        if start.is_none_or(|start| bytes[start] == b'{') {
+            return Ok(serde_json::from_slice(bytes)?);
+        }
+
+        let text = String::from_utf8_lossy(bytes);
+
+        if qemu::detect(&text) {
+            qemu::parse(&text)
+        } else if tenet::detect(&text) {
+            tenet::parse(&text)
+        } else {
+            Err(Error::UnknownFormat)
+        }
+    }
+}
+
+/// Gathers the registers and instructions of a trace in another format.
+#[derive(Default)]
+struct Importer {
+    registers: Vec<RegisterInfo>,
+    /// Where each register is in the list, by name.
+    lookup: HashMap<String, u32>,
+    instructions: Vec<Instruction>,
+    /// The register writes of every instruction, before the registers have their final sizes.
+    writes: Vec<Vec<(u32, Vec<u8>)>>,
+    /// The registers before the trace, as far as they are known.
+    initial: HashMap<u32, Vec<u8>>,
+    /// The memory before the trace, as far as it was read.
+    memory: BTreeMap<u64, u64>,
+}
+
+impl Importer {
+    /// Adds an executed instruction, returning its step.
+    fn push(&mut self, thread: u32, addr: u64, insn: String, size: Option<u16>) -> usize {
+        self.instructions.push(Instruction {
+            addr,
+            insn,
+            size,
+            kind: None,
+            events: Vec::new(),
+            thread: Some(thread),
+        });
+        self.writes.push(Vec::new());
+
+        self.instructions.len() - 1
+    }
+
+    /// Records a write of a little-endian value to a register by the instruction at a step,
+    /// adding the register if it is new.
+    ///
+    /// Values too wide to be shown are dropped.
+    fn write(&mut self, step: usize, name: &str, value: Vec<u8>) {
+        if let Some(register) = self.register(name, value.len()) {
+            self.writes[step].push((register, value));
+        }
+    }
+
+    /// Records the value of a register before the trace, adding the register if it is new.
+    fn seed(&mut self, name: &str, value: Vec<u8>) {
+        if let Some(register) = self.register(name, value.len()) {
+            self.initial.insert(register, value);
+        }
+    }
+
+    /// The register with the given name, added if it is new and widened to hold a value of the
+    /// given length unless that is too wide to be shown.
+    fn register(&mut self, name: &str, len: usize) -> Option<u32> {
+        let size = REGISTER_SIZES
+            .into_iter()
+            .find(|size| len <= *size as usize)?;
+
+        let register = match self.lookup.get(name) {
+            Some(register) => *register,
+            None => {
+                let register = self.registers.len() as u32;
+                self.registers.push(RegisterInfo {
+                    name: name.to_owned(),
+                    register,
+                    full_register: None,
+                    size,
+                    value: None,
+                });
+                self.lookup.insert(name.to_owned(), register);
+                register
+            }
+        };
+
+        let info = &mut self.registers[register as usize];
+        info.size = info.size.max(size);
+
+        Some(register)
+    }
+
+    /// Works out the jumps the instructions took and builds the trace, with the register
+    /// writes widened to their registers.
+    fn finish(mut self, sizes: &HashMap<u64, u64>) -> Result<TraceFile, Error> {
+        if self.instructions.is_empty() {
+            return Err(Error::MissingInstruction);
+        }
+
+        derive_kinds(&mut self.instructions, sizes);
+
+        for (insn, writes) in self.instructions.iter_mut().zip(self.writes) {
+            for (register, mut value) in writes {
+                value.resize(self.registers[register as usize].size as usize, 0);
+                insn.events
+                    .push(Event::RegWrite(register, value.into_boxed_slice()));
+            }
+        }
+
+        for (register, mut value) in self.initial {
+            let info = &mut self.registers[register as usize];
+            value.resize(info.size as usize, 0);
+            info.value = Some(value);
+        }
+
+        Ok(TraceFile {
+            info: ArchInfo {
+                registers: self.registers,
+                memory: self.memory.into_iter().collect(),
+            },
+            instructions: self.instructions,
+        })
+    }
+}
+
+/// Parses a hexadecimal number, with or without a `0x` prefix, into little-endian bytes.
+fn parse_hex(value: &str) -> Option<Vec<u8>> {
+    let digits = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+
+    if digits.is_empty() || !digits.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut bytes = Vec::new();
+    let mut end = digits.len();
+
+    while end > 0 {
+        let start = end.saturating_sub(2);
+        bytes.push(u8::from_str_radix(&digits[start..end], 16).ok()?);
+        end = start;
+    }
+
+    Some(bytes)
+}
+
+/// Where execution falls through to from the instructions, by their sizes where they are known
+/// and otherwise as the next instruction that executed, if it is close enough.
+struct Fallthrough<'a> {
+    sizes: &'a HashMap<u64, u64>,
+    executed: BTreeSet<u64>,
+}
+
+impl Fallthrough<'_> {
+    /// Whether execution going from one address to the other is just the next instruction.
+    fn follows(&self, from: u64, to: u64) -> bool {
+        match self.sizes.get(&from) {
+            Some(size) => to == from + size,
+            None => {
+                self.executed.range(from + 1..).next() == Some(&to)
+                    && to - from <= MAX_INSTRUCTION_SIZE
+            }
+        }
+    }
+}
+
+/// Marks the instructions which jumped, by where execution of the same thread went after
+/// them, leaving those already marked as they are.
+///
+/// Whichever jumps execution later came back to right after are taken as calls, and the jumps
+/// that came back as returns, unless those were ever seen to fall through like the branch out
+/// of a loop does. Every other jump is conditional if it was also seen to fall through.
+fn derive_kinds(instructions: &mut [Instruction], sizes: &HashMap<u64, u64>) {
+    let fallthrough = Fallthrough {
+        sizes,
+        executed: instructions.iter().map(|insn| insn.addr).collect(),
+    };
+    let mut successors: HashMap<u64, BTreeSet<u64>> = HashMap::new();
+    // The calls along with the returns that came back from them.
+    let mut returned = HashSet::new();
+    let mut last: HashMap<u32, u64> = HashMap::new();
+    let mut stacks: HashMap<u32, Vec<u64>> = HashMap::new();
+
+    for insn in instructions.iter() {
+        let Some(from) = last.insert(insn.thread(), insn.addr) else {
+            continue;
+        };
+
+        successors.entry(from).or_default().insert(insn.addr);
+
+        if fallthrough.follows(from, insn.addr) {
+            continue;
+        }
+
+        let stack = stacks.entry(insn.thread()).or_default();
+
+        match stack
+            .iter()
+            .rposition(|site| fallthrough.follows(*site, insn.addr))
+        {
+            Some(depth) => {
+                returned.insert((stack[depth], from));
+                stack.truncate(depth);
+            }
+            None => {
+                if stack.len() == MAX_CALL_DEPTH {
+                    stack.remove(0);
+                }
+                stack.push(from);
+            }
+        }
+    }
+
+    let falls_through = |addr: u64| {
+        successors
+            .get(&addr)
+            .is_some_and(|successors| successors.iter().any(|to| fallthrough.follows(addr, *to)))
+    };
+
+    let mut calls = HashSet::new();
+    let mut returns = HashSet::new();
+
+    for (call, ret) in returned {
+        if !falls_through(ret) {
+            calls.insert(call);
+            returns.insert(ret);
+        }
+    }
+
+    for insn in instructions.iter_mut().filter(|insn| insn.kind.is_none()) {
+        let Some(successors) = successors.get(&insn.addr) else {
+            continue;
+        };
+
+        insn.kind = if returns.contains(&insn.addr) {
+            Some(JumpKind::Return)
+        } else if calls.contains(&insn.addr) {
+            Some(JumpKind::Call)
+        } else if !falls_through(insn.addr) {
+            Some(JumpKind::Unconditional)
+        } else if successors.len() > 1 {
+            Some(JumpKind::Conditional)
+        } else {
+            None
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{derive_kinds, Importer};
+    use crate::trace::{Instruction, JumpKind};
+    use std::collections::HashMap;
+
+    /// The instructions at the given addresses, all one byte long.
+    fn instructions(addresses: &[u64]) -> (Vec<Instruction>, HashMap<u64, u64>) {
+        let mut importer = Importer::default();
+        for addr in addresses {
+            importer.push(0, *addr, String::new(), Some(1));
+        }
+
+        let sizes = addresses.iter().map(|addr| (*addr, 1)).collect();
+        (importer.instructions, sizes)
+    }
+
+    fn kinds(instructions: &[Instruction]) -> HashMap<u64, Option<JumpKind>> {
+        instructions
+            .iter()
+            .map(|insn| (insn.addr, insn.kind))
+            .collect()
+    }
+
+    #[test]
+    fn calls_and_returns() {
+        // 0x0 calls 0x100, which returns to right after the call.
+        let (mut instructions, sizes) = instructions(&[0x0, 0x100, 0x101, 0x1, 0x2]);
+        derive_kinds(&mut instructions, &sizes);
+
+        let kinds = kinds(&instructions);
+        assert_eq!(kinds[&0x0], Some(JumpKind::Call));
+        assert_eq!(kinds[&0x100], None);
+        assert_eq!(kinds[&0x101], Some(JumpKind::Return));
+        assert_eq!(kinds[&0x1], None);
+    }
+
+    #[test]
+    fn branches() {
+        // 0x11 jumps back to 0x10 once and falls through the second time, then 0x12 jumps
+        // ahead to 0x20.
+        let (mut instructions, sizes) = instructions(&[0x10, 0x11, 0x10, 0x11, 0x12, 0x20]);
+        derive_kinds(&mut instructions, &sizes);
+
+        let kinds = kinds(&instructions);
+        assert_eq!(kinds[&0x10], None);
+        assert_eq!(kinds[&0x11], Some(JumpKind::Conditional));
+        assert_eq!(kinds[&0x12], Some(JumpKind::Unconditional));
+    }
+
+    #[test]
+    fn unknown_sizes() {
+        // Without sizes, the next instruction executed is taken as following when it is close.
+        let mut importer = Importer::default();
+        for addr in [0x0, 0x4, 0x40, 0x44] {
+            importer.push(0, addr, String::new(), None);
+        }
+        derive_kinds(&mut importer.instructions, &HashMap::new());
+
+        let kinds = kinds(&importer.instructions);
+        assert_eq!(kinds[&0x0], None);
+        assert_eq!(kinds[&0x4], Some(JumpKind::Unconditional));
+    }
+
+    #[test]
+    fn marked_kinds_are_kept() {
+        let (mut instructions, sizes) = instructions(&[0x0, 0x100, 0x101, 0x1]);
+        instructions[0].kind = Some(JumpKind::Unconditional);
+        derive_kinds(&mut instructions, &sizes);
+
+        assert_eq!(instructions[0].kind, Some(JumpKind::Unconditional));
+    }
+}
@@ -0,0 +1,332 @@
+//! Logs of QEMU run with `-d exec,cpu,in_asm`, ideally along with `-d nochain` or
+//! `-one-insn-per-tb` so that every block executed is logged.
+//!
+//! Every block is disassembled once when it is translated:
+//!
+//! ```text
+//! IN: main
+//! 0x00401126:  55                       pushq    %rbp
+//! 0x00401127:  48 89 e5                 movq     %rsp, %rbp
+//! ```
+//!
+//! and logged every time it executes, along with the registers right before it does:
+//!
+//! ```text
+//! Trace 0: 0x7f52c4000100 [00000000/0000000000401126/0000c0b0/ff000000] main
+//! RAX=0000000000000000 RBX=0000000000000000 RCX=00007ffd6a3e2f58 RDX=00007ffd6a3e2f68
+//! ```
+
+use super::{parse_hex, Importer};
+use crate::trace::{Error, JumpKind, TraceFile};
+use std::collections::HashMap;
+
+/// Mnemonics which call, across the architectures QEMU disassembles.
+const CALLS: &[&str] = &["call", "callq", "calll", "bl", "blx", "blr", "jal", "jalr"];
+const RETURNS: &[&str] = &["ret", "retq", "retl", "retn", "iret", "iretq", "eret"];
+
+pub fn detect(text: &str) -> bool {
+    text.lines()
+        .any(|line| line.starts_with("Trace ") || line.starts_with("IN:"))
+}
+
+/// What the lines being read are part of.
+enum Section {
+    Other,
+    /// The disassembly of a block, the instructions so far.
+    Listing(Vec<(u64, String)>),
+    /// The registers of a thread before a block executed, along with the step they changed at,
+    /// which is the last instruction of the thread before the block. Those before the first
+    /// block of the log have no step, being the state the trace starts from.
+    Registers(u32, Option<usize>),
+}
+
+pub fn parse(text: &str) -> Result<TraceFile, Error> {
+    let mut importer = Importer::default();
+    let mut listings: HashMap<u64, Vec<(u64, String)>> = HashMap::new();
+    let mut sizes = HashMap::new();
+    let mut section = Section::Other;
+
+    // The last step of every thread and its registers as they were last logged, along with the
+    // registers every thread starts out with.
+    let mut last: HashMap<u32, usize> = HashMap::new();
+    let mut registers: HashMap<(u32, String), Vec<u8>> = HashMap::new();
+    let mut initial: HashMap<String, Vec<u8>> = HashMap::new();
+
+    for line in text.lines() {
+        if line.starts_with("IN:") {
+            finish(&mut section, &mut listings, &mut sizes);
+            section = Section::Listing(Vec::new());
+            continue;
+        }
+
+        if let Some(trace) = line.strip_prefix("Trace ") {
+            finish(&mut section, &mut listings, &mut sizes);
+
+            let (thread, pc) = parse_trace(trace).ok_or(Error::MalformedQemuLog)?;
+            let listing = listings.get(&pc).ok_or(Error::MalformedQemuLog)?;
+
+            let first = importer.instructions.len();
+
+            for (addr, insn) in listing {
+                let size = sizes.get(addr).and_then(|size| u16::try_from(*size).ok());
+                let step = importer.push(thread, *addr, insn.clone(), size);
+                importer.instructions[step].kind = mnemonic_kind(insn);
+            }
+
+            // The registers before the first block are the initial state, and those before the
+            // first block of another thread are what it changed from that.
+            let step = match last.insert(thread, importer.instructions.len() - 1) {
+                Some(step) => Some(step),
+                None if first == 0 => None,
+                None => Some(first),
+            };
+            section = Section::Registers(thread, step);
+            continue;
+        }
+
+        match &mut section {
+            Section::Listing(listing) => match parse_instruction(line) {
+                Some(instruction) => listing.push(instruction),
+                None if line.trim().is_empty() => finish(&mut section, &mut listings, &mut sizes),
+                None => {}
+            },
+            Section::Registers(thread, step) if line.contains('=') => {
+                for (name, value) in parse_registers(line) {
+                    let previous = registers
+                        .insert((*thread, name.to_owned()), value.clone())
+                        .or_else(|| initial.get(name).cloned());
+
+                    match step {
+                        Some(step) if previous.as_ref() != Some(&value) => {
+                            importer.write(*step, name, value)
+                        }
+                        Some(_) => {}
+                        None => {
+                            initial.insert(name.to_owned(), value.clone());
+                            importer.seed(name, value);
+                        }
+                    }
+                }
+            }
+            Section::Registers(..) => section = Section::Other,
+            Section::Other => {}
+        }
+    }
+
+    importer.finish(&sizes)
+}
+
+/// Stores the listing being read, with the sizes of its instructions but the last.
+fn finish(
+    section: &mut Section,
+    listings: &mut HashMap<u64, Vec<(u64, String)>>,
+    sizes: &mut HashMap<u64, u64>,
+) {
+    let Section::Listing(listing) = std::mem::replace(section, Section::Other) else {
+        return;
+    };
+
+    // Where a listing goes backwards it tells nothing of the size.
+    for pair in listing.windows(2) {
+        if let Some(size) = pair[1].0.checked_sub(pair[0].0) {
+            sizes.insert(pair[0].0, size);
+        }
+    }
+
+    if let Some((start, _)) = listing.first() {
+        listings.insert(*start, listing);
+    }
+}
+
+/// The thread and address of an executed block, out of either
+/// `0: 0x7f52c4000100 [00000000/0000000000401126/0000c0b0/ff000000] main` or the older
+/// `0x7f52c4000100 [0000000000401126] main`.
+fn parse_trace(trace: &str) -> Option<(u32, u64)> {
+    let thread = match trace.split_once(':') {
+        Some((thread, _)) if !thread.contains(' ') => thread.parse().ok()?,
+        _ => 0,
+    };
+
+    let (_, fields) = trace.split_once('[')?;
+    let (fields, _) = fields.split_once(']')?;
+    let fields: Vec<&str> = fields.split('/').collect();
+    let pc = if fields.len() > 1 {
+        fields[1]
+    } else {
+        fields[0]
+    };
+
+    Some((thread, u64::from_str_radix(pc, 16).ok()?))
+}
+
+/// An instruction of a listing without its bytes, leaving out the lines continuing the bytes of
+/// the one before.
+fn parse_instruction(line: &str) -> Option<(u64, String)> {
+    let (address, insn) = line.split_once(':')?;
+    let address = u64::from_str_radix(address.strip_prefix("0x")?, 16).ok()?;
+    let insn = insn.trim();
+
+    let is_bytes = |text: &str| {
+        text.split_whitespace()
+            .all(|token| token.len() == 2 && token.bytes().all(|c| c.is_ascii_hexdigit()))
+    };
+
+    if is_bytes(insn) {
+        return None;
+    }
+
+    // The bytes are set apart from the instruction by a wider gap than its operands are.
+    let insn = match insn.split_once("  ") {
+        Some((bytes, insn)) if is_bytes(bytes) => insn,
+        _ => insn,
+    };
+
+    Some((
+        address,
+        insn.split_whitespace().collect::<Vec<_>>().join(" "),
+    ))
+}
+
+/// The `NAME=value` pairs of a line of registers, leaving out those which are not plain
+/// hexadecimal numbers.
+fn parse_registers(line: &str) -> Vec<(&str, Vec<u8>)> {
+    let mut registers = Vec::new();
+    let mut rest = line;
+
+    while let Some((name, after)) = rest.split_once('=') {
+        // Names may be padded before the sign, as in `R8 =`.
+        let name = name.split_whitespace().last().unwrap_or("");
+        let value = after.split_whitespace().next().unwrap_or("");
+
+        if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric()) {
+            registers.extend(parse_hex(value).map(|value| (name, value)));
+        }
+
+        rest = after.trim_start().get(value.len()..).unwrap_or("");
+    }
+
+    registers
+}
+
+/// What an instruction jumps as going by its mnemonic, if it calls or returns.
+fn mnemonic_kind(insn: &str) -> Option<JumpKind> {
+    let lowercase = insn.to_lowercase();
+
+    for token in lowercase.split_whitespace() {
+        if CALLS.contains(&token) {
+            return Some(JumpKind::Call);
+        }
+        if RETURNS.contains(&token) {
+            return Some(JumpKind::Return);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{finish, parse, parse_instruction, parse_registers, parse_trace, Section};
+    use crate::trace::{Event, JumpKind, Tracer};
+    use std::collections::HashMap;
+
+    const LOG: &str = "\
+IN: main
+0x00401126:  55                       pushq    %rbp
+0x00401127:  e8 10 00 00 00           callq    0x40113c
+
+IN: f
+0x0040113c:  c3                       retq
+
+Trace 0: 0x7f52c4000100 [00000000/0000000000401126/0000c0b0/ff000000] main
+RAX=0000000000000001 RBX=0000000000000002
+R8 =0000000000000003 R9 =0000000000000004
+Trace 0: 0x7f52c4000200 [00000000/000000000040113c/0000c0b0/ff000000] f
+RAX=0000000000000005 RBX=0000000000000002
+R8 =0000000000000003 R9 =0000000000000004
+";
+
+    #[test]
+    fn trace_lines() {
+        assert_eq!(
+            parse_trace("0: 0x7f52c4000100 [00000000/0000000000401126/0000c0b0/ff000000] main"),
+            Some((0, 0x401126))
+        );
+        assert_eq!(
+            parse_trace("3: 0x7f52c4000100 [00000000/0000000000401126/0000c0b0/ff000000]"),
+            Some((3, 0x401126))
+        );
+        assert_eq!(
+            parse_trace("0x7f52c4000100 [0000000000401126] main"),
+            Some((0, 0x401126))
+        );
+        assert_eq!(parse_trace("0x7f52c4000100 main"), None);
+    }
+
+    #[test]
+    fn registers() {
+        let registers = parse_registers("R8 =0000000000000003 R9 =00000000000000ff FL=0");
+        let names: Vec<&str> = registers.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["R8", "R9", "FL"]);
+        assert_eq!(registers[1].1[0], 0xff);
+
+        // Flags spelled out are not numbers.
+        let registers = parse_registers("CCS=00000044 CCO=EFLAGS EFL=00000246 [---Z-P-]");
+        let names: Vec<&str> = registers.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["CCS", "EFL"]);
+    }
+
+    #[test]
+    fn instructions() {
+        assert_eq!(
+            parse_instruction("0x00401127:  e8 10 00 00 00           callq    0x40113c"),
+            Some((0x401127, String::from("callq 0x40113c")))
+        );
+        assert_eq!(parse_instruction("0x00401127:  e8 10 00 00"), None);
+        assert_eq!(parse_instruction("OBJD-T: 554889e5"), None);
+    }
+
+    #[test]
+    fn initial_registers() {
+        let tracer = Tracer::new(parse(LOG).unwrap(), true, Default::default()).unwrap();
+
+        let initial: Vec<(String, u8)> = tracer
+            .initial_registers()
+            .iter()
+            .map(|register| (register.name().to_owned(), register.value()[0]))
+            .collect();
+        assert_eq!(
+            initial,
+            [
+                (String::from("RAX"), 1),
+                (String::from("RBX"), 2),
+                (String::from("R8"), 3),
+                (String::from("R9"), 4)
+            ]
+        );
+
+        // Only what changed since is written, by the last instruction before the block.
+        assert!(tracer.instructions[0].events().is_empty());
+        assert!(matches!(
+            tracer.instructions[1].events(),
+            [Event::RegWrite(0, value)] if value[0] == 5
+        ));
+        assert_eq!(tracer.instructions[1].kind, Some(JumpKind::Call));
+        assert_eq!(tracer.instructions[2].kind, Some(JumpKind::Return));
+    }
+
+    #[test]
+    fn listing_backwards() {
+        let mut section = Section::Listing(vec![
+            (0x401130, String::from("nop")),
+            (0x401126, String::from("nop")),
+            (0x401127, String::from("ret")),
+        ]);
+        let mut listings = HashMap::new();
+        let mut sizes = HashMap::new();
+        finish(&mut section, &mut listings, &mut sizes);
+
+        assert_eq!(sizes, HashMap::from([(0x401126, 1)]));
+        assert_eq!(listings[&0x401130].len(), 3);
+    }
+}
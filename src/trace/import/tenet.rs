@@ -0,0 +1,236 @@
+//! Traces in the text format of Tenet, as written by its tracers and
+//! [`crate::export::tenet`]:
+//!
+//! ```text
+//! rax=0x0,rbx=0x7ffd6a3e2f58,rip=0x401126
+//! rsp=0x7ffd6a3e2e50,rip=0x401127,mw=0x7ffd6a3e2e50:0000000000000000
+//! ```
+//!
+//! Every line is an instruction, with the registers which changed since the line before along
+//! with the instruction pointer, followed by the memory it read and wrote.
+
+use super::{parse_hex, Importer};
+use crate::trace::{Error, Event, TraceFile};
+use std::collections::{HashMap, HashSet};
+
+/// How many bytes of memory every address holds, wider accesses spanning the addresses after.
+const CELL_SIZE: usize = 8;
+
+/// What the instruction pointer is called, in the order they are looked for.
+const PROGRAM_COUNTERS: &[&str] = &["rip", "eip", "pc"];
+
+pub fn detect(text: &str) -> bool {
+    text.lines().next().is_some_and(|line| {
+        line.split(',').all(|field| {
+            field
+                .split_once('=')
+                .is_some_and(|(_, value)| !value.is_empty())
+        })
+    })
+}
+
+pub fn parse(text: &str) -> Result<TraceFile, Error> {
+    let lines: Vec<Vec<(&str, &str)>> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            line.split(',')
+                .filter_map(|field| field.split_once('='))
+                .map(|(name, value)| (name.trim(), value.trim()))
+                .collect()
+        })
+        .collect();
+
+    let pc = program_counter(&lines).ok_or(Error::MalformedTenet)?;
+    let mut importer = Importer::default();
+    let mut written = HashSet::new();
+
+    for (step, fields) in lines.iter().enumerate() {
+        let addr = fields
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(pc))
+            .and_then(|(_, value)| parse_value(value))
+            .ok_or(Error::MalformedTenet)?;
+
+        importer.push(0, addr, String::new(), None);
+
+        for (name, value) in fields {
+            let lowercase = name.to_lowercase();
+
+            match lowercase.as_str() {
+                "mr" | "mw" | "mrw" => {
+                    let (address, bytes) = value.split_once(':').ok_or(Error::MalformedTenet)?;
+                    let address = parse_value(address).ok_or(Error::MalformedTenet)?;
+                    let cells = parse_bytes(bytes).ok_or(Error::MalformedTenet)?;
+                    let events = &mut importer.instructions[step].events;
+
+                    for (address, value) in (address..).step_by(CELL_SIZE).zip(cells) {
+                        // What is read before it is ever written was there before the trace.
+                        if lowercase.contains('r') {
+                            events.push(Event::MemRead(address));
+
+                            if !written.contains(&address) {
+                                importer.memory.entry(address).or_insert(value);
+                            }
+                        }
+                        if lowercase.contains('w') {
+                            events.push(Event::MemWrite(address, value));
+                            written.insert(address);
+                        }
+                    }
+                }
+                _ => {
+                    let value = parse_hex(value).ok_or(Error::MalformedTenet)?;
+
+                    // The registers of a line are the ones the instruction before it wrote,
+                    // those of the first line are the initial state.
+                    match step.checked_sub(1) {
+                        Some(step) => importer.write(step, name, value),
+                        None => importer.seed(name, value),
+                    }
+                }
+            }
+        }
+    }
+
+    importer.finish(&HashMap::new())
+}
+
+/// The name of the instruction pointer, which is a register on every line.
+fn program_counter<'a>(lines: &[Vec<(&'a str, &str)>]) -> Option<&'a str> {
+    let everywhere: Vec<&str> = lines
+        .first()?
+        .iter()
+        .map(|(name, _)| *name)
+        .filter(|name| {
+            lines
+                .iter()
+                .all(|fields| fields.iter().any(|(other, _)| other == name))
+        })
+        .collect();
+
+    PROGRAM_COUNTERS
+        .iter()
+        .find_map(|pc| everywhere.iter().find(|name| name.eq_ignore_ascii_case(pc)))
+        .or(everywhere.first())
+        .copied()
+}
+
+fn parse_value(value: &str) -> Option<u64> {
+    let value = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+
+    u64::from_str_radix(value, 16).ok()
+}
+
+/// Memory as a string of bytes in the order they are in memory, read little-endian into a value
+/// for every cell it spans.
+fn parse_bytes(bytes: &str) -> Option<Vec<u64>> {
+    if bytes.is_empty()
+        || !bytes.len().is_multiple_of(2)
+        || !bytes.bytes().all(|byte| byte.is_ascii_hexdigit())
+    {
+        return None;
+    }
+
+    bytes
+        .as_bytes()
+        .chunks(CELL_SIZE * 2)
+        .map(|cell| {
+            let mut value = [0u8; CELL_SIZE];
+            for (byte, digits) in value.iter_mut().zip(cell.chunks(2)) {
+                *byte = u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()?;
+            }
+
+            Some(u64::from_le_bytes(value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, parse_bytes};
+    use crate::trace::{Event, Tracer};
+
+    fn tracer(text: &str) -> Tracer {
+        Tracer::new(parse(text).unwrap(), true, Default::default()).unwrap()
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(parse_bytes("0100"), Some(vec![1]));
+        assert_eq!(parse_bytes("efbeadde"), Some(vec![0xdeadbeef]));
+        assert_eq!(
+            parse_bytes("000000000000000001"),
+            Some(vec![0, 1]),
+            "wider accesses span several cells"
+        );
+        assert_eq!(parse_bytes("123"), None);
+        assert_eq!(parse_bytes("zz"), None);
+        assert_eq!(parse_bytes(""), None);
+    }
+
+    /// The memory accesses of the instruction at a step.
+    fn accesses(tracer: &Tracer, step: usize) -> Vec<&Event> {
+        tracer.instructions[step]
+            .events()
+            .iter()
+            .filter(|event| matches!(event, Event::MemRead(_) | Event::MemWrite(..)))
+            .collect()
+    }
+
+    #[test]
+    fn read_and_written() {
+        let tracer = tracer("rax=0x1,rip=0x10\nrip=0x11,mrw=0x100:0200\nrip=0x12,mr=0x100:0300\n");
+
+        assert_eq!(
+            accesses(&tracer, 1),
+            [&Event::MemRead(0x100), &Event::MemWrite(0x100, 2)]
+        );
+        // Only what was read before being written was there before the trace.
+        assert_eq!(tracer.initial_memory.get(&0x100), Some(&2));
+    }
+
+    #[test]
+    fn read_before_written() {
+        let tracer = tracer("rip=0x10,mr=0x100:05\nrip=0x11,mw=0x100:06\nrip=0x12,mr=0x100:06\n");
+
+        assert_eq!(tracer.initial_memory.get(&0x100), Some(&5));
+        assert_eq!(accesses(&tracer, 1), [&Event::MemWrite(0x100, 6)]);
+    }
+
+    #[test]
+    fn wide_accesses() {
+        let tracer = tracer("rip=0x10,mw=0x100:0100000000000000020000000000000003\n");
+
+        assert_eq!(
+            accesses(&tracer, 0),
+            [
+                &Event::MemWrite(0x100, 1),
+                &Event::MemWrite(0x108, 2),
+                &Event::MemWrite(0x110, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn initial_registers() {
+        let tracer = tracer("rax=0x7,rip=0x10\nrax=0x8,rip=0x11\nrip=0x12\n");
+
+        let rax = tracer.initial_registers().iter().next().unwrap();
+        assert_eq!((rax.name(), rax.value()[0]), ("rax", 7));
+
+        // The registers of a line were written by the instruction before it.
+        assert!(tracer.instructions[0]
+            .events()
+            .iter()
+            .any(|event| matches!(event, Event::RegWrite(0, value) if value[0] == 8)));
+    }
+
+    #[test]
+    fn missing_program_counter() {
+        assert!(parse("rax=0x1\nrbx=0x2\n").is_err());
+    }
+}
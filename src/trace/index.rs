@@ -0,0 +1,315 @@
+use super::{
+    cfg::ControlFlowGraph, instructions::Instructions, register::Registers, Checkpoint, Error,
+    Instruction, Tracer, Xref,
+};
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use memmap2::Mmap;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Identifies a sidecar index and the version of its layout.
+const MAGIC: &[u8; 8] = b"TVINDEXB";
+
+/// The magic, the content hash of the trace, the instruction count and the metadata length.
+const HEADER_LEN: usize = 32;
+
+/// How many instruction records are decoded at once.
+const CHUNK_LEN: usize = 4096;
+
+/// Everything derived from a trace that is expensive to rebuild, as stored in its sidecar.
+///
+/// The sidecar is laid out as the header, the bincode encoded metadata (step lists, cross
+/// references, checkpoints, initial memory, the control flow graph and initial registers), a
+/// table of `count + 1` offsets and finally the bincode encoded instruction records the
+/// offsets point into.
+pub struct TraceIndex {
+    pub instructions: Instructions,
+    pub steps: BTreeMap<u64, Vec<usize>>,
+    pub thread_steps: BTreeMap<u32, Vec<usize>>,
+    pub xrefs: BTreeMap<u64, Vec<Xref>>,
+    pub checkpoints: Vec<Checkpoint>,
+    pub initial_memory: BTreeMap<u64, u64>,
+    pub cfg: ControlFlowGraph,
+    pub registers: Registers,
+}
+
+impl TraceIndex {
+    /// Loads the index at the given path if it exists and was built from a trace with the same
+    /// content hash.
+    pub fn load(path: &Path, hash: u64) -> Result<Option<TraceIndex>, Error> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(_) => return Ok(None),
+        };
+        // SAFETY: the index is only ever read, a concurrent rewrite would fail the hash check
+        // or the bounds checks below.
+        let bytes = unsafe { Mmap::map(&file)? };
+
+        if bytes.len() < HEADER_LEN || &bytes[..8] != MAGIC {
+            return Err(Error::MalformedIndex);
+        }
+
+        if LittleEndian::read_u64(&bytes[8..16]) != hash {
+            return Ok(None);
+        }
+
+        let count = LittleEndian::read_u64(&bytes[16..24]) as usize;
+        let meta_len = LittleEndian::read_u64(&bytes[24..32]) as usize;
+
+        let meta = bytes
+            .get(HEADER_LEN..HEADER_LEN + meta_len)
+            .ok_or(Error::MalformedIndex)?;
+        let (steps, thread_steps, xrefs, checkpoints, initial_memory, cfg, registers) =
+            bincode::deserialize(meta)?;
+
+        let records = Records::new(bytes, HEADER_LEN + meta_len, count)?;
+
+        Ok(Some(TraceIndex {
+            instructions: Instructions::from_records(records),
+            steps,
+            thread_steps,
+            xrefs,
+            checkpoints,
+            initial_memory,
+            cfg,
+            registers,
+        }))
+    }
+
+    /// Writes the index for an already constructed tracer to the given path.
+    ///
+    /// The index is written next to it and then moved over it, so that a tracer still reading
+    /// the records of the one it replaces keeps them.
+    pub fn write(path: &Path, hash: u64, tracer: &Tracer) -> Result<(), Error> {
+        let meta = bincode::serialize(&(
+            &tracer.steps,
+            &tracer.thread_steps,
+            &tracer.xrefs,
+            &tracer.checkpoints,
+            &tracer.initial_memory,
+            &tracer.cfg,
+            &tracer.registers,
+        ))?;
+
+        let mut records = Vec::new();
+        let mut offsets = vec![0u64];
+        for insn in &tracer.instructions {
+            bincode::serialize_into(&mut records, insn)?;
+            offsets.push(records.len() as u64);
+        }
+
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+
+        let mut fd = BufWriter::new(File::create(&partial)?);
+
+        fd.write_all(MAGIC)?;
+        fd.write_u64::<LittleEndian>(hash)?;
+        fd.write_u64::<LittleEndian>(tracer.instructions.len() as u64)?;
+        fd.write_u64::<LittleEndian>(meta.len() as u64)?;
+        fd.write_all(&meta)?;
+        for offset in offsets {
+            fd.write_u64::<LittleEndian>(offset)?;
+        }
+        fd.write_all(&records)?;
+        fd.flush()?;
+        drop(fd);
+
+        std::fs::rename(&partial, path)?;
+
+        Ok(())
+    }
+}
+
+/// The instruction records of an index, which are decoded in chunks as they are first needed
+/// rather than all up front.
+pub struct Records {
+    bytes: Mmap,
+    /// Where the table of offsets starts, the records following right after it.
+    table: usize,
+    count: usize,
+    chunks: Vec<OnceLock<Box<[Instruction]>>>,
+}
+
+impl Records {
+    /// Checks that the table of offsets at the given position is in bounds and that every
+    /// record it points to is too.
+    fn new(bytes: Mmap, table: usize, count: usize) -> Result<Records, Error> {
+        let records = count
+            .checked_add(1)
+            .and_then(|offsets| offsets.checked_mul(8))
+            .and_then(|len| table.checked_add(len))
+            .filter(|records| *records <= bytes.len())
+            .ok_or(Error::MalformedIndex)?;
+
+        let mut previous = 0;
+        for i in 0..=count {
+            let offset = LittleEndian::read_u64(&bytes[table + i * 8..]) as usize;
+            if offset < previous || offset > bytes.len() - records {
+                return Err(Error::MalformedIndex);
+            }
+            previous = offset;
+        }
+
+        Ok(Records {
+            bytes,
+            table,
+            count,
+            chunks: (0..count.div_ceil(CHUNK_LEN))
+                .map(|_| OnceLock::new())
+                .collect(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn get(&self, step: usize) -> &Instruction {
+        let chunk = self.chunks[step / CHUNK_LEN].get_or_init(|| self.decode(step / CHUNK_LEN));
+        &chunk[step % CHUNK_LEN]
+    }
+
+    /// Decodes every record, reusing the chunks which already were.
+    pub fn into_vec(mut self) -> Vec<Instruction> {
+        let chunks = std::mem::take(&mut self.chunks);
+
+        chunks
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, chunk)| {
+                chunk
+                    .into_inner()
+                    .unwrap_or_else(|| self.decode(i))
+                    .into_vec()
+            })
+            .collect()
+    }
+
+    /// Decodes a chunk of records.
+    ///
+    /// The records were written along with the rest of the index and their bounds checked on
+    /// load, so one that does not decode means the index was corrupted since.
+    fn decode(&self, chunk: usize) -> Box<[Instruction]> {
+        let offset = |i: usize| LittleEndian::read_u64(&self.bytes[self.table + i * 8..]) as usize;
+        let records = &self.bytes[self.table + (self.count + 1) * 8..];
+
+        (chunk * CHUNK_LEN..((chunk + 1) * CHUNK_LEN).min(self.count))
+            .map(|i| {
+                bincode::deserialize(&records[offset(i)..offset(i + 1)])
+                    .expect("the instruction records of the index are corrupted")
+            })
+            .collect()
+    }
+}
+
+/// The path of the sidecar index for a trace, which sits right next to it.
+pub fn sidecar_path(trace: &Path) -> PathBuf {
+    let mut path = trace.as_os_str().to_owned();
+    path.push(".tvidx");
+    PathBuf::from(path)
+}
+
+/// A 64-bit FNV-1a hash of the trace contents, used to tell when an index has gone stale.
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{content_hash, sidecar_path, TraceIndex};
+    use crate::trace::{tests::counting as trace, TraceFile, Tracer, CHECKPOINT_INTERVAL};
+    use std::path::PathBuf;
+
+    /// A path of its own for every test, as they run at the same time.
+    fn path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("traceview-{name}-{0}", std::process::id()))
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = trace(CHECKPOINT_INTERVAL * 2 + 100);
+        let hash = content_hash(text.as_bytes());
+        let tracer = Tracer::new(
+            TraceFile::parse(text.as_bytes()).unwrap(),
+            true,
+            Default::default(),
+        )
+        .unwrap();
+
+        let path = path("round-trip");
+        TraceIndex::write(&path, hash, &tracer).unwrap();
+        let index = TraceIndex::load(&path, hash).unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(index.instructions.len(), tracer.instructions.len());
+        for (loaded, insn) in index.instructions.iter().zip(&tracer.instructions) {
+            assert_eq!(loaded.addr(), insn.addr());
+            assert_eq!(loaded.events(), insn.events());
+        }
+        assert_eq!(index.steps, tracer.steps);
+        assert_eq!(index.thread_steps, tracer.thread_steps);
+        assert_eq!(index.initial_memory, tracer.initial_memory);
+        assert_eq!(index.checkpoints.len(), tracer.checkpoints.len());
+        for (loaded, checkpoint) in index.checkpoints.iter().zip(&tracer.checkpoints) {
+            assert_eq!(loaded.before, checkpoint.before);
+            assert_eq!(loaded.after, checkpoint.after);
+        }
+    }
+
+    #[test]
+    fn stale_hash() {
+        let text = trace(10);
+        let hash = content_hash(text.as_bytes());
+        let tracer = Tracer::new(
+            TraceFile::parse(text.as_bytes()).unwrap(),
+            true,
+            Default::default(),
+        )
+        .unwrap();
+
+        let path = path("stale-hash");
+        TraceIndex::write(&path, hash, &tracer).unwrap();
+        let stale = TraceIndex::load(&path, hash ^ 1).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(stale.is_none());
+    }
+
+    #[test]
+    fn malformed() {
+        let path = path("malformed");
+        std::fs::write(&path, b"TVINDEX").unwrap();
+        let loaded = TraceIndex::load(&path, 0);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(loaded.is_err());
+    }
+
+    #[test]
+    fn rebuilt_once_changed() {
+        let path = path("rebuilt");
+        let sidecar = sidecar_path(&path);
+
+        std::fs::write(&path, trace(10)).unwrap();
+        let first = Tracer::load(&path, true).unwrap();
+        assert!(sidecar.exists());
+
+        std::fs::write(&path, trace(20)).unwrap();
+        let second = Tracer::load(&path, true).unwrap();
+        let reopened = Tracer::load(&path, true).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&sidecar).unwrap();
+
+        assert_eq!(first.instructions.len(), 10);
+        assert_eq!(second.instructions.len(), 20);
+        assert_eq!(reopened.instructions.len(), 20);
+    }
+}
@@ -0,0 +1,111 @@
+#[cfg(not(target_arch = "wasm32"))]
+use super::index::Records;
+use super::Instruction;
+use std::ops::{Index, Range};
+
+/// The instructions of a trace, either parsed up front or decoded out of the records of an index
+/// as they are first needed.
+#[derive(Default)]
+pub struct Instructions {
+    parsed: Vec<Instruction>,
+    #[cfg(not(target_arch = "wasm32"))]
+    records: Option<Records>,
+}
+
+impl Instructions {
+    /// The instructions stored in an index.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(super) fn from_records(records: Records) -> Instructions {
+        Instructions {
+            parsed: Vec::new(),
+            records: Some(records),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(records) = &self.records {
+            return records.len();
+        }
+
+        self.parsed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, step: usize) -> Option<&Instruction> {
+        (step < self.len()).then(|| &self[step])
+    }
+
+    /// The instructions in the order they were executed.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            instructions: self,
+            steps: 0..self.len(),
+        }
+    }
+
+    /// Appends instructions, decoding whatever is left of an index first.
+    pub fn extend(&mut self, insns: Vec<Instruction>) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(records) = self.records.take() {
+            self.parsed = records.into_vec();
+        }
+
+        self.parsed.extend(insns);
+    }
+}
+
+impl Index<usize> for Instructions {
+    type Output = Instruction;
+
+    fn index(&self, step: usize) -> &Instruction {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(records) = &self.records {
+            return records.get(step);
+        }
+
+        &self.parsed[step]
+    }
+}
+
+impl<'a> IntoIterator for &'a Instructions {
+    type Item = &'a Instruction;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// Goes through instructions by step, so that skipping over some does not decode them.
+pub struct Iter<'a> {
+    instructions: &'a Instructions,
+    steps: Range<usize>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a Instruction;
+
+    fn next(&mut self) -> Option<&'a Instruction> {
+        self.steps.next().map(|step| &self.instructions[step])
+    }
+
+    fn nth(&mut self, n: usize) -> Option<&'a Instruction> {
+        self.steps.nth(n).map(|step| &self.instructions[step])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.steps.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.steps.next_back().map(|step| &self.instructions[step])
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
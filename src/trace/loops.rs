@@ -0,0 +1,252 @@
+use super::{dominators::Dominators, function::Function, Tracer};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// A natural loop of a function, the blocks that can jump back to a header which dominates
+/// them, with those of every back edge to the same header taken as one loop.
+pub struct Loop {
+    pub header: u64,
+    /// The blocks of the loop, the header included.
+    pub blocks: BTreeSet<u64>,
+    /// The blocks jumping back to the header, and how many times they did.
+    pub back_edges: Vec<(u64, u64)>,
+    /// How many times the loop was entered from outside of it.
+    pub entries: u64,
+    /// The header of the innermost loop this one is nested in.
+    pub parent: Option<u64>,
+}
+
+impl Loop {
+    /// How many times the body of the loop was run, once for every time it was entered and
+    /// again for every jump back to the header.
+    pub fn iterations(&self) -> u64 {
+        self.entries + self.back_edges.iter().map(|(_, count)| count).sum::<u64>()
+    }
+}
+
+/// A time the header of a loop was executed.
+pub struct Iteration {
+    pub step: usize,
+    /// Whether the loop was entered from outside of it rather than jumped back to.
+    pub entry: bool,
+}
+
+impl Function {
+    /// Where each block jumps to.
+    fn successors(&self) -> BTreeMap<u64, Vec<u64>> {
+        self.blocks
+            .iter()
+            .map(|(start, block)| (*start, block.edges().map(|(_, to, _)| *to).collect()))
+            .collect()
+    }
+
+    /// The dominators of the blocks, from where the function starts.
+    pub fn dominators(&self) -> Dominators {
+        Dominators::new(&[self.start], &self.successors())
+    }
+
+    /// The post-dominators of the blocks, from the blocks the function leaves from. Blocks
+    /// which never reached one of those, such as those of a loop the trace ended in, have none.
+    pub fn post_dominators(&self) -> Dominators {
+        let mut predecessors: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+        for (from, tos) in self.successors() {
+            for to in tos {
+                predecessors.entry(to).or_default().push(from);
+            }
+        }
+
+        let exits: Vec<u64> = self
+            .blocks
+            .iter()
+            .filter(|(_, block)| block.edges().next().is_none())
+            .map(|(start, _)| *start)
+            .collect();
+
+        Dominators::new(&exits, &predecessors)
+    }
+
+    /// The natural loops of the function, by their header.
+    pub fn loops(&self) -> Vec<Loop> {
+        let dominators = self.dominators();
+        let mut loops: BTreeMap<u64, Loop> = BTreeMap::new();
+
+        for (from, block) in &self.blocks {
+            for (_, to, count) in block.edges() {
+                if !dominators.dominates(*to, *from) {
+                    continue;
+                }
+
+                loops
+                    .entry(*to)
+                    .or_insert_with(|| Loop {
+                        header: *to,
+                        blocks: BTreeSet::from([*to]),
+                        back_edges: Vec::new(),
+                        entries: 0,
+                        parent: None,
+                    })
+                    .back_edges
+                    .push((*from, *count));
+            }
+        }
+
+        let mut predecessors: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+        for (from, block) in &self.blocks {
+            for (_, to, _) in block.edges() {
+                predecessors.entry(*to).or_default().push(*from);
+            }
+        }
+
+        for natural in loops.values_mut() {
+            // Everything that reaches a back edge without going through the header.
+            let mut pending: Vec<u64> = natural.back_edges.iter().map(|(from, _)| *from).collect();
+
+            while let Some(block) = pending.pop() {
+                if natural.blocks.insert(block) {
+                    pending.extend(predecessors.get(&block).into_iter().flatten());
+                }
+            }
+
+            natural.entries = self
+                .blocks
+                .iter()
+                .filter(|(from, _)| !natural.blocks.contains(from))
+                .flat_map(|(_, block)| block.edges())
+                .filter(|(_, to, _)| *to == natural.header)
+                .map(|(_, _, count)| count)
+                .sum();
+
+            // The function is entered every time it is called, or once by the thread it
+            // starts.
+            if natural.header == self.start {
+                natural.entries += self.stats.calls.max(1);
+            }
+        }
+
+        let parents: Vec<(u64, Option<u64>)> = loops
+            .values()
+            .map(|natural| {
+                let parent = loops
+                    .values()
+                    .filter(|other| {
+                        other.header != natural.header && other.blocks.is_superset(&natural.blocks)
+                    })
+                    .min_by_key(|other| other.blocks.len())
+                    .map(|other| other.header);
+
+                (natural.header, parent)
+            })
+            .collect();
+
+        for (header, parent) in parents {
+            if let Some(natural) = loops.get_mut(&header) {
+                natural.parent = parent;
+            }
+        }
+
+        loops.into_values().collect()
+    }
+}
+
+impl Tracer {
+    /// Every time a thread executed the header of a loop of the given function, in order.
+    pub fn iterations(&self, function: &Function, natural: &Loop, thread: u32) -> Vec<Iteration> {
+        let (Some(steps), Some(thread_steps)) = (
+            self.steps.get(&natural.header),
+            self.thread_steps.get(&thread),
+        ) else {
+            return Vec::new();
+        };
+
+        // The instructions of the loop, to tell jumps back to the header from entries.
+        let addresses: HashSet<u64> = natural
+            .blocks
+            .iter()
+            .filter_map(|block| function.blocks.get(block))
+            .flat_map(|block| block.block.keys().copied())
+            .collect();
+
+        steps
+            .iter()
+            .filter(|step| self.instructions[**step].thread() == thread)
+            .map(|step| {
+                let previous = thread_steps.partition_point(|other| other < step);
+                let entry = previous == 0
+                    || !addresses.contains(&self.instructions[thread_steps[previous - 1]].addr());
+
+                Iteration { step: *step, entry }
+            })
+            .collect()
+    }
+}
+
+/// How many loops every block is in.
+pub fn depths(loops: &[Loop]) -> BTreeMap<u64, usize> {
+    let mut depths = BTreeMap::new();
+
+    for natural in loops {
+        for block in &natural.blocks {
+            *depths.entry(*block).or_default() += 1;
+        }
+    }
+
+    depths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::depths;
+    use crate::trace::{TraceFile, Tracer};
+
+    /// A Tenet trace which jumps from 0x10 to the loop from 0x20 to 0x21, goes around it three
+    /// times and then leaves it for 0x22.
+    const LOOP: &str = "pc=0x10\npc=0x20\npc=0x21\npc=0x20\npc=0x21\npc=0x20\npc=0x21\npc=0x22\n";
+
+    fn tracer(text: &str) -> Tracer {
+        let trace = TraceFile::parse(text.as_bytes()).unwrap();
+        Tracer::new(trace, true, Default::default()).unwrap()
+    }
+
+    #[test]
+    fn natural_loop() {
+        let tracer = tracer(LOOP);
+        let function = tracer.cfg.function(0x10).unwrap();
+        let loops = function.loops();
+
+        assert_eq!(loops.len(), 1);
+        let natural = &loops[0];
+        assert_eq!(natural.header, 0x20);
+        assert!(natural.blocks.contains(&0x20));
+        assert!(!natural.blocks.contains(&0x10));
+        assert_eq!(natural.back_edges, [(0x20, 2)]);
+        assert_eq!(natural.entries, 1);
+        assert_eq!(natural.iterations(), 3);
+        assert_eq!(natural.parent, None);
+
+        let depths = depths(&loops);
+        assert_eq!(depths.get(&0x20), Some(&1));
+        assert_eq!(depths.get(&0x10), None);
+    }
+
+    #[test]
+    fn iterations() {
+        let tracer = tracer(LOOP);
+        let function = tracer.cfg.function(0x10).unwrap();
+        let loops = function.loops();
+
+        let iterations = tracer.iterations(function, &loops[0], 0);
+        let steps: Vec<(usize, bool)> = iterations
+            .iter()
+            .map(|iteration| (iteration.step, iteration.entry))
+            .collect();
+        assert_eq!(steps, [(1, true), (3, false), (5, false)]);
+    }
+
+    #[test]
+    fn without_loops() {
+        let tracer = tracer("pc=0x10\npc=0x11\npc=0x12\n");
+        let function = tracer.cfg.function(0x10).unwrap();
+
+        assert!(function.loops().is_empty());
+        assert_eq!(function.dominators().immediate(0x10), None);
+    }
+}
@@ -0,0 +1,78 @@
+use super::Tracer;
+
+/// How many times the code of the trace was executed, per instruction and per block, the
+/// functions keeping their own [`Stats`](super::function::Stats).
+pub struct Profile {
+    /// Every executed instruction in ascending order of address.
+    pub instructions: Vec<Hotspot>,
+    /// Every block of every function in ascending order of function and address.
+    pub blocks: Vec<BlockHotspot>,
+    /// How many instructions the whole trace executed.
+    pub total: u64,
+}
+
+/// How many times the instruction at an address was executed.
+pub struct Hotspot {
+    pub address: u64,
+    pub executions: u64,
+    /// The first step at which it was executed.
+    pub first_step: usize,
+}
+
+/// How many times a block was executed.
+pub struct BlockHotspot {
+    /// The starting address of the function the block is in.
+    pub function: u64,
+    pub start: u64,
+    /// How many instructions the block has.
+    pub size: usize,
+    /// How many times execution went through the start of the block.
+    pub executions: u64,
+    /// How many of its instructions were executed in total.
+    pub instructions: u64,
+}
+
+impl Tracer {
+    /// Counts how many times every instruction and block was executed.
+    ///
+    /// Instructions are counted by address, so code shared by more than one function counts
+    /// towards the blocks of each.
+    pub fn profile(&self) -> Profile {
+        let instructions = self
+            .steps
+            .iter()
+            .map(|(address, steps)| Hotspot {
+                address: *address,
+                executions: steps.len() as u64,
+                first_step: steps[0],
+            })
+            .collect();
+
+        let executions = |address: &u64| {
+            self.steps
+                .get(address)
+                .map_or(0, |steps| steps.len() as u64)
+        };
+
+        let mut blocks: Vec<BlockHotspot> = self
+            .cfg
+            .functions()
+            .flat_map(|function| {
+                function.blocks.values().map(|block| BlockHotspot {
+                    function: function.start,
+                    start: block.start,
+                    size: block.block.len(),
+                    executions: executions(&block.start),
+                    instructions: block.block.keys().map(executions).sum(),
+                })
+            })
+            .collect();
+        blocks.sort_by_key(|block| (block.function, block.start));
+
+        Profile {
+            instructions,
+            blocks,
+            total: self.instructions.len() as u64,
+        }
+    }
+}
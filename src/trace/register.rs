@@ -1,8 +1,11 @@
 use std::collections::BTreeMap;
+use std::fmt;
 
 use super::RegisterInfo;
 use byteorder::{ByteOrder, LittleEndian};
+use serde::{Deserialize, Serialize};
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Registers {
     registers: Vec<Register>,
     lookup: BTreeMap<usize, usize>,
@@ -18,7 +21,13 @@ impl Registers {
             if let Some(reg) = register.full_register {
                 lookup.insert(register.register as usize, reg as usize);
             } else {
-                registers.push(Register::new(register.name.clone(), register.size as usize))
+                let mut reg = Register::new(register.name.clone(), register.size as usize);
+                if let Some(value) = &register.value {
+                    let mut value = value.clone();
+                    value.resize(register.size as usize, 0);
+                    reg.write(&value);
+                }
+                registers.push(reg);
             }
         }
 
@@ -29,7 +38,12 @@ impl Registers {
         // Use the lookup to see if this is an aliased register.
         let idx = self.lookup.entry(idx).or_insert(idx);
 
-        self.registers[*idx].write(value)
+        self.registers[*idx].write(&value)
+    }
+
+    /// The index of the full register the given one is a part of.
+    pub fn index(&self, register: usize) -> usize {
+        self.lookup.get(&register).copied().unwrap_or(register)
     }
 
     pub fn iter(&self) -> impl Iterator<Item = &Register> {
@@ -37,6 +51,7 @@ impl Registers {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Register {
     name: String,
     value: Box<[u8]>,
@@ -50,15 +65,25 @@ impl Register {
         }
     }
 
-    pub fn write(&mut self, value: Box<[u8]>) {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The value of the register, little-endian.
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+
+    pub fn write(&mut self, value: &[u8]) {
         self.value
             .iter_mut()
             .enumerate()
             .for_each(|(i, v)| *v = value[i])
     }
 
-    pub fn to_string(&self) -> String {
-        let value = match self.value.len() {
+    /// The value in hexadecimal, padded to the size of the register.
+    pub fn hex(&self) -> String {
+        match self.value.len() {
             1 => format!("{:X}", self.value[0]),
             2 => format!("{:0>2X}", LittleEndian::read_u16(&self.value)),
             3 => format!("{:0>3X}", LittleEndian::read_u24(&self.value)),
@@ -67,8 +92,12 @@ impl Register {
             8 => format!("{:0>8X}", LittleEndian::read_u64(&self.value)),
             16 => format!("{:0>16X}", LittleEndian::read_u128(&self.value)),
             _ => panic!("Unsupported register size, unimplemented!"),
-        };
+        }
+    }
+}
 
-        format!("{0} : {value}", self.name)
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{0} : {1}", self.name, self.hex())
     }
 }
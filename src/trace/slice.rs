@@ -0,0 +1,118 @@
+use super::{taint::Location, Tracer};
+use std::collections::BTreeSet;
+
+/// Where the value in a location at some step came from.
+pub struct Slice {
+    pub target: Location,
+    /// The step the value was taken at, before its instruction executed.
+    pub step: usize,
+    /// The steps at which instructions wrote something the value came from, in ascending order.
+    pub steps: Vec<usize>,
+    /// The addresses of the instructions in the slice.
+    pub addresses: BTreeSet<u64>,
+    /// The locations whose values from before the trace began the value came from.
+    pub origins: BTreeSet<Location>,
+}
+
+impl Tracer {
+    /// Follows the value in a location at a step backward through the trace, to every earlier
+    /// instruction whose writes it was computed from.
+    ///
+    /// A write depends on the reads the same way as when tainting, see [`Tracer::taint`].
+    pub fn slice(&self, target: Location, step: usize) -> Slice {
+        let mut wanted = BTreeSet::from([target]);
+        let mut steps = Vec::new();
+        let mut addresses = BTreeSet::new();
+
+        for step in (0..step.min(self.instructions.len())).rev() {
+            if wanted.is_empty() {
+                break;
+            }
+
+            let insn = &self.instructions[step];
+
+            // The writes of the instruction, along with the reads each depended on.
+            let mut writes = Vec::new();
+            let mut reads = Vec::new();
+            let mut reading = false;
+
+            for event in insn.events() {
+                let (location, write) = self.access(insn, event);
+
+                if write {
+                    reading = false;
+                    writes.push((location, reads.clone()));
+                } else {
+                    if !reading {
+                        reads.clear();
+                        reading = true;
+                    }
+
+                    reads.push(location);
+                }
+            }
+
+            // Later writes overwrote earlier ones, so they are undone last to first.
+            let mut contributed = false;
+            for (location, reads) in writes.into_iter().rev() {
+                if wanted.remove(&location) {
+                    wanted.extend(reads);
+                    contributed = true;
+                }
+            }
+
+            if contributed {
+                steps.push(step);
+                addresses.insert(insn.addr());
+            }
+        }
+
+        steps.reverse();
+
+        Slice {
+            target,
+            step,
+            steps,
+            addresses,
+            origins: wanted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trace::{
+        taint::Location,
+        tests::{tracer, COPIES},
+    };
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn through_copies() {
+        let tracer = tracer(COPIES);
+        let slice = tracer.slice(Location::Memory(0x118), 5);
+
+        assert_eq!(slice.steps, [0, 2]);
+        assert_eq!(slice.addresses, BTreeSet::from([0x10, 0x12]));
+        assert_eq!(slice.origins, BTreeSet::from([Location::Memory(0x100)]));
+    }
+
+    #[test]
+    fn last_write() {
+        let tracer = tracer(COPIES);
+
+        // Only the write to 0x108 before the step counts.
+        let slice = tracer.slice(Location::Memory(0x108), 3);
+        assert_eq!(slice.steps, [0]);
+        assert_eq!(slice.origins, BTreeSet::from([Location::Memory(0x100)]));
+
+        let slice = tracer.slice(Location::Memory(0x108), 4);
+        assert_eq!(slice.steps, [3]);
+        assert_eq!(slice.origins, BTreeSet::from([Location::Memory(0x300)]));
+
+        // Never written in the trace.
+        let slice = tracer.slice(Location::Memory(0x200), 5);
+        assert!(slice.steps.is_empty());
+        assert_eq!(slice.origins, BTreeSet::from([Location::Memory(0x200)]));
+    }
+}
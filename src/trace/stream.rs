@@ -0,0 +1,441 @@
+use super::{ArchInfo, Changes, Error, Instruction, Tracer};
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Starts a stream in the binary record format, which is otherwise newline-delimited JSON.
+pub const MAGIC: &[u8; 8] = b"TVRECORD";
+
+/// The longest record of the binary format, in bytes, which no instruction comes close to.
+const MAX_FRAME_LEN: u64 = 4 << 20;
+
+/// How often waiting for a connection checks whether the stream was dropped.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A single record of a live trace, the architecture info always comes first.
+enum Record {
+    Info(ArchInfo),
+    Instruction(Instruction),
+}
+
+/// A live trace being received from a running tracer.
+///
+/// The tracer connects to the given address and sends the [`ArchInfo`] followed by one
+/// [`Instruction`] at a time, either as newline-delimited JSON or, after [`MAGIC`], as bincode
+/// records each prefixed by their length as a little endian `u64`.
+///
+/// The traced program may crash or be stopped at any point, so live traces are always built
+/// leniently.
+pub struct Stream {
+    pub address: String,
+    rx: Receiver<Result<Record, Error>>,
+    info: Option<ArchInfo>,
+    closed: bool,
+    /// Why the stream ended early, if it did.
+    error: Option<Error>,
+    /// Set once the stream is dropped, which stops waiting for a connection and receiving.
+    stopped: Arc<AtomicBool>,
+}
+
+impl Stream {
+    /// Listens on a TCP address, or on a Unix domain socket when prefixed with `unix:`, and
+    /// receives the first connection made to it in the background. The address is let go of
+    /// once connected to or once the stream is dropped.
+    ///
+    /// The notify callback is called whenever a record arrives, so that the UI can wake up.
+    pub fn listen(address: &str, notify: impl Fn() + Send + 'static) -> Result<Stream, Error> {
+        let (tx, rx) = channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let stop = stopped.clone();
+
+        #[cfg(unix)]
+        if let Some(path) = address.strip_prefix("unix:") {
+            use std::os::unix::fs::FileTypeExt;
+
+            // Only a socket left behind by an earlier listener is replaced, binding fails on
+            // anything else.
+            if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+                std::fs::remove_file(path)?;
+            }
+
+            let listener = std::os::unix::net::UnixListener::bind(path)?;
+            listener.set_nonblocking(true)?;
+
+            thread::spawn(move || {
+                let conn = accept(|| listener.accept(), &stop).and_then(|conn| {
+                    conn.set_nonblocking(false)?;
+                    Ok(conn)
+                });
+                drop(listener);
+                forward(conn, &tx, &stop, notify);
+            });
+
+            return Ok(Stream::new(address, rx, stopped));
+        }
+
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+
+        thread::spawn(move || {
+            let conn = accept(|| listener.accept(), &stop).and_then(|conn| {
+                conn.set_nonblocking(false)?;
+                Ok(conn)
+            });
+            drop(listener);
+            forward(conn, &tx, &stop, notify);
+        });
+
+        Ok(Stream::new(address, rx, stopped))
+    }
+
+    fn new(address: &str, rx: Receiver<Result<Record, Error>>, stopped: Arc<AtomicBool>) -> Stream {
+        Stream {
+            address: address.to_owned(),
+            rx,
+            info: None,
+            closed: false,
+            error: None,
+            stopped,
+        }
+    }
+
+    /// Whether the tracer has finished sending or the connection was lost.
+    pub fn closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Why the stream ended early, whatever was received before that is still kept.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// Moves every record received so far into the tracer, creating it once the first
+    /// instruction arrives, and returns what changed in its control flow graph.
+    pub fn receive(&mut self, tracer: &mut Option<Tracer>) -> Result<Changes, Error> {
+        let mut insns = Vec::new();
+        let was_closed = self.closed;
+
+        loop {
+            match self.rx.try_recv() {
+                Ok(Ok(Record::Info(info))) => self.info = Some(info),
+                Ok(Ok(Record::Instruction(insn))) => insns.push(insn),
+                Ok(Err(e)) => {
+                    self.closed = true;
+                    self.error = Some(e);
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+
+        if tracer.is_none() && !insns.is_empty() {
+            let info = self.info.take().ok_or(Error::MalformedStream)?;
+            *tracer = Some(Tracer::empty(info, true));
+        }
+
+        let Some(tracer) = tracer else {
+            return Ok(Changes::default());
+        };
+
+        let mut changes = tracer.extend(insns)?;
+
+        if self.closed && !was_closed {
+            changes.merge(tracer.finish()?);
+        }
+
+        Ok(changes)
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Waits for the first connection to a non-blocking listener, or until the stream is dropped.
+fn accept<C, A>(
+    mut accept: impl FnMut() -> io::Result<(C, A)>,
+    stopped: &AtomicBool,
+) -> io::Result<C> {
+    loop {
+        match accept() {
+            Ok((conn, _)) => return Ok(conn),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                if stopped.load(Ordering::Relaxed) {
+                    return Err(ErrorKind::Interrupted.into());
+                }
+                thread::sleep(ACCEPT_INTERVAL);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Reads every record from the connection and sends them to the UI, ending with the error if
+/// there was one.
+fn forward(
+    conn: io::Result<impl Read>,
+    tx: &Sender<Result<Record, Error>>,
+    stopped: &AtomicBool,
+    notify: impl Fn(),
+) {
+    let result = conn.map_err(Error::from).and_then(|conn| {
+        read(conn, |record| {
+            // Once the stream is dropped there is no one left to receive it.
+            if stopped.load(Ordering::Relaxed) || tx.send(Ok(record)).is_err() {
+                return false;
+            }
+            notify();
+            true
+        })
+    });
+
+    if let Err(e) = result {
+        let _ = tx.send(Err(e));
+    }
+
+    notify();
+}
+
+/// Reads records until the connection ends or the given callback returns false.
+fn read(conn: impl Read, mut emit: impl FnMut(Record) -> bool) -> Result<(), Error> {
+    let mut reader = BufReader::new(conn);
+
+    if reader.fill_buf()?.first() == Some(&b'{') {
+        let mut lines = reader
+            .lines()
+            .filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()));
+
+        let info = lines.next().ok_or(Error::MalformedStream)??;
+        if !emit(Record::Info(serde_json::from_str(&info)?)) {
+            return Ok(());
+        }
+
+        for line in lines {
+            if !emit(Record::Instruction(serde_json::from_str(&line?)?)) {
+                break;
+            }
+        }
+
+        return Ok(());
+    }
+
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(Error::MalformedStream);
+    }
+
+    let info = read_frame(&mut reader)?.ok_or(Error::MalformedStream)?;
+    if !emit(Record::Info(bincode::deserialize(&info)?)) {
+        return Ok(());
+    }
+
+    while let Some(frame) = read_frame(&mut reader)? {
+        if !emit(Record::Instruction(bincode::deserialize(&frame)?)) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a length prefixed record, or nothing if the stream ended cleanly before it.
+///
+/// The length comes from whoever connected, so records longer than [`MAX_FRAME_LEN`] are
+/// refused rather than allocated for.
+fn read_frame(reader: &mut impl Read) -> Result<Option<Vec<u8>>, Error> {
+    let len = match reader.read_u64::<LittleEndian>() {
+        Ok(len) => len,
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    if len > MAX_FRAME_LEN {
+        return Err(Error::MalformedStream);
+    }
+
+    let mut frame = Vec::new();
+    reader.take(len).read_to_end(&mut frame)?;
+    if frame.len() as u64 != len {
+        return Err(io::Error::from(ErrorKind::UnexpectedEof).into());
+    }
+
+    Ok(Some(frame))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, Record, Stream, MAGIC};
+    use crate::trace::{Error, Event, Instruction};
+    use byteorder::{LittleEndian, WriteBytesExt};
+    use std::io::Write;
+    use std::time::{Duration, Instant};
+
+    const INFO: &str = r#"{"registers":[{"name":"A","register":0,"full_register":null,"size":4}],"memory":[[256,7]]}"#;
+    const INSTRUCTIONS: [&str; 2] = [
+        r#"{"addr":16,"insn":"nop","size":1,"kind":null,"events":[],"thread":null}"#,
+        r#"{"addr":17,"insn":"st","size":1,"kind":null,"events":[{"MemWrite":[256,5]}],"thread":null}"#,
+    ];
+
+    /// The records read from the given bytes, and how reading ended.
+    fn records(bytes: &[u8]) -> (Vec<Record>, Result<(), Error>) {
+        let mut records = Vec::new();
+        let result = read(bytes, |record| {
+            records.push(record);
+            true
+        });
+
+        (records, result)
+    }
+
+    /// The given records in the binary format, the architecture info first.
+    fn binary(frames: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        for frame in frames {
+            bytes.write_u64::<LittleEndian>(frame.len() as u64).unwrap();
+            bytes.extend(frame);
+        }
+
+        bytes
+    }
+
+    /// The records of the binary format, with the architecture info encoded the way it is laid
+    /// out.
+    fn binary_records() -> Vec<Vec<u8>> {
+        let info = (
+            vec![(String::from("A"), 0u32, None::<u32>, 4u16)],
+            vec![(256u64, 7u64)],
+        );
+
+        let mut frames = vec![bincode::serialize(&info).unwrap()];
+        for insn in INSTRUCTIONS {
+            let insn: Instruction = serde_json::from_str(insn).unwrap();
+            frames.push(bincode::serialize(&insn).unwrap());
+        }
+
+        frames
+    }
+
+    fn check(records: &[Record]) {
+        let [Record::Info(info), Record::Instruction(first), Record::Instruction(second)] = records
+        else {
+            panic!("expected the info and two instructions");
+        };
+
+        assert_eq!(info.registers[0].name, "A");
+        assert_eq!(info.memory, [(256, 7)]);
+        assert_eq!(first.addr(), 16);
+        assert_eq!(second.events(), [Event::MemWrite(256, 5)]);
+    }
+
+    #[test]
+    fn json() {
+        let text = format!("{INFO}\n{0}\n\n{1}\n", INSTRUCTIONS[0], INSTRUCTIONS[1]);
+        let (records, result) = records(text.as_bytes());
+
+        result.unwrap();
+        check(&records);
+    }
+
+    #[test]
+    fn binary_format() {
+        let (records, result) = records(&binary(&binary_records()));
+
+        result.unwrap();
+        check(&records);
+    }
+
+    #[test]
+    fn truncated_frame() {
+        let mut bytes = binary(&binary_records());
+        bytes.pop();
+        let (records, result) = records(&bytes);
+
+        assert_eq!(records.len(), 2);
+        assert!(matches!(result, Err(Error::IO(_))));
+    }
+
+    #[test]
+    fn oversized_frame() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.write_u64::<LittleEndian>(u64::MAX).unwrap();
+        let (records, result) = records(&bytes);
+
+        assert!(records.is_empty());
+        assert!(matches!(result, Err(Error::MalformedStream)));
+    }
+
+    #[test]
+    fn unknown_format() {
+        let (_, result) = records(b"TVRECORX");
+        assert!(matches!(result, Err(Error::MalformedStream)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn receive() {
+        let path = std::env::temp_dir().join(format!("traceview-receive-{0}", std::process::id()));
+        let address = format!("unix:{0}", path.display());
+        let mut stream = Stream::listen(&address, || {}).unwrap();
+
+        let mut conn = std::os::unix::net::UnixStream::connect(&path).unwrap();
+        conn.write_all(&binary(&binary_records())).unwrap();
+        drop(conn);
+
+        let mut tracer = None;
+        let start = Instant::now();
+        while !stream.closed() && start.elapsed() < Duration::from_secs(10) {
+            stream.receive(&mut tracer).unwrap();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        let tracer = tracer.unwrap();
+        assert!(stream.error().is_none());
+        assert_eq!(tracer.instructions.len(), 2);
+        assert_eq!(tracer.initial_memory.get(&256), Some(&7));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_files() {
+        let path = std::env::temp_dir().join(format!("traceview-file-{0}", std::process::id()));
+        std::fs::write(&path, "not a socket").unwrap();
+
+        let listened = Stream::listen(&format!("unix:{0}", path.display()), || {});
+        let kept = std::fs::read_to_string(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(listened.is_err());
+        assert_eq!(kept.unwrap(), "not a socket");
+    }
+
+    #[test]
+    fn dropped_lets_go() {
+        let address = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+
+        drop(Stream::listen(&address, || {}).unwrap());
+
+        // The listener is let go of the next time it checks for a connection.
+        let start = Instant::now();
+        while std::net::TcpListener::bind(&address).is_err() {
+            assert!(start.elapsed() < Duration::from_secs(10), "still listening");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
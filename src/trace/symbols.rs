@@ -0,0 +1,178 @@
+use super::{
+    elf::{Elf, SHT_DYNSYM, SHT_SYMTAB, STT_FUNC},
+    Error,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Names given to addresses, which name the function when it starts there and label the
+/// instruction otherwise.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct Symbols {
+    names: BTreeMap<u64, String>,
+}
+
+impl Symbols {
+    /// Imports the function symbols of an ELF file, or the symbols of a text file with an
+    /// `address name` pair on each line, the address in hexadecimal.
+    ///
+    /// The text format also covers the symbol lines of a GNU linker map, everything else in one
+    /// is skipped.
+    pub fn import(bytes: &[u8]) -> Result<Symbols, Error> {
+        let names = if bytes.starts_with(b"\x7fELF") {
+            parse_elf(bytes)?
+        } else {
+            parse_text(&String::from_utf8_lossy(bytes))
+        };
+
+        if names.is_empty() {
+            return Err(Error::MalformedSymbols);
+        }
+
+        let mut symbols = Symbols::default();
+        for (address, name) in names {
+            symbols.set(address, &name);
+        }
+
+        Ok(symbols)
+    }
+
+    pub fn get(&self, address: u64) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    /// Names an address, dropping the characters graphviz labels cannot hold.
+    pub fn set(&mut self, address: u64, name: &str) {
+        let name: String = name
+            .trim()
+            .chars()
+            .filter(|c| *c != '"' && *c != '\\')
+            .collect();
+
+        if name.is_empty() {
+            self.names.remove(&address);
+        } else {
+            self.names.insert(address, name);
+        }
+    }
+
+    pub fn remove(&mut self, address: u64) {
+        self.names.remove(&address);
+    }
+
+    /// Adds the names of other symbols, replacing those given to the same addresses.
+    pub fn extend(&mut self, other: Symbols) {
+        self.names.extend(other.names);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&u64, &String)> {
+        self.names.iter()
+    }
+
+    /// Loads the names saved in the project sidecar of a trace, if it has one.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(trace: &std::path::Path) -> Result<Option<Symbols>, Error> {
+        match std::fs::read(project_path(trace)) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(Error::Project),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Saves the names to the project sidecar of a trace.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, trace: &std::path::Path) -> Result<(), Error> {
+        std::fs::write(project_path(trace), serde_json::to_vec_pretty(self)?)?;
+
+        Ok(())
+    }
+}
+
+/// The path of the project sidecar for a trace, which sits right next to it.
+#[cfg(not(target_arch = "wasm32"))]
+fn project_path(trace: &std::path::Path) -> std::path::PathBuf {
+    let mut path = trace.as_os_str().to_owned();
+    path.push(".tvproj");
+    std::path::PathBuf::from(path)
+}
+
+fn parse_text(text: &str) -> BTreeMap<u64, String> {
+    let mut names = BTreeMap::new();
+
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        let [address, name] = fields.as_slice() else {
+            continue;
+        };
+        let address = address
+            .strip_prefix("0x")
+            .or_else(|| address.strip_prefix("0X"))
+            .unwrap_or(address);
+
+        if let Ok(address) = u64::from_str_radix(address, 16) {
+            names.insert(address, name.to_string());
+        }
+    }
+
+    names
+}
+
+/// Reads the function symbols out of the symbol tables of an ELF file, preferring the full
+/// symbol table over the dynamic one.
+fn parse_elf(bytes: &[u8]) -> Result<BTreeMap<u64, String>, Error> {
+    let elf = Elf::parse(bytes)?;
+    let sections = elf.sections()?;
+
+    let mut tables: Vec<_> = sections
+        .iter()
+        .filter(|section| section.kind == SHT_SYMTAB || section.kind == SHT_DYNSYM)
+        .collect();
+
+    // The dynamic symbols go first so that the full table replaces them.
+    tables.sort_by_key(|table| table.kind != SHT_DYNSYM);
+
+    let mut names = BTreeMap::new();
+
+    for table in tables {
+        let strtab = sections
+            .get(table.link as usize)
+            .ok_or(Error::MalformedElf)?;
+
+        for symbol in elf.symbols(table)? {
+            if symbol.info & 0xF != STT_FUNC || symbol.value == 0 {
+                continue;
+            }
+
+            let offset = strtab
+                .offset
+                .checked_add(symbol.name as u64)
+                .ok_or(Error::MalformedElf)?;
+            let name = elf.string(offset)?;
+            if !name.is_empty() {
+                names.insert(symbol.value, name);
+            }
+        }
+    }
+
+    Ok(names)
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::{project_path, Symbols};
+    use crate::trace::Error;
+
+    #[test]
+    fn corrupt_project() {
+        let trace = std::env::temp_dir().join(format!("traceview-project-{}", std::process::id()));
+        std::fs::write(project_path(&trace), "{\"names\":").unwrap();
+
+        let loaded = Symbols::load(&trace);
+        std::fs::remove_file(project_path(&trace)).unwrap();
+
+        assert!(matches!(loaded, Err(Error::Project(_))));
+    }
+}
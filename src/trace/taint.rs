@@ -0,0 +1,166 @@
+use super::{Event, Instruction, Tracer};
+use std::collections::BTreeSet;
+
+/// A register of a thread or a cell of memory, which data flows through.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Location {
+    /// A register of a thread, by its index among the full registers.
+    Register {
+        thread: u32,
+        register: usize,
+    },
+    Memory(u64),
+}
+
+/// Where the data in a location at some step flowed to from then on.
+pub struct Taint {
+    pub source: Location,
+    /// The step the source was tainted at, before its instruction executed.
+    pub step: usize,
+    /// The steps at which instructions read tainted data, in ascending order.
+    pub steps: Vec<usize>,
+    /// The addresses of the instructions which read tainted data.
+    pub addresses: BTreeSet<u64>,
+    /// Every time a location was tainted or cleaned, by the step from which it was.
+    changes: Vec<(usize, Location, bool)>,
+}
+
+impl Taint {
+    /// The locations which are tainted at the given step.
+    pub fn at(&self, step: usize) -> BTreeSet<Location> {
+        let mut tainted = BTreeSet::new();
+
+        for (_, location, taint) in self.changes.iter().take_while(|(at, ..)| *at <= step) {
+            if *taint {
+                tainted.insert(*location);
+            } else {
+                tainted.remove(location);
+            }
+        }
+
+        tainted
+    }
+}
+
+impl Tracer {
+    /// Where an event of an instruction read or wrote, and whether it wrote.
+    pub(super) fn access(&self, insn: &Instruction, event: &Event) -> (Location, bool) {
+        let register = |register: &u32| Location::Register {
+            thread: insn.thread(),
+            register: self.registers.index(*register as usize),
+        };
+
+        match event {
+            Event::RegRead(reg) => (register(reg), false),
+            Event::RegWrite(reg, _) => (register(reg), true),
+            Event::MemRead(addr) => (Location::Memory(*addr), false),
+            Event::MemWrite(addr, _) => (Location::Memory(*addr), true),
+        }
+    }
+
+    /// Taints a location at a step and follows the taint forward through the trace.
+    ///
+    /// The events of an instruction are taken in order, with a write depending on the reads
+    /// since the previous write, or on the same ones as it when there were none in between. A
+    /// write of tainted data taints where it went and any other write cleans it.
+    pub fn taint(&self, source: Location, step: usize) -> Taint {
+        let mut tainted = BTreeSet::from([source]);
+        let mut steps = Vec::new();
+        let mut addresses = BTreeSet::new();
+        let mut changes = vec![(step, source, true)];
+
+        for (step, insn) in self.instructions.iter().enumerate().skip(step) {
+            if tainted.is_empty() {
+                break;
+            }
+
+            // Whether the reads since the last write were of tainted data.
+            let mut inputs = false;
+            let mut reading = false;
+            let mut read = false;
+
+            for event in insn.events() {
+                let (location, write) = self.access(insn, event);
+
+                if write {
+                    reading = false;
+
+                    let changed = if inputs {
+                        tainted.insert(location)
+                    } else {
+                        tainted.remove(&location)
+                    };
+
+                    if changed {
+                        changes.push((step + 1, location, inputs));
+                    }
+                } else {
+                    if !reading {
+                        inputs = false;
+                        reading = true;
+                    }
+
+                    let is_tainted = tainted.contains(&location);
+                    inputs |= is_tainted;
+                    read |= is_tainted;
+                }
+            }
+
+            if read {
+                steps.push(step);
+                addresses.insert(insn.addr());
+            }
+        }
+
+        Taint {
+            source,
+            step,
+            steps,
+            addresses,
+            changes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Location;
+    use crate::trace::tests::{tracer, COPIES};
+    use std::collections::BTreeSet;
+
+    /// The memory tainted at a step.
+    fn memory(taint: &super::Taint, step: usize) -> Vec<u64> {
+        taint
+            .at(step)
+            .into_iter()
+            .filter_map(|location| match location {
+                Location::Memory(address) => Some(address),
+                Location::Register { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spreads_and_cleans() {
+        let tracer = tracer(COPIES);
+        let taint = tracer.taint(Location::Memory(0x100), 0);
+
+        assert_eq!(taint.steps, [0, 2, 4]);
+        assert_eq!(taint.addresses, BTreeSet::from([0x10, 0x12, 0x14]));
+        assert_eq!(memory(&taint, 0), [0x100]);
+        assert_eq!(memory(&taint, 1), [0x100, 0x108]);
+        assert_eq!(memory(&taint, 3), [0x100, 0x108, 0x118]);
+        // Overwriting with untainted data cleans it.
+        assert_eq!(memory(&taint, 4), [0x100, 0x118]);
+    }
+
+    #[test]
+    fn starts_at_step() {
+        let tracer = tracer(COPIES);
+
+        // By the time 0x108 is tainted here it has been read for the last time.
+        let taint = tracer.taint(Location::Memory(0x108), 3);
+        assert!(taint.steps.is_empty());
+        assert_eq!(memory(&taint, 4), Vec::<u64>::new());
+    }
+}
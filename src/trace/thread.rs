@@ -0,0 +1,97 @@
+use super::{register::Registers, Event, Instruction, JumpKind, Tracer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The state of a single thread of the traced program, memory is shared between all of them.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Thread {
+    pub registers: Registers,
+    /// The functions which were entered and not yet returned from, outermost first.
+    ///
+    /// The outermost frame is whichever function the thread started in.
+    pub call_stack: Vec<Frame>,
+    /// The jump taken by the last instruction of the thread, where it went is only known once
+    /// the next one executes.
+    pending: Option<JumpKind>,
+}
+
+/// A function on the call stack of a thread.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Frame {
+    /// The starting address of the function.
+    pub function: u64,
+    /// The step at which the function was entered.
+    pub step: usize,
+}
+
+impl Thread {
+    /// Creates a thread which starts at the given instruction.
+    pub fn new(registers: Registers, entry_point: u64, step: usize) -> Self {
+        Thread {
+            registers,
+            call_stack: vec![Frame {
+                function: entry_point,
+                step,
+            }],
+            pending: None,
+        }
+    }
+
+    /// Applies the writes of an instruction this thread executed at the given step.
+    pub fn execute(&mut self, insn: &Instruction, step: usize, memory: &mut BTreeMap<u64, u64>) {
+        self.enter(insn, step);
+
+        for event in &insn.events {
+            match event {
+                Event::RegWrite(reg, val) => {
+                    self.registers.write(*reg as usize, val.clone());
+                }
+                Event::MemWrite(addr, val) => {
+                    memory
+                        .entry(*addr)
+                        .and_modify(|v| *v = *val)
+                        .or_insert(*val);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Follows the jump taken before an instruction this thread executed at the given step,
+    /// updating the call stack.
+    fn enter(&mut self, insn: &Instruction, step: usize) {
+        let frame = Frame {
+            function: insn.addr,
+            step,
+        };
+
+        match self.pending.take() {
+            Some(JumpKind::Call) => self.call_stack.push(frame),
+            Some(JumpKind::Return) if self.call_stack.len() > 1 => {
+                self.call_stack.pop();
+            }
+            // Whoever was returned to is unknown, the same as when building the graph.
+            Some(JumpKind::Return) => self.call_stack = vec![frame],
+            _ => {}
+        }
+
+        self.pending = insn.kind;
+    }
+}
+
+impl Tracer {
+    /// Goes through the whole trace, giving every instruction along with its step and the call
+    /// stack of its thread as it executed it, outermost first.
+    pub fn call_stacks(&self, mut visit: impl FnMut(usize, &Instruction, &[Frame])) {
+        let mut threads: BTreeMap<u32, Thread> = BTreeMap::new();
+
+        for (step, insn) in self.instructions.iter().enumerate() {
+            let thread = threads
+                .entry(insn.thread())
+                .or_insert_with(|| Thread::new(self.registers.clone(), insn.addr, step));
+            thread.enter(insn, step);
+
+            visit(step, insn, &thread.call_stack);
+        }
+    }
+}
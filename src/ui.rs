@@ -1,37 +1,138 @@
-use self::{file::FileDialog, graph::Graph};
-use crate::trace::{TraceFile, Tracer};
-use serde_json::from_str;
+use self::{
+    binary::BinaryWindow,
+    dataflow::Follow,
+    defuse::DefUseWindow,
+    export::ExportWindow,
+    file::FileDialog,
+    functions::{Action, FunctionTable},
+    graph::{Graph, Heat, Highlight},
+    heatmap::HeatmapWindow,
+    loops::LoopsWindow,
+    memory::MemoryWindow,
+    minimap::Minimap,
+    plot::PlotWindow,
+    profile::ProfileWindow,
+    slice::SliceWindow,
+    symbols::SymbolsWindow,
+    taint::TaintWindow,
+    xrefs::Xrefs,
+};
+use crate::trace::{GraphId, Location, Tracer, CALL_GRAPH};
 
+mod binary;
+mod dataflow;
+mod defuse;
+mod export;
 mod file;
+mod functions;
 mod graph;
+mod heatmap;
+#[cfg(not(target_arch = "wasm32"))]
+mod live;
+mod loops;
+mod memory;
+mod minimap;
+mod plot;
+mod profile;
+mod slice;
+mod symbols;
+mod taint;
+mod xrefs;
 
 #[derive(Default)]
 pub struct TraceView {
     fd: FileDialog,
     tracer: Option<Tracer>,
     graph: Option<Graph>,
+    functions: FunctionTable,
+    #[cfg(not(target_arch = "wasm32"))]
+    live: live::Live,
+    xrefs: Xrefs,
+    loops: LoopsWindow,
+    memory: MemoryWindow,
+    taint: TaintWindow,
+    slice: SliceWindow,
+    def_use: DefUseWindow,
+    plot: PlotWindow,
+    heatmap: HeatmapWindow,
+    profile: ProfileWindow,
+    minimap: Minimap,
+    symbols: SymbolsWindow,
+    binary: BinaryWindow,
+    export: ExportWindow,
+    /// Whether traces are loaded leniently, see [`Tracer::new`].
+    lenient: bool,
+    /// Whether the warnings about the loaded trace are being shown.
+    warnings: bool,
+    /// Why the last trace could not be opened, shown until dismissed.
+    error: Option<String>,
+    /// The thread being followed when stepping, otherwise all of them are stepped through in
+    /// the order they were interleaved.
+    thread: Option<u32>,
 }
 
 impl TraceView {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
         TraceView {
+            lenient: true,
             ..Default::default()
         }
     }
+
+    /// Forgets whatever the views worked out from the previous trace, which may have had as
+    /// many instructions as the one replacing it.
+    fn reset_views(&mut self) {
+        self.loops.reset();
+        self.taint.reset();
+        self.def_use.reset();
+        self.plot.reset();
+        self.heatmap.reset();
+        self.profile.invalidate();
+        self.minimap.reset();
+    }
 }
 
 impl eframe::App for TraceView {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if let Some(trace) = self.fd.get() {
-            let trace: TraceFile = from_str(&String::from_utf8_lossy(&trace)).unwrap();
+        #[cfg(not(target_arch = "wasm32"))]
+        let tracer = self.fd.get().map(|path| {
+            let tracer = Tracer::load(&path, self.lenient);
+            if tracer.is_ok() {
+                if let Some(stem) = path.file_stem() {
+                    self.export.module = stem.to_string_lossy().into_owned();
+                }
+                self.symbols.trace = Some(path);
+            }
+            tracer
+        });
+
+        #[cfg(target_arch = "wasm32")]
+        let tracer = self.fd.get().map(|trace| {
+            crate::trace::TraceFile::parse(&trace)
+                .and_then(|trace| Tracer::new(trace, self.lenient, Default::default()))
+        });
 
-            let tracer = Tracer::new(trace).expect("failed to parse");
-            let graph = Graph::new(ctx, &tracer.graphs).expect("failed to graph");
+        // A trace which fails to open leaves the one before it open.
+        match tracer {
+            Some(Ok(tracer)) => {
+                // Whatever arrives on a live stream would be added to the trace replacing it.
+                #[cfg(not(target_arch = "wasm32"))]
+                self.live.stop();
 
-            self.tracer = Some(tracer);
-            self.graph = Some(graph);
+                self.warnings = !tracer.cfg.warnings().is_empty();
+                self.error = None;
+                self.thread = None;
+                self.tracer = Some(tracer);
+                self.graph = Some(Graph::new(GraphId::Calls));
+                self.reset_views();
+            }
+            Some(Err(e)) => self.error = Some(e.to_string()),
+            None => {}
         }
 
+        #[cfg(not(target_arch = "wasm32"))]
+        self.live.update(ctx, &mut self.tracer, &mut self.graph);
+
         egui::TopBottomPanel::top("top").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
@@ -43,51 +144,295 @@ impl eframe::App for TraceView {
                     if ui.button("Open trace").clicked() {
                         self.fd.open()
                     }
+
+                    ui.checkbox(&mut self.lenient, "Tolerate truncated traces");
+
+                    if ui.button("Load binary").clicked() {
+                        self.binary.open = true;
+                    }
+
+                    if ui.button("Export").clicked() {
+                        if let Some(graph) = &self.graph {
+                            self.export.select(graph.current());
+                        }
+                        self.export.open = true;
+                    }
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui.button("Listen for trace").clicked() {
+                        self.live.open = true;
+                    }
+                });
+
+                ui.menu_button("View", |ui| {
+                    ui.checkbox(&mut self.xrefs.open, "Cross references");
+                    ui.checkbox(&mut self.loops.open, "Loops");
+                    ui.checkbox(&mut self.memory.open, "Memory");
+                    ui.checkbox(&mut self.taint.open, "Taint");
+                    ui.checkbox(&mut self.slice.open, "Slice");
+                    ui.checkbox(&mut self.def_use.open, "Def-use");
+                    ui.checkbox(&mut self.plot.open, "Values");
+                    ui.checkbox(&mut self.heatmap.open, "Memory heatmap");
+                    ui.checkbox(&mut self.profile.open, "Profile");
+                    ui.checkbox(&mut self.minimap.open, "Timeline");
+                    ui.checkbox(&mut self.symbols.open, "Symbols");
                 });
 
                 if let Some(tracer) = &mut self.tracer {
+                    if tracer.thread_steps.len() > 1 {
+                        let name = |thread: Option<u32>| match thread {
+                            Some(thread) => format!("THREAD {thread}"),
+                            None => String::from("ALL THREADS"),
+                        };
+
+                        egui::ComboBox::from_id_source("thread")
+                            .selected_text(name(self.thread))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.thread, None, name(None));
+
+                                for thread in tracer.thread_steps.keys() {
+                                    ui.selectable_value(
+                                        &mut self.thread,
+                                        Some(*thread),
+                                        name(Some(*thread)),
+                                    );
+                                }
+                            });
+                    }
+
                     if ui.button("PREVIOUS").clicked() {
-                        tracer.step_backward()
+                        match self.thread {
+                            Some(thread) => tracer.step_backward_in(thread),
+                            None => tracer.step_backward(),
+                        }
                     }
 
                     if ui.button("NEXT").clicked() {
-                        tracer.step_forward()
+                        match self.thread {
+                            Some(thread) => tracer.step_forward_in(thread),
+                            None => tracer.step_forward(),
+                        }
                     }
 
-                    ui.label(format!(
-                        "LAST INSTRUCTION: {0}",
-                        tracer.instructions[tracer.step].insn
-                    ));
+                    let insn = &tracer.instructions[tracer.step];
+                    match tracer.cfg.symbols().get(insn.addr()) {
+                        Some(name) => ui.label(format!("LAST INSTRUCTION: {name}: {0}", insn.insn)),
+                        None => ui.label(format!("LAST INSTRUCTION: {0}", insn.insn)),
+                    };
+
+                    let warnings = tracer.cfg.warnings().len();
+                    if warnings > 0 && ui.button(format!("{warnings} WARNINGS")).clicked() {
+                        self.warnings = !self.warnings;
+                    }
                 }
             });
         });
 
         egui::TopBottomPanel::bottom("registers").show(ctx, |ui| {
             if let Some(tracer) = &mut self.tracer {
-                for register in tracer.registers.iter() {
-                    ui.label(register.to_string());
+                let thread = self.thread.unwrap_or_else(|| tracer.thread());
+
+                if let Some(state) = tracer.threads.get(&thread) {
+                    let call_stack: Vec<_> = state
+                        .call_stack
+                        .iter()
+                        .filter_map(|frame| tracer.cfg.function(frame.function))
+                        .map(|function| function.name.as_str())
+                        .collect();
+
+                    ui.label(format!(
+                        "THREAD {thread} CALL STACK: {0}",
+                        call_stack.join(" > ")
+                    ));
+                }
+
+                let tainted = self.taint.tainted(tracer);
+                let mut follow = None;
+
+                // Tainted registers are marked, and the data in any register can be followed.
+                for (i, register) in tracer.registers(thread).iter().enumerate() {
+                    let location = Location::Register {
+                        thread,
+                        register: i,
+                    };
+                    let mut text = egui::RichText::new(register.to_string());
+                    if tainted.is_some_and(|tainted| tainted.contains(&location)) {
+                        text = text.color(taint::COLOR);
+                    }
+
+                    let label = egui::Label::new(text).sense(egui::Sense::click());
+                    if let Some(chosen) = dataflow::menu(ui.add(label), location) {
+                        follow = Some(chosen);
+                    }
+                }
+
+                match follow {
+                    Some(Follow::Taint(location)) => self.taint.start(tracer, location),
+                    Some(Follow::Slice(location)) => self.slice.start(tracer, location),
+                    Some(Follow::Plot(location)) => self.plot.add(tracer, location),
+                    None => {}
                 }
             }
         });
 
-        egui::SidePanel::right("functions").show(ctx, |ui| {
-            if let Some(tracer) = &mut self.tracer {
-                for func in &tracer.graphs {
-                    if ui.button(&func.name).clicked() {
-                        // Yeah, rather than rework the structure, just do this.
-                        // Its bad.
+        if self.minimap.open {
+            egui::TopBottomPanel::bottom("minimap").show(ctx, |ui| {
+                if let Some(tracer) = &mut self.tracer {
+                    self.minimap.ui(ui, tracer);
+                }
+            });
+        }
+
+        egui::SidePanel::right("functions")
+            .resizable(true)
+            .show(ctx, |ui| {
+                if let Some(tracer) = &mut self.tracer {
+                    if ui.button(CALL_GRAPH).clicked() {
                         if let Some(graph) = &mut self.graph {
-                            graph.set_texture(&func.name);
+                            graph.select(GraphId::Calls);
+                        }
+                    }
+
+                    match self.functions.ui(ui, tracer) {
+                        Some(Action::Open(address)) => {
+                            if let Some(graph) = &mut self.graph {
+                                graph.select(GraphId::Function(address));
+                            }
+
+                            self.xrefs.select(address);
+                            self.loops.select(address, tracer);
+                            self.symbols.select(address, tracer);
+                        }
+                        Some(Action::Rename(address)) => {
+                            self.symbols.select(address, tracer);
+                            self.symbols.open = true;
                         }
+                        None => {}
                     }
                 }
-            }
-        });
+            });
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            if let Some(graph) = &mut self.graph {
-                graph.ui(ui);
+            if let Some((graph, tracer)) = self.graph.as_mut().zip(self.tracer.as_ref()) {
+                let mut heat = None;
+                let mut highlights = Vec::new();
+
+                if self.profile.heat {
+                    let profile = self.profile.profile(tracer);
+                    heat = Some(Heat::new(tracer, profile, graph.current()));
+                }
+
+                if let Some(addresses) = self.taint.addresses() {
+                    highlights.push(Highlight::new(
+                        "tainted",
+                        taint::COLOR,
+                        tracer,
+                        graph.current(),
+                        addresses,
+                    ));
+                }
+
+                if let Some(addresses) = self.slice.addresses() {
+                    highlights.push(Highlight::new(
+                        "slice",
+                        slice::COLOR,
+                        tracer,
+                        graph.current(),
+                        addresses,
+                    ));
+                }
+
+                let clicked = graph.ui(ui, tracer, heat.as_ref(), &highlights);
+
+                // Clicking a function in the call graph opens its control flow graph, while
+                // clicking a block shows what jumps to it.
+                if let Some(address) = clicked {
+                    if graph.current() == GraphId::Calls {
+                        graph.select(GraphId::Function(address));
+                    } else {
+                        self.xrefs.open = true;
+                    }
+
+                    self.xrefs.select(address);
+                    self.loops.select(address, tracer);
+                    self.symbols.select(address, tracer);
+                }
             }
         });
+
+        if let Some(error) = &self.error {
+            let mut open = true;
+            egui::Window::new("Could not open the trace")
+                .open(&mut open)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.colored_label(egui::Color32::RED, error);
+                });
+
+            if !open {
+                self.error = None;
+            }
+        }
+
+        if let Some(tracer) = &self.tracer {
+            egui::Window::new("Warnings")
+                .open(&mut self.warnings)
+                .show(ctx, |ui| {
+                    ui.label("The trace appears to have been cut short, these were tolerated:");
+
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for warning in tracer.cfg.warnings() {
+                            ui.label(warning.describe(tracer.cfg.symbols()));
+                        }
+                    });
+                });
+        }
+
+        if let Some(tracer) = &mut self.tracer {
+            self.xrefs.window(ctx, tracer);
+            self.loops.window(ctx, tracer, self.thread);
+            self.taint.window(ctx, tracer, self.thread);
+            self.slice.window(ctx, tracer, self.thread);
+            self.def_use.window(ctx, tracer);
+            self.plot.window(ctx, tracer, self.thread);
+            self.heatmap.window(ctx, tracer);
+
+            if let Some(address) = self.profile.window(ctx, tracer) {
+                if let Some(graph) = &mut self.graph {
+                    graph.select(GraphId::Function(address));
+                }
+
+                self.xrefs.select(address);
+                self.loops.select(address, tracer);
+                self.symbols.select(address, tracer);
+            }
+
+            let tainted = self.taint.tainted(tracer);
+            match self.memory.window(ctx, tracer, tainted) {
+                Some(Follow::Taint(location)) => self.taint.start(tracer, location),
+                Some(Follow::Slice(location)) => self.slice.start(tracer, location),
+                Some(Follow::Plot(location)) => self.plot.add(tracer, location),
+                None => {}
+            }
+
+            let renamed = self.symbols.window(ctx, tracer, self.graph.as_mut());
+            let rebuilt = self.binary.window(ctx, tracer, self.graph.as_mut());
+            if renamed || rebuilt {
+                self.profile.invalidate();
+            }
+            self.export.window(ctx, tracer);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if self.live.window(ctx, &mut self.tracer, &mut self.graph) {
+                self.reset_views();
+            }
+
+            // Live traces have no file to save the names next to.
+            if self.tracer.is_none() {
+                self.symbols.trace = None;
+            }
+        }
     }
 }
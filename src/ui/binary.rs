@@ -0,0 +1,108 @@
+use super::{file::FileDialog, graph::Graph};
+use crate::trace::{architectures, Binary, Error, Tracer};
+use egui::Context;
+
+/// Loads the traced binary, so that the code which never executed shows up in the graphs.
+#[derive(Default)]
+pub struct BinaryWindow {
+    pub open: bool,
+    fd: FileDialog,
+    /// The architecture the binary is decoded as, out of [`architectures`].
+    arch: usize,
+    /// Where the binary is loaded, in hexadecimal.
+    base: String,
+    error: Option<String>,
+}
+
+impl BinaryWindow {
+    /// Shows the window, returning whether any function or name changed.
+    pub fn window(
+        &mut self,
+        ctx: &Context,
+        tracer: &mut Tracer,
+        graph: Option<&mut Graph>,
+    ) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        let loaded = self
+            .fd
+            .get()
+            .map(|path| std::fs::read(path).map_err(Error::from));
+
+        #[cfg(target_arch = "wasm32")]
+        let loaded = self.fd.get().map(Ok);
+
+        let mut changed = false;
+
+        if let Some(bytes) = loaded {
+            let base = u64::from_str_radix(self.base.trim(), 16).unwrap_or(0);
+            let arch = architectures().swap_remove(self.arch);
+
+            match bytes.and_then(|bytes| Binary::load(&bytes, base, arch)) {
+                Ok(binary) => {
+                    tracer.cfg.set_binary(Some(binary));
+                    changed = true;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
+        let mut open = self.open;
+
+        egui::Window::new("Binary")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let names: Vec<_> = architectures().iter().map(|arch| arch.name()).collect();
+
+                egui::ComboBox::from_label("Architecture")
+                    .selected_text(names[self.arch])
+                    .show_ui(ui, |ui| {
+                        for (i, name) in names.iter().enumerate() {
+                            ui.selectable_value(&mut self.arch, i, *name);
+                        }
+                    });
+
+                ui.horizontal(|ui| {
+                    ui.label("Load address 0x");
+                    ui.text_edit_singleline(&mut self.base);
+                });
+                ui.label(
+                    "Raw binaries are loaded there, the segments of ELF files are offset by it.",
+                );
+
+                ui.horizontal(|ui| {
+                    if ui.button("Open binary").clicked() {
+                        self.fd.open();
+                    }
+
+                    if tracer.cfg.binary().is_some() && ui.button("Unload").clicked() {
+                        tracer.cfg.set_binary(None);
+                        changed = true;
+                    }
+                });
+
+                if let Some(binary) = tracer.cfg.binary() {
+                    ui.label(format!(
+                        "Showing unexecuted code decoded as {0}.",
+                        binary.arch().name()
+                    ));
+                }
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+            });
+
+        self.open = open;
+
+        if changed {
+            let changes = tracer.cfg.take_changes();
+            if let Some(graph) = graph {
+                graph.invalidate(&changes);
+            }
+            self.error = None;
+        }
+
+        changed
+    }
+}
@@ -0,0 +1,148 @@
+use crate::trace::{Location, Tracer};
+use egui::{Color32, Response, RichText, Ui};
+use egui_extras::{Column, TableBuilder};
+
+/// Picks a register or a memory address to follow data from.
+#[derive(Default)]
+pub struct LocationPicker {
+    /// Whether memory is being picked rather than a register.
+    memory: bool,
+    register: usize,
+    /// The address being picked, in hexadecimal.
+    address: String,
+}
+
+impl LocationPicker {
+    /// Shows the picker, with the registers being those of the given thread, returning what was
+    /// picked unless the address is not valid.
+    pub fn ui(&mut self, ui: &mut Ui, tracer: &Tracer, thread: u32) -> Option<Location> {
+        ui.radio_value(&mut self.memory, false, "Register");
+        ui.radio_value(&mut self.memory, true, "Memory");
+
+        if self.memory {
+            ui.add(egui::TextEdit::singleline(&mut self.address).hint_text("Address"));
+
+            let address = self.address.trim().trim_start_matches("0x");
+            return u64::from_str_radix(address, 16).ok().map(Location::Memory);
+        }
+
+        let registers = tracer.initial_registers();
+        let name = registers
+            .iter()
+            .nth(self.register)
+            .map_or("", |register| register.name());
+
+        egui::ComboBox::from_id_source(ui.id().with("register"))
+            .selected_text(name)
+            .show_ui(ui, |ui| {
+                for (i, register) in registers.iter().enumerate() {
+                    ui.selectable_value(&mut self.register, i, register.name());
+                }
+            });
+
+        Some(Location::Register {
+            thread,
+            register: self.register,
+        })
+    }
+}
+
+/// How to follow the data in a location from the current step.
+pub enum Follow {
+    /// Forward, to where it went.
+    Taint(Location),
+    /// Backward, to where it came from.
+    Slice(Location),
+    /// Over the whole trace, as the values it held.
+    Plot(Location),
+}
+
+/// Offers to follow the data in a location when it is right-clicked.
+pub fn menu(response: Response, location: Location) -> Option<Follow> {
+    let mut follow = None;
+
+    response.context_menu(|ui| {
+        if ui.button("Taint from here").clicked() {
+            follow = Some(Follow::Taint(location));
+            ui.close_menu();
+        }
+
+        if ui.button("Slice from here").clicked() {
+            follow = Some(Follow::Slice(location));
+            ui.close_menu();
+        }
+
+        if ui.button("Plot over time").clicked() {
+            follow = Some(Follow::Plot(location));
+            ui.close_menu();
+        }
+    });
+
+    follow
+}
+
+/// Names a register after its thread, and memory by its address.
+pub fn describe(tracer: &Tracer, location: Location) -> String {
+    match location {
+        Location::Register { thread, register } => {
+            let name = tracer.initial_registers().iter().nth(register).map_or_else(
+                || register.to_string(),
+                |register| register.name().to_owned(),
+            );
+
+            format!("{name} of thread {thread}")
+        }
+        Location::Memory(address) => format!("0x{address:X}"),
+    }
+}
+
+/// Lists the instructions executed at the given steps in the given color, returning the step
+/// that was clicked to seek to.
+pub fn listing(ui: &mut Ui, tracer: &Tracer, steps: &[usize], color: Color32) -> Option<usize> {
+    let height = ui.text_style_height(&egui::TextStyle::Body);
+    let mut seek = None;
+
+    TableBuilder::new(ui)
+        .striped(true)
+        .columns(Column::auto().resizable(true), 3)
+        .header(height, |mut header| {
+            for heading in ["Step", "Address", "Instruction"] {
+                header.col(|ui| {
+                    ui.strong(heading);
+                });
+            }
+        })
+        .body(|body| {
+            body.rows(height, steps.len(), |mut row| {
+                let step = steps[row.index()];
+                let insn = &tracer.instructions[step];
+
+                // The instruction at the current step is in bold.
+                let text = |text: String| {
+                    if step == tracer.step {
+                        RichText::new(text).strong()
+                    } else {
+                        RichText::new(text)
+                    }
+                };
+
+                row.col(|ui| {
+                    if ui.link(text(step.to_string())).clicked() {
+                        seek = Some(step);
+                    }
+                });
+                row.col(|ui| {
+                    let address = match tracer.cfg.symbols().get(insn.addr()) {
+                        Some(name) => name.to_owned(),
+                        None => format!("0x{0:X}", insn.addr()),
+                    };
+                    ui.label(text(address));
+                });
+                row.col(|ui| {
+                    ui.label(text(insn.insn.clone()).color(color));
+                });
+            });
+        });
+
+    seek
+}
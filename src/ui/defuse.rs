@@ -0,0 +1,132 @@
+use super::{dataflow::describe, graph::Graph};
+use crate::trace::{DefUse, GraphId, Tracer, MAX_DEF_USE_DEPTH};
+use egui::Context;
+
+/// Shows where the values an instruction read came from and where those it wrote went, as a
+/// graph expanded some levels deep.
+pub struct DefUseWindow {
+    pub open: bool,
+    /// The step of the instruction in the middle of the graph.
+    step: Option<usize>,
+    /// How many levels of producers and consumers are shown.
+    depth: usize,
+    chains: Option<DefUse>,
+    /// The graph drawn, which is replaced whenever the step or depth changes.
+    graph: Option<Graph>,
+}
+
+impl Default for DefUseWindow {
+    fn default() -> Self {
+        DefUseWindow {
+            open: false,
+            step: None,
+            depth: 1,
+            chains: None,
+            graph: None,
+        }
+    }
+}
+
+impl DefUseWindow {
+    /// Forgets the chains of the previous trace.
+    pub fn reset(&mut self) {
+        self.step = None;
+        self.chains = None;
+        self.graph = None;
+    }
+
+    pub fn window(&mut self, ctx: &Context, tracer: &mut Tracer) {
+        let mut open = self.open;
+
+        egui::Window::new("Def-use")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                // A trace opened since may not have got that far.
+                let step = match self.step {
+                    Some(step) if step < tracer.instructions.len() => step,
+                    _ => *self.step.insert(tracer.step),
+                };
+                let mut select = None;
+
+                ui.horizontal(|ui| {
+                    ui.label(format!("Step {step}"));
+
+                    if ui.button("Current step").clicked() {
+                        select = Some(tracer.step);
+                    }
+
+                    if ui.button("Seek here").clicked() {
+                        tracer.seek(step);
+                    }
+
+                    ui.label("Depth");
+                    let depth =
+                        egui::DragValue::new(&mut self.depth).clamp_range(1..=MAX_DEF_USE_DEPTH);
+                    ui.add(depth);
+                });
+
+                if self.chains.as_ref().map(|chains| chains.step) != Some(step) {
+                    self.chains = Some(tracer.def_use(step));
+                }
+
+                if let Some(chains) = &self.chains {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("Reads");
+
+                        for (location, producer) in &chains.producers {
+                            let name = describe(tracer, *location);
+
+                            match producer {
+                                Some(producer) => {
+                                    if ui.link(format!("{name} from {producer}")).clicked() {
+                                        select = Some(*producer);
+                                    }
+                                }
+                                None => {
+                                    ui.label(format!("{name} from before the trace"));
+                                }
+                            }
+                        }
+                    });
+
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("Writes");
+
+                        for (location, consumers) in &chains.consumers {
+                            ui.label(format!("{0}, read by", describe(tracer, *location)));
+
+                            if consumers.is_empty() {
+                                ui.label("nothing");
+                            }
+
+                            for consumer in consumers {
+                                if ui.link(consumer.to_string()).clicked() {
+                                    select = Some(*consumer);
+                                }
+                            }
+                        }
+                    });
+                }
+
+                let id = GraphId::DefUse {
+                    step,
+                    depth: self.depth,
+                };
+                let graph = match &mut self.graph {
+                    Some(graph) if graph.current() == id => graph,
+                    graph => graph.insert(Graph::new(id)),
+                };
+
+                // Clicking an instruction puts it in the middle instead.
+                if let Some(clicked) = graph.ui(ui, tracer, None, &[]) {
+                    select = Some(clicked as usize);
+                }
+
+                if let Some(step) = select {
+                    self.step = Some(step);
+                }
+            });
+
+        self.open = open;
+    }
+}
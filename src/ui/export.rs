@@ -0,0 +1,244 @@
+use super::file;
+use crate::export::{chrome, drcov, folded, graph, tenet};
+use crate::trace::{GraphId, Tracer, CALL_GRAPH};
+use egui::Context;
+
+/// Saves what was traced in the formats of other tools.
+pub struct ExportWindow {
+    pub open: bool,
+    /// The module the coverage is attributed to, which should be named after the binary.
+    pub module: String,
+    /// Where the module was loaded, in hexadecimal.
+    base: String,
+    /// How the trace is exported to Tenet, once there is a trace to export.
+    tenet: Option<tenet::Options>,
+    /// The function whose graph is exported, otherwise the call graph is.
+    function: Option<u64>,
+    format: usize,
+    /// How many pixels images have per point.
+    scale: f32,
+    error: Option<String>,
+}
+
+impl Default for ExportWindow {
+    fn default() -> Self {
+        ExportWindow {
+            open: false,
+            module: String::new(),
+            base: String::new(),
+            tenet: None,
+            function: None,
+            format: 0,
+            scale: graph::DEFAULT_SCALE,
+            error: None,
+        }
+    }
+}
+
+impl ExportWindow {
+    /// Exports the given graph, the one being viewed.
+    pub fn select(&mut self, graph: GraphId) {
+        self.function = match graph {
+            GraphId::Function(start) => Some(start),
+            GraphId::Calls | GraphId::DefUse { .. } => None,
+        };
+    }
+
+    pub fn window(&mut self, ctx: &Context, tracer: &Tracer) {
+        let mut open = self.open;
+
+        egui::Window::new("Export")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.heading("Coverage");
+                ui.label(
+                    "The executed basic blocks as a drcov file, for Lighthouse and Lightkeeper.",
+                );
+
+                ui.horizontal(|ui| {
+                    ui.label("Module");
+                    ui.text_edit_singleline(&mut self.module);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Base 0x");
+                    ui.text_edit_singleline(&mut self.base);
+                });
+
+                let base = match self.base.trim() {
+                    "" => Some(0),
+                    base => u64::from_str_radix(base, 16).ok(),
+                };
+
+                match base {
+                    Some(_) if self.module.trim().is_empty() => {
+                        ui.label("Enter the name of the traced binary.");
+                    }
+                    Some(base) => {
+                        if ui.button("Save coverage").clicked() {
+                            let module = drcov::Module {
+                                name: self.module.trim().to_owned(),
+                                base,
+                            };
+
+                            let mut bytes = Vec::new();
+                            self.error = drcov::write(tracer, &module, &mut bytes)
+                                .and_then(|_| {
+                                    let name = format!("{0}.drcov", module.name);
+                                    Ok(file::save(&name, &bytes)?)
+                                })
+                                .err()
+                                .map(|e| e.to_string());
+                        }
+                    }
+                    None => {
+                        ui.label("Enter the base in hexadecimal.");
+                    }
+                }
+
+                ui.separator();
+
+                ui.heading("Tenet");
+                ui.label("The register and memory changes of every instruction, for Tenet.");
+
+                let options = self
+                    .tenet
+                    .get_or_insert_with(|| tenet::Options::new(tracer));
+
+                // A trace opened since may not have the thread.
+                if !tracer.thread_steps.contains_key(&options.thread) {
+                    options.thread = tenet::Options::new(tracer).thread;
+                }
+
+                if tracer.thread_steps.len() > 1 {
+                    egui::ComboBox::from_label("Thread")
+                        .selected_text(options.thread.to_string())
+                        .show_ui(ui, |ui| {
+                            for thread in tracer.thread_steps.keys() {
+                                ui.selectable_value(
+                                    &mut options.thread,
+                                    *thread,
+                                    thread.to_string(),
+                                );
+                            }
+                        });
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Instruction pointer");
+                    ui.text_edit_singleline(&mut options.pc);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Bytes per address");
+                    ui.add(egui::DragValue::new(&mut options.cell).clamp_range(1..=8));
+                });
+
+                if ui.button("Save trace").clicked() {
+                    let mut bytes = Vec::new();
+                    self.error = tenet::write(tracer, options, &mut bytes)
+                        .and_then(|_| Ok(file::save("trace.tenet", &bytes)?))
+                        .err()
+                        .map(|e| e.to_string());
+                }
+
+                ui.separator();
+
+                ui.heading("Flame graph");
+                ui.label("The call stacks, weighted by instructions for inferno or as a timeline for Perfetto.");
+
+                ui.horizontal(|ui| {
+                    if ui.button("Save folded stacks").clicked() {
+                        let mut bytes = Vec::new();
+                        self.error = folded::write(tracer, &mut bytes)
+                            .and_then(|_| Ok(file::save("stacks.folded", &bytes)?))
+                            .err()
+                            .map(|e| e.to_string());
+                    }
+
+                    if ui.button("Save Chrome trace").clicked() {
+                        let mut bytes = Vec::new();
+                        self.error = chrome::write(tracer, &mut bytes)
+                            .and_then(|_| Ok(file::save("trace.json", &bytes)?))
+                            .err()
+                            .map(|e| e.to_string());
+                    }
+                });
+
+                ui.separator();
+
+                ui.heading("Graph");
+
+                let name = |function: Option<u64>| match function {
+                    Some(address) => tracer
+                        .cfg
+                        .function(address)
+                        .map_or_else(String::new, |function| function.name.clone()),
+                    None => String::from(CALL_GRAPH),
+                };
+
+                egui::ComboBox::from_label("Graph")
+                    .selected_text(name(self.function))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.function, None, name(None));
+
+                        for function in tracer.cfg.functions() {
+                            ui.selectable_value(
+                                &mut self.function,
+                                Some(function.start),
+                                function.name.as_str(),
+                            );
+                        }
+                    });
+
+                egui::ComboBox::from_label("Format")
+                    .selected_text(graph::Format::ALL[self.format].name())
+                    .show_ui(ui, |ui| {
+                        for (i, format) in graph::Format::ALL.iter().enumerate() {
+                            ui.selectable_value(&mut self.format, i, format.name());
+                        }
+                    });
+
+                let format = graph::Format::ALL[self.format];
+
+                if format.is_image() {
+                    ui.horizontal(|ui| {
+                        ui.label("Scale");
+                        ui.add(
+                            egui::DragValue::new(&mut self.scale)
+                                .clamp_range(0.5..=graph::MAX_SCALE)
+                                .speed(0.1)
+                                .suffix("x"),
+                        );
+                    });
+                }
+
+                if ui.button("Save graph").clicked() {
+                    let exported = match self.function {
+                        Some(address) => graph::Graph::function(tracer, address),
+                        None => Some(graph::Graph::calls(tracer)),
+                    };
+
+                    if let Some(exported) = exported {
+                        let mut bytes = Vec::new();
+                        let name = format!(
+                            "{0}.{1}",
+                            exported.name.replace(' ', "_"),
+                            format.extension()
+                        );
+
+                        self.error = exported
+                            .write(format, self.scale, &mut bytes)
+                            .and_then(|_| Ok(file::save(&name, &bytes)?))
+                            .err()
+                            .map(|e| e.to_string());
+                    }
+                }
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+            });
+
+        self.open = open;
+    }
+}
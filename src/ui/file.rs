@@ -1,5 +1,3 @@
-type FileData = Vec<u8>;
-
 // Taken from this work, but modified with cfg-if and converting to String not Vec<u8>
 // https://github.com/kirjavascript/trueLMAO/blob/3bab516e577359cb8374a381dd803a651632fcad/frontend/src/widgets/file.rs
 
@@ -8,7 +6,9 @@ cfg_if::cfg_if! {
         use js_sys::{ArrayBuffer, Uint8Array};
         use wasm_bindgen::prelude::*;
         use wasm_bindgen::JsCast;
-        use web_sys::{window, FileReader, HtmlInputElement};
+        use web_sys::{window, Blob, FileReader, HtmlAnchorElement, HtmlInputElement, Url};
+
+        type FileData = Vec<u8>;
 
         pub struct FileDialog {
             tx: std::sync::mpsc::Sender<FileData>,
@@ -97,29 +97,55 @@ cfg_if::cfg_if! {
                 }
             }
         }
+
+        /// Saves a file by having the browser download it.
+        pub fn save(name: &str, bytes: &[u8]) -> std::io::Result<()> {
+            let parts = js_sys::Array::of1(&Uint8Array::from(bytes));
+            let blob = Blob::new_with_u8_array_sequence(&parts).unwrap();
+            let url = Url::create_object_url_with_blob(&blob).unwrap();
+
+            let anchor = window()
+                .unwrap()
+                .document()
+                .unwrap()
+                .create_element("a")
+                .unwrap()
+                .dyn_into::<HtmlAnchorElement>()
+                .unwrap();
+            anchor.set_href(&url);
+            anchor.set_download(name);
+            anchor.click();
+
+            Url::revoke_object_url(&url).unwrap();
+
+            Ok(())
+        }
 } else {
         use rfd;
+        use std::path::PathBuf;
 
+        // Natively the trace is handed over by path, so that it can be memory-mapped and its
+        // sidecar index found next to it.
+        #[derive(Default)]
         pub struct FileDialog {
-            file: Option<FileData>,
-        }
-
-        impl Default for FileDialog {
-            fn default() -> Self {
-                Self { file: None }
-            }
+            path: Option<PathBuf>,
         }
 
         impl FileDialog {
             pub fn open(&mut self) {
-                let path = rfd::FileDialog::new().pick_file();
-                if let Some(path) = path {
-                    self.file = std::fs::read(path).ok()
-                }
+                self.path = rfd::FileDialog::new().pick_file();
             }
 
-            pub fn get(&mut self) -> Option<FileData> {
-                std::mem::replace(&mut self.file, None)
+            pub fn get(&mut self) -> Option<PathBuf> {
+                self.path.take()
+            }
+        }
+
+        /// Saves a file wherever the user picks, unless they cancel.
+        pub fn save(name: &str, bytes: &[u8]) -> std::io::Result<()> {
+            match rfd::FileDialog::new().set_file_name(name).save_file() {
+                Some(path) => std::fs::write(path, bytes),
+                None => Ok(()),
             }
         }
     }
@@ -0,0 +1,159 @@
+use crate::trace::Tracer;
+use egui::Ui;
+use egui_extras::{Column, TableBuilder};
+
+/// What the function table can be sorted by.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum Sort {
+    #[default]
+    Address,
+    Name,
+    Calls,
+    Inclusive,
+    Exclusive,
+    Blocks,
+    Edges,
+    FirstStep,
+    LastStep,
+}
+
+const COLUMNS: [(Sort, &str); 9] = [
+    (Sort::Address, "Address"),
+    (Sort::Name, "Name"),
+    (Sort::Calls, "Calls"),
+    (Sort::Inclusive, "Incl."),
+    (Sort::Exclusive, "Excl."),
+    (Sort::Blocks, "Blocks"),
+    (Sort::Edges, "Edges"),
+    (Sort::FirstStep, "First"),
+    (Sort::LastStep, "Last"),
+];
+
+/// What was done to a function in the table.
+pub enum Action {
+    /// Its name was clicked.
+    Open(u64),
+    /// Renaming it was chosen from its context menu.
+    Rename(u64),
+}
+
+/// Lists every function along with how it was executed, sorted by any column and filtered by
+/// name or address.
+#[derive(Default)]
+pub struct FunctionTable {
+    filter: String,
+    sort: Sort,
+    descending: bool,
+}
+
+impl FunctionTable {
+    /// Shows the table, returning what was done to a function if anything.
+    pub fn ui(&mut self, ui: &mut Ui, tracer: &Tracer) -> Option<Action> {
+        ui.add(egui::TextEdit::singleline(&mut self.filter).hint_text("Filter by name or address"));
+
+        let filter = self.filter.trim().to_lowercase();
+        let filter = filter.strip_prefix("0x").unwrap_or(&filter);
+
+        let mut functions: Vec<_> = tracer
+            .cfg
+            .functions()
+            .filter(|function| {
+                function.name.to_lowercase().contains(filter)
+                    || format!("{0:x}", function.start).contains(filter)
+            })
+            .collect();
+
+        functions.sort_by(|a, b| {
+            let ordering = match self.sort {
+                Sort::Address => a.start.cmp(&b.start),
+                Sort::Name => a.name.cmp(&b.name),
+                Sort::Calls => a.stats.calls.cmp(&b.stats.calls),
+                Sort::Inclusive => a.stats.inclusive.cmp(&b.stats.inclusive),
+                Sort::Exclusive => a.stats.exclusive.cmp(&b.stats.exclusive),
+                Sort::Blocks => a.blocks.len().cmp(&b.blocks.len()),
+                Sort::Edges => a.edges().cmp(&b.edges()),
+                Sort::FirstStep => a.stats.first_step.cmp(&b.stats.first_step),
+                Sort::LastStep => a.stats.last_step.cmp(&b.stats.last_step),
+            };
+
+            // Ties fall back to the address so that the order is always the same.
+            let ordering = ordering.then(a.start.cmp(&b.start));
+
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        let step = |step: Option<usize>| step.map_or(String::from("-"), |step| step.to_string());
+        let height = ui.text_style_height(&egui::TextStyle::Body);
+        let mut action = None;
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .columns(Column::auto().resizable(true), COLUMNS.len())
+            .header(height, |mut header| {
+                for (sort, title) in COLUMNS {
+                    header.col(|ui| {
+                        let arrow = match (self.sort == sort, self.descending) {
+                            (false, _) => "",
+                            (true, false) => " ⬆",
+                            (true, true) => " ⬇",
+                        };
+
+                        if ui.button(format!("{title}{arrow}")).clicked() {
+                            self.descending = self.sort == sort && !self.descending;
+                            self.sort = sort;
+                        }
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(height, functions.len(), |mut row| {
+                    let function = functions[row.index()];
+
+                    row.col(|ui| {
+                        ui.label(format!("0x{0:X}", function.start));
+                    });
+                    row.col(|ui| {
+                        let link = ui.link(&function.name);
+
+                        if link.clicked() {
+                            action = Some(Action::Open(function.start));
+                        }
+
+                        link.context_menu(|ui| {
+                            if ui.button("Rename").clicked() {
+                                action = Some(Action::Rename(function.start));
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    row.col(|ui| {
+                        ui.label(function.stats.calls.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(function.stats.inclusive.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(function.stats.exclusive.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(function.blocks.len().to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(function.edges().to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(step(function.stats.first_step));
+                    });
+                    row.col(|ui| {
+                        ui.label(step(function.stats.last_step));
+                    });
+                });
+            });
+
+        action
+    }
+}
@@ -1,61 +1,295 @@
-use std::time::Duration;
-
-use crate::trace::{Error, GraphFile};
-use egui::{vec2, Context, Response, Ui};
-use egui_plot::{Legend, Plot, PlotImage, PlotPoint};
+use crate::trace::{Changes, DotFile, Error, GraphFile, GraphId, GraphNode, Profile, Tracer};
+use egui::{vec2, Color32, ColorImage, Context, Stroke, Ui};
+use egui_plot::{Legend, Plot, PlotImage, PlotPoint, Polygon};
 use image::DynamicImage;
 use image::EncodableLayout;
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// The color of the hottest nodes of a graph.
+pub const HEAT_COLOR: Color32 = Color32::from_rgb(220, 40, 30);
+
+/// Nodes of a graph which are outlined, such as those with tainted instructions.
+pub struct Highlight {
+    pub name: &'static str,
+    pub color: Color32,
+    pub nodes: BTreeSet<u64>,
+}
+
+impl Highlight {
+    /// Outlines the nodes of the given graph holding any of the given instructions, which are
+    /// the functions they are in for the call graph and the blocks otherwise.
+    pub fn new(
+        name: &'static str,
+        color: Color32,
+        tracer: &Tracer,
+        graph: GraphId,
+        addresses: &BTreeSet<u64>,
+    ) -> Highlight {
+        let nodes = match graph {
+            GraphId::Calls => tracer
+                .cfg
+                .functions()
+                .filter(|function| addresses.iter().any(|address| function.contains(*address)))
+                .map(|function| function.start)
+                .collect(),
+            GraphId::Function(start) => tracer
+                .cfg
+                .function(start)
+                .into_iter()
+                .flat_map(|function| &function.blocks)
+                .filter(|(_, block)| {
+                    block
+                        .block
+                        .keys()
+                        .any(|address| addresses.contains(address))
+                })
+                .map(|(start, _)| *start)
+                .collect(),
+            GraphId::DefUse { .. } => BTreeSet::new(),
+        };
+
+        Highlight { name, color, nodes }
+    }
+}
+
+/// How hot each node of a graph is, from nothing to the hottest node of the graph.
+pub struct Heat {
+    pub nodes: BTreeMap<u64, f32>,
+}
 
-#[derive(Default)]
+impl Heat {
+    /// Heats the nodes of the given graph by how many instructions they executed, which are
+    /// those of the functions themselves for the call graph.
+    pub fn new(tracer: &Tracer, profile: &Profile, graph: GraphId) -> Heat {
+        let counts: Vec<(u64, u64)> = match graph {
+            GraphId::Calls => tracer
+                .cfg
+                .functions()
+                .map(|function| (function.start, function.stats.exclusive))
+                .collect(),
+            GraphId::Function(start) => profile
+                .blocks
+                .iter()
+                .filter(|block| block.function == start)
+                .map(|block| (block.start, block.instructions))
+                .collect(),
+            GraphId::DefUse { .. } => Vec::new(),
+        };
+
+        // Hot loops can run orders of magnitude more than the rest, which would leave
+        // everything else cold on a linear scale.
+        let hottest = counts.iter().map(|(_, count)| *count).max().unwrap_or(0);
+        let nodes = counts
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(node, count)| {
+                let heat = (count as f32).ln_1p() / (hottest as f32).ln_1p();
+                (node, heat)
+            })
+            .collect();
+
+        Heat { nodes }
+    }
+}
+
+/// A graph rendered and decoded, along with where its nodes are.
+type Rendered = Result<(ColorImage, Vec<GraphNode>), Error>;
+
+/// The graphs of a trace, rendered as they are first viewed and again once what they show
+/// changes.
+///
+/// Graphviz runs on a thread of its own, the graph being shown until then if it is out of
+/// date.
 pub struct Graph {
-    current_texture: usize,
-    textures: Vec<egui::TextureHandle>,
+    current: GraphId,
+    /// The rendered graphs along with where their nodes are.
+    textures: BTreeMap<GraphId, (egui::TextureHandle, Vec<GraphNode>)>,
+    /// Rendered graphs which are out of date.
+    stale: BTreeSet<GraphId>,
+    /// Graphs being rendered, whose DOT files are not written again until they are done.
+    pending: BTreeSet<GraphId>,
+    #[cfg(not(target_arch = "wasm32"))]
+    worker: Option<Worker>,
+    /// Why the current graph could not be rendered.
+    error: Option<String>,
 }
 
-impl Graph {
-    pub fn new(ctx: &Context, graphs: &Vec<GraphFile>) -> Result<Graph, Error> {
-        let mut textures = Vec::new();
-
-        std::thread::sleep(Duration::from_secs(1));
-
-        for graph in graphs {
-            let image = image::io::Reader::open(&graph.path)?.decode()?;
-
-            let color_image = match &image {
-                DynamicImage::ImageRgb8(image) => egui::ColorImage::from_rgb(
-                    [image.width() as usize, image.height() as usize],
-                    image.as_bytes(),
-                ),
-                other => {
-                    let image = other.to_rgba8();
-                    egui::ColorImage::from_rgba_unmultiplied(
-                        [image.width() as usize, image.height() as usize],
-                        image.as_bytes(),
-                    )
+/// The thread graphs are rendered on, which stops once the graphs it renders for are dropped.
+#[cfg(not(target_arch = "wasm32"))]
+struct Worker {
+    requests: Sender<(GraphId, DotFile)>,
+    rendered: Receiver<(GraphId, Rendered)>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Worker {
+    /// Starts the thread, which wakes up the UI whenever a graph is done.
+    fn spawn(ctx: Context) -> Worker {
+        let (requests, queue) = channel::<(GraphId, DotFile)>();
+        let (done, rendered) = channel();
+
+        std::thread::spawn(move || {
+            for (id, dot) in queue {
+                if done.send((id, rasterize(&dot))).is_err() {
+                    break;
                 }
-            };
+                ctx.request_repaint();
+            }
+        });
+
+        Worker { requests, rendered }
+    }
+}
 
-            textures.push(ctx.load_texture(&graph.name, color_image, Default::default()));
+impl Graph {
+    /// Shows the given graph first.
+    pub fn new(current: GraphId) -> Graph {
+        Graph {
+            current,
+            textures: BTreeMap::new(),
+            stale: BTreeSet::new(),
+            pending: BTreeSet::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            worker: None,
+            error: None,
         }
+    }
 
-        Ok(Self {
-            current_texture: 0,
-            textures,
-        })
+    /// Marks the graphs of whatever changed in the control flow graph to be rendered again.
+    pub fn invalidate(&mut self, changes: &Changes) {
+        if changes.calls {
+            self.stale.insert(GraphId::Calls);
+        }
+
+        self.stale.extend(
+            changes
+                .functions
+                .iter()
+                .map(|start| GraphId::Function(*start)),
+        );
+
+        // Instructions that were appended may read what the instructions of def-use graphs wrote.
+        if changes.steps {
+            let def_use = self
+                .textures
+                .keys()
+                .chain(&self.pending)
+                .filter(|id| matches!(id, GraphId::DefUse { .. }))
+                .copied()
+                .collect::<Vec<_>>();
+            self.stale.extend(def_use);
+        }
+        self.error = None;
+    }
+
+    /// Shows the given graph.
+    pub fn select(&mut self, id: GraphId) {
+        self.current = id;
+        self.error = None;
+    }
+
+    /// The graph being shown.
+    pub fn current(&self) -> GraphId {
+        self.current
+    }
+
+    /// Swaps in the graphs which finished rendering, then starts rendering the current graph
+    /// if it has not been or is out of date. Failing to is not tried again until the graph is
+    /// selected or changes.
+    fn load(&mut self, ctx: &Context, tracer: &Tracer) {
+        #[cfg(not(target_arch = "wasm32"))]
+        while let Some((id, rendered)) = self
+            .worker
+            .as_ref()
+            .and_then(|worker| worker.rendered.try_recv().ok())
+        {
+            self.finish(ctx, id, rendered);
+        }
+
+        let id = self.current;
+
+        if self.error.is_some()
+            || self.pending.contains(&id)
+            || (self.textures.contains_key(&id) && !self.stale.contains(&id))
+        {
+            return;
+        }
+
+        // The DOT file is written here, as it needs the trace, and only rendered elsewhere.
+        let dot = match tracer.dot(id) {
+            Ok(dot) => dot,
+            Err(e) => {
+                self.error = Some(e.to_string());
+                return;
+            }
+        };
+
+        self.stale.remove(&id);
+        self.pending.insert(id);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let worker = self
+                .worker
+                .get_or_insert_with(|| Worker::spawn(ctx.clone()));
+            if worker.requests.send((id, dot)).is_err() {
+                self.pending.remove(&id);
+                self.error = Some(String::from("The graph renderer stopped."));
+            }
+        }
+
+        // There are no threads to render on.
+        #[cfg(target_arch = "wasm32")]
+        self.finish(ctx, id, rasterize(&dot));
     }
 
-    pub fn set_texture(&mut self, name: &str) {
-        let idx = self
-            .textures
-            .iter()
-            .position(|tx| tx.name() == name)
-            .unwrap();
+    /// Loads a rendered graph into its texture.
+    fn finish(&mut self, ctx: &Context, id: GraphId, rendered: Rendered) {
+        self.pending.remove(&id);
+
+        match rendered {
+            Ok((image, nodes)) => {
+                let texture = match self.textures.remove(&id) {
+                    Some((mut texture, _)) => {
+                        texture.set(image, Default::default());
+                        texture
+                    }
+                    None => ctx.load_texture(format!("{id:?}"), image, Default::default()),
+                };
 
-        self.current_texture = idx;
+                self.textures.insert(id, (texture, nodes));
+            }
+            Err(e) if id == self.current => self.error = Some(e.to_string()),
+            // Graphs which are not in view anymore are rendered again if they come back.
+            Err(_) => {}
+        }
     }
 
-    pub fn ui(&mut self, ui: &mut Ui) -> Response {
-        let texture = &self.textures[self.current_texture];
+    /// Shows the current graph with its nodes filled by how hot they are and the given nodes
+    /// outlined, returning the address of the node that was clicked if any.
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        tracer: &Tracer,
+        heat: Option<&Heat>,
+        highlights: &[Highlight],
+    ) -> Option<u64> {
+        self.load(ui.ctx(), tracer);
+
+        if let Some(error) = &self.error {
+            ui.colored_label(Color32::RED, error);
+            return None;
+        }
+
+        let Some((texture, nodes)) = self.textures.get(&self.current) else {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Rendering...");
+            });
+            return None;
+        };
 
         let aspect_ratio = texture.aspect_ratio();
 
@@ -69,7 +303,107 @@ impl Graph {
             .show_grid(false)
             .show_axes(false);
 
-        plot.show(ui, |plot_ui| plot_ui.image(image.name("graph")))
-            .response
+        plot.show(ui, |plot_ui| {
+            plot_ui.image(image.name("graph"));
+
+            // The image is centered on the origin, one unit high.
+            let outline = |node: &GraphNode| {
+                let left = f64::from((node.x - node.width / 2.0 - 0.5) * aspect_ratio);
+                let right = f64::from((node.x + node.width / 2.0 - 0.5) * aspect_ratio);
+                let bottom = f64::from(node.y - node.height / 2.0 - 0.5);
+                let top = f64::from(node.y + node.height / 2.0 - 0.5);
+
+                vec![[left, bottom], [right, bottom], [right, top], [left, top]]
+            };
+
+            for (node, heat) in nodes.iter().filter_map(|node| {
+                heat.and_then(|heat| heat.nodes.get(&node.address))
+                    .map(|heat| (node, heat))
+            }) {
+                plot_ui.polygon(
+                    Polygon::new(outline(node))
+                        .stroke(Stroke::NONE)
+                        .fill_color(HEAT_COLOR.gamma_multiply(0.1 + 0.5 * heat))
+                        .name("heat"),
+                );
+            }
+
+            for highlight in highlights {
+                for node in nodes
+                    .iter()
+                    .filter(|node| highlight.nodes.contains(&node.address))
+                {
+                    plot_ui.polygon(
+                        Polygon::new(outline(node))
+                            .stroke(Stroke::new(3.0, highlight.color))
+                            .fill_color(highlight.color.gamma_multiply(0.15))
+                            .name(highlight.name),
+                    );
+                }
+            }
+
+            if !plot_ui.response().clicked() {
+                return None;
+            }
+
+            let pointer = plot_ui.pointer_coordinate()?;
+            let x = pointer.x as f32 / aspect_ratio + 0.5;
+            let y = pointer.y as f32 + 0.5;
+
+            nodes
+                .iter()
+                .find(|node| node.contains(x, y))
+                .map(|node| node.address)
+        })
+        .inner
+    }
+}
+
+/// Renders a graph with graphviz and decodes the image.
+fn rasterize(dot: &DotFile) -> Rendered {
+    let graph = dot.render()?;
+    Ok((decode(&graph)?, graph.nodes))
+}
+
+/// Decodes the rendered image of a graph.
+fn decode(graph: &GraphFile) -> Result<ColorImage, Error> {
+    let image = image::io::Reader::open(&graph.path)?.decode()?;
+
+    let color_image = match &image {
+        DynamicImage::ImageRgb8(image) => ColorImage::from_rgb(
+            [image.width() as usize, image.height() as usize],
+            image.as_bytes(),
+        ),
+        other => {
+            let image = other.to_rgba8();
+            ColorImage::from_rgba_unmultiplied(
+                [image.width() as usize, image.height() as usize],
+                image.as_bytes(),
+            )
+        }
+    };
+
+    Ok(color_image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Graph;
+    use crate::trace::{Changes, GraphId};
+
+    #[test]
+    fn def_use_stale_once_appended() {
+        let def_use = GraphId::DefUse { step: 3, depth: 1 };
+        let mut graph = Graph::new(def_use);
+        graph.pending.insert(def_use);
+
+        graph.invalidate(&Changes::default());
+        assert!(graph.stale.is_empty());
+
+        graph.invalidate(&Changes {
+            steps: true,
+            ..Default::default()
+        });
+        assert!(graph.stale.contains(&def_use));
     }
 }
@@ -0,0 +1,289 @@
+use crate::trace::{Event, Tracer};
+use egui::{Color32, ColorImage, Context, TextureHandle, TextureOptions};
+use egui_plot::{Plot, PlotBounds, PlotImage, PlotPoint, VLine};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+const READ: [f32; 3] = [60.0, 120.0, 230.0];
+const WRITE: [f32; 3] = [220.0, 60.0, 50.0];
+/// The most pixels the map is drawn with along either side.
+const MAX_SIZE: usize = 2048;
+/// How far from an access the pointer can be to hover it, in points.
+const HOVER_DISTANCE: f64 = 4.0;
+
+/// Maps every memory access of the trace by address and step, with reads and writes in
+/// different colors.
+#[derive(Default)]
+pub struct HeatmapWindow {
+    pub open: bool,
+    accesses: Option<Accesses>,
+    /// The map as it was last drawn, along with the bounds and size it was drawn for.
+    texture: Option<(PlotBounds, [usize; 2], TextureHandle)>,
+}
+
+/// The memory accesses of the trace, by address.
+struct Accesses {
+    /// The addresses accessed in ascending order, one for every row of the map.
+    addresses: Arc<Vec<u64>>,
+    /// The steps each address was accessed at, in ascending order, and whether it was written.
+    rows: Vec<Vec<(usize, bool)>>,
+    /// How many instructions the trace had, as live traces grow.
+    instructions: usize,
+}
+
+impl Accesses {
+    fn new(tracer: &Tracer) -> Accesses {
+        let mut rows: BTreeMap<u64, Vec<(usize, bool)>> = BTreeMap::new();
+
+        for (step, insn) in tracer.instructions.iter().enumerate() {
+            for event in insn.events() {
+                let (address, write) = match event {
+                    Event::MemRead(address) => (*address, false),
+                    Event::MemWrite(address, _) => (*address, true),
+                    _ => continue,
+                };
+
+                // An instruction may access the same address the same way more than once.
+                let row = rows.entry(address).or_default();
+                let mut same_step = row.iter().rev().take_while(|(other, _)| *other == step);
+                if !same_step.any(|access| *access == (step, write)) {
+                    row.push((step, write));
+                }
+            }
+        }
+
+        Accesses {
+            addresses: Arc::new(rows.keys().copied().collect()),
+            rows: rows.into_values().collect(),
+            instructions: tracer.instructions.len(),
+        }
+    }
+
+    /// The accesses to the row of an address which are within the given steps.
+    fn within(&self, row: usize, min: f64, max: f64) -> &[(usize, bool)] {
+        let accesses = &self.rows[row];
+        let start = accesses.partition_point(|(step, _)| (*step as f64) + 1.0 < min);
+        let end = accesses.partition_point(|(step, _)| (*step as f64) <= max);
+
+        &accesses[start..end.max(start)]
+    }
+
+    /// Draws the accesses within the bounds, every access filling its step and row, or at least
+    /// a pixel, with the color darker the more accesses share a pixel.
+    fn draw(&self, bounds: &PlotBounds, [width, height]: [usize; 2]) -> ColorImage {
+        let [min_x, min_y] = bounds.min();
+        let [max_x, max_y] = bounds.max();
+        let scale_x = width as f64 / (max_x - min_x);
+        let scale_y = height as f64 / (max_y - min_y);
+
+        let span = |from: f64, to: f64, len: usize| {
+            let start = (from.floor().max(0.0) as usize).min(len);
+            let end = (to.ceil().max(0.0) as usize).max(start + 1).min(len);
+            start..end
+        };
+
+        let mut reads = vec![0u32; width * height];
+        let mut writes = vec![0u32; width * height];
+
+        let first = (min_y - 0.5).ceil().max(0.0) as usize;
+        let last = ((max_y + 0.5).floor().max(0.0) as usize).min(self.rows.len());
+
+        for row in first..last {
+            // Rows grow upwards while images grow downwards.
+            let top = (max_y - (row as f64 + 0.5)) * scale_y;
+            let rows = span(top, top + scale_y, height);
+
+            for (step, write) in self.within(row, min_x, max_x) {
+                let left = (*step as f64 - min_x) * scale_x;
+                let columns = span(left, left + scale_x, width);
+
+                let counts = if *write { &mut writes } else { &mut reads };
+                for y in rows.clone() {
+                    for x in columns.clone() {
+                        counts[y * width + x] += 1;
+                    }
+                }
+            }
+        }
+
+        let most = reads
+            .iter()
+            .zip(&writes)
+            .map(|(reads, writes)| reads + writes)
+            .max()
+            .unwrap_or(0);
+
+        let pixels = reads
+            .iter()
+            .zip(&writes)
+            .map(|(reads, writes)| {
+                let count = reads + writes;
+                if count == 0 {
+                    return Color32::TRANSPARENT;
+                }
+
+                let share = *writes as f32 / count as f32;
+                let [r, g, b] =
+                    [0, 1, 2].map(|i| (READ[i] * (1.0 - share) + WRITE[i] * share) as u8);
+                let intensity = (count as f32).ln_1p() / (most as f32).ln_1p();
+
+                Color32::from_rgba_unmultiplied(r, g, b, (90.0 + 165.0 * intensity) as u8)
+            })
+            .collect();
+
+        ColorImage {
+            size: [width, height],
+            pixels,
+        }
+    }
+
+    /// The access closest to a point in the same row, if it is within the given distance of it
+    /// in steps.
+    fn hovered(&self, point: PlotPoint, distance: f64) -> Option<(usize, u64, bool)> {
+        let row = point.y.round();
+        if row < 0.0 || row as usize >= self.rows.len() {
+            return None;
+        }
+
+        let row = row as usize;
+        self.within(row, point.x - distance, point.x + distance)
+            .iter()
+            .min_by(|(a, _), (b, _)| {
+                let a = (*a as f64 + 0.5 - point.x).abs();
+                let b = (*b as f64 + 0.5 - point.x).abs();
+                a.total_cmp(&b)
+            })
+            .map(|(step, write)| (*step, self.addresses[row], *write))
+    }
+}
+
+impl HeatmapWindow {
+    /// Forgets the accesses of the previous trace.
+    pub fn reset(&mut self) {
+        self.accesses = None;
+        self.texture = None;
+    }
+
+    pub fn window(&mut self, ctx: &Context, tracer: &mut Tracer) {
+        let mut open = self.open;
+
+        egui::Window::new("Memory heatmap")
+            .open(&mut open)
+            .default_size([700.0, 450.0])
+            .show(ctx, |ui| {
+                if self
+                    .accesses
+                    .as_ref()
+                    .is_none_or(|accesses| accesses.instructions != tracer.instructions.len())
+                {
+                    self.accesses = Some(Accesses::new(tracer));
+                    self.texture = None;
+                }
+
+                let Some(accesses) = &self.accesses else {
+                    return;
+                };
+
+                ui.label(format!(
+                    "{0} addresses, reads in blue and writes in red",
+                    accesses.rows.len()
+                ));
+
+                let addresses = accesses.addresses.clone();
+                let plot = Plot::new("heatmap")
+                    .include_x(0.0)
+                    .include_x(tracer.instructions.len() as f64)
+                    .include_y(-0.5)
+                    .include_y(accesses.rows.len() as f64 - 0.5)
+                    .x_axis_label("Step")
+                    .y_axis_label("Address")
+                    .y_axis_formatter(move |value, _, _| {
+                        if value < 0.0 || value.fract() != 0.0 {
+                            return String::new();
+                        }
+
+                        addresses
+                            .get(value as usize)
+                            .map_or_else(String::new, |address| format!("0x{address:X}"))
+                    })
+                    .label_formatter(|_, _| String::new());
+
+                let response = plot.show(ui, |plot_ui| {
+                    let bounds = plot_ui.plot_bounds();
+                    let rect = plot_ui.response().rect;
+                    let pixels = ctx.pixels_per_point();
+                    let size = [rect.width(), rect.height()]
+                        .map(|side| ((side * pixels) as usize).clamp(1, MAX_SIZE));
+
+                    let drawn = self.texture.as_ref().is_some_and(|(drawn, drawn_size, _)| {
+                        *drawn == bounds && *drawn_size == size
+                    });
+
+                    if !drawn && bounds.is_valid() {
+                        let image = accesses.draw(&bounds, size);
+
+                        match &mut self.texture {
+                            Some((drawn, drawn_size, texture)) => {
+                                texture.set(image, TextureOptions::NEAREST);
+                                *drawn = bounds;
+                                *drawn_size = size;
+                            }
+                            None => {
+                                let texture =
+                                    ctx.load_texture("heatmap", image, TextureOptions::NEAREST);
+                                self.texture = Some((bounds, size, texture));
+                            }
+                        }
+                    }
+
+                    if let Some((bounds, _, texture)) = &self.texture {
+                        let [width, height] = [bounds.width(), bounds.height()];
+                        plot_ui.image(PlotImage::new(
+                            texture,
+                            bounds.center(),
+                            [width as f32, height as f32],
+                        ));
+                    }
+
+                    plot_ui.vline(VLine::new(tracer.step as f64).name("current step"));
+
+                    // How many steps the pointer can be away from an access to hover it.
+                    let distance = HOVER_DISTANCE * bounds.width() / f64::from(rect.width());
+                    let hovered = plot_ui
+                        .pointer_coordinate()
+                        .and_then(|point| accesses.hovered(point, distance));
+
+                    let clicked = plot_ui.response().clicked();
+                    let pointer = plot_ui.pointer_coordinate();
+
+                    (hovered, clicked, pointer)
+                });
+
+                let (hovered, clicked, pointer) = response.inner;
+
+                if let Some((step, address, write)) = hovered {
+                    let insn = &tracer.instructions[step];
+                    let kind = if write { "Write" } else { "Read" };
+
+                    response.response.on_hover_ui_at_pointer(|ui| {
+                        ui.label(format!("Step {step}"));
+                        ui.label(format!("{kind} of 0x{address:X}"));
+                        ui.label(format!("0x{0:X}: {1}", insn.addr(), insn.insn));
+                    });
+                }
+
+                if clicked {
+                    // Clicking an access seeks to it, and anywhere else to the step there.
+                    let step = hovered
+                        .map(|(step, _, _)| step)
+                        .or(pointer.map(|pointer| pointer.x.max(0.0) as usize));
+
+                    if let Some(step) = step {
+                        tracer.seek(step);
+                    }
+                }
+            });
+
+        self.open = open;
+    }
+}
@@ -0,0 +1,142 @@
+use super::graph::Graph;
+use crate::trace::{stream::Stream, Changes, GraphId, Tracer};
+use egui::Context;
+use std::time::Duration;
+
+/// How often the graphs are rendered again while instructions keep arriving, in seconds.
+const REFRESH_INTERVAL: f64 = 2.0;
+
+/// Listens for a live trace and keeps the tracer and graphs up to date as it arrives.
+pub struct Live {
+    pub open: bool,
+    address: String,
+    stream: Option<Stream>,
+    /// What changed since the graphs were last marked out of date.
+    changes: Changes,
+    /// When the graphs were last marked out of date, in seconds since the UI started.
+    refreshed: f64,
+    error: Option<String>,
+}
+
+impl Default for Live {
+    fn default() -> Self {
+        Self {
+            open: false,
+            address: String::from("127.0.0.1:7878"),
+            stream: None,
+            changes: Changes::default(),
+            refreshed: 0.0,
+            error: None,
+        }
+    }
+}
+
+impl Live {
+    /// Shows the window used to start listening, which replaces the current trace, returning
+    /// whether it was.
+    pub fn window(
+        &mut self,
+        ctx: &Context,
+        tracer: &mut Option<Tracer>,
+        graph: &mut Option<Graph>,
+    ) -> bool {
+        let mut open = self.open;
+        let mut listening = false;
+
+        egui::Window::new("Live trace")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Address");
+                    ui.text_edit_singleline(&mut self.address);
+                });
+                ui.label("A TCP address, or a Unix socket path prefixed with unix:");
+
+                if ui.button("Listen").clicked() {
+                    let ctx = ctx.clone();
+
+                    match Stream::listen(&self.address, move || ctx.request_repaint()) {
+                        Ok(stream) => {
+                            *tracer = None;
+                            *graph = None;
+                            self.stream = Some(stream);
+                            self.changes = Changes::default();
+                            self.error = None;
+                            listening = true;
+                        }
+                        Err(e) => self.error = Some(e.to_string()),
+                    }
+                }
+
+                if let Some(stream) = &self.stream {
+                    if let Some(error) = stream.error() {
+                        ui.colored_label(egui::Color32::RED, error.to_string());
+                    }
+
+                    match tracer {
+                        _ if stream.closed() => ui.label("Finished receiving."),
+                        Some(tracer) => ui.label(format!(
+                            "Received {0} instructions from {1}.",
+                            tracer.instructions.len(),
+                            stream.address
+                        )),
+                        None => ui.label(format!("Waiting on {0}...", stream.address)),
+                    };
+                }
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+            });
+
+        self.open = open;
+        listening
+    }
+
+    /// Stops receiving, as the tracer it was building has been replaced.
+    pub fn stop(&mut self) {
+        self.stream = None;
+        self.changes = Changes::default();
+        self.error = None;
+    }
+
+    /// Moves whatever has been received into the tracer, rendering the graphs again at most every
+    /// [`REFRESH_INTERVAL`] seconds so that the UI stays responsive.
+    pub fn update(
+        &mut self,
+        ctx: &Context,
+        tracer: &mut Option<Tracer>,
+        graph: &mut Option<Graph>,
+    ) {
+        let Some(stream) = &mut self.stream else {
+            return;
+        };
+
+        match stream.receive(tracer) {
+            Ok(changes) => self.changes.merge(changes),
+            Err(e) => self.error = Some(e.to_string()),
+        }
+
+        let closed = stream.closed();
+        let now = ctx.input(|i| i.time);
+        if self.changes.is_empty() {
+            return;
+        }
+
+        if !closed && now - self.refreshed < REFRESH_INTERVAL {
+            ctx.request_repaint_after(Duration::from_secs_f64(REFRESH_INTERVAL));
+            return;
+        }
+
+        // Only the graph in view is rendered again, once it is out of date.
+        if tracer.is_some() {
+            graph
+                .get_or_insert_with(|| Graph::new(GraphId::Calls))
+                .invalidate(&self.changes);
+        }
+
+        self.changes = Changes::default();
+        self.refreshed = now;
+    }
+}
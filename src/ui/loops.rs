@@ -0,0 +1,330 @@
+use crate::trace::Tracer;
+use egui::{Context, RichText};
+use egui_extras::{Column, TableBuilder};
+use std::collections::BTreeSet;
+
+/// Lists the loops of the selected function with how many times they went around, along with
+/// what dominates the selected block, and steps through the iterations of one of them.
+#[derive(Default)]
+pub struct LoopsWindow {
+    pub open: bool,
+    /// The start of the function whose loops are listed.
+    function: Option<u64>,
+    block: Option<u64>,
+    /// The header of the loop whose iterations are stepped through.
+    header: Option<u64>,
+    /// The registers shown for every iteration, by their index.
+    registers: BTreeSet<usize>,
+    history: Option<History>,
+}
+
+/// The chosen registers at the start of every iteration of a loop, kept until the loop, the
+/// thread, the registers or the trace change.
+struct History {
+    header: u64,
+    thread: u32,
+    registers: BTreeSet<usize>,
+    steps: usize,
+    values: Vec<Vec<String>>,
+}
+
+impl LoopsWindow {
+    /// Forgets the function and iterations of the previous trace.
+    pub fn reset(&mut self) {
+        self.function = None;
+        self.block = None;
+        self.header = None;
+        self.history = None;
+    }
+
+    /// Selects a function by where it starts, or a block of the function being viewed.
+    pub fn select(&mut self, address: u64, tracer: &Tracer) {
+        let in_function = |function: u64| {
+            tracer
+                .cfg
+                .function(function)
+                .is_some_and(|function| function.blocks.contains_key(&address))
+        };
+
+        if tracer.cfg.function(address).is_some() {
+            self.function = Some(address);
+        } else if !self.function.is_some_and(in_function) {
+            self.function = tracer
+                .cfg
+                .functions()
+                .find(|function| function.blocks.contains_key(&address))
+                .map(|function| function.start);
+        }
+
+        self.block = Some(address);
+    }
+
+    /// Shows the window, following the iterations of the given thread or otherwise those of the
+    /// thread at the current step.
+    pub fn window(&mut self, ctx: &Context, tracer: &mut Tracer, thread: Option<u32>) {
+        let mut open = self.open;
+
+        egui::Window::new("Loops").open(&mut open).show(ctx, |ui| {
+            let Some(function) = self.function.and_then(|start| tracer.cfg.function(start)) else {
+                ui.label("Select a function or a block.");
+                return;
+            };
+
+            let name = |address: u64| match tracer.cfg.symbols().get(address) {
+                Some(name) => name.to_owned(),
+                None => format!("0x{address:X}"),
+            };
+
+            ui.label(format!("Function {0}", function.name));
+
+            let loops = function.loops();
+
+            if loops.is_empty() {
+                ui.label("Nothing loops.");
+            } else {
+                egui::Grid::new("loops").striped(true).show(ui, |ui| {
+                    for heading in [
+                        "Header",
+                        "Depth",
+                        "Blocks",
+                        "Back edges",
+                        "Entries",
+                        "Iterations",
+                    ] {
+                        ui.strong(heading);
+                    }
+                    ui.end_row();
+
+                    for natural in &loops {
+                        let depth = std::iter::successors(Some(natural), |natural| {
+                            let parent = natural.parent?;
+                            loops.iter().find(|other| other.header == parent)
+                        })
+                        .count();
+
+                        if ui.link(name(natural.header)).clicked() {
+                            self.block = Some(natural.header);
+                            self.header = Some(natural.header);
+                        }
+
+                        let back_edges: Vec<String> = natural
+                            .back_edges
+                            .iter()
+                            .map(|(from, count)| format!("{0} ({count})", name(*from)))
+                            .collect();
+
+                        ui.label(depth.to_string());
+                        ui.label(natural.blocks.len().to_string());
+                        ui.label(back_edges.join(", "));
+                        ui.label(natural.entries.to_string());
+                        ui.label(natural.iterations().to_string());
+                        ui.end_row();
+                    }
+                });
+            }
+
+            if let Some(block) = self
+                .block
+                .filter(|block| function.blocks.contains_key(block))
+            {
+                ui.separator();
+                ui.label(format!("Block {0}", name(block)));
+
+                let dominator = function.dominators().immediate(block);
+                let post_dominator = function.post_dominators().immediate(block);
+                let headers: Vec<String> = loops
+                    .iter()
+                    .filter(|natural| natural.blocks.contains(&block))
+                    .map(|natural| name(natural.header))
+                    .collect();
+
+                ui.label(format!(
+                    "Immediate dominator: {0}",
+                    dominator.map_or_else(|| String::from("none"), name)
+                ));
+                ui.label(format!(
+                    "Immediate post-dominator: {0}",
+                    post_dominator.map_or_else(|| String::from("none"), name)
+                ));
+                if !headers.is_empty() {
+                    ui.label(format!("In the loops of {0}", headers.join(", ")));
+                }
+            }
+
+            let Some(natural) = loops
+                .iter()
+                .find(|natural| Some(natural.header) == self.header)
+            else {
+                return;
+            };
+
+            let thread = thread.unwrap_or_else(|| tracer.thread());
+            let iterations = tracer.iterations(function, natural, thread);
+
+            ui.separator();
+            ui.label(format!(
+                "Iterations of the loop at {0} in thread {thread}",
+                name(natural.header)
+            ));
+
+            // How many iterations have started by the current step, and since the loop was
+            // last entered.
+            let current = iterations.partition_point(|iteration| iteration.step <= tracer.step);
+            let entered = iterations[..current]
+                .iter()
+                .rposition(|iteration| iteration.entry)
+                .unwrap_or(0);
+
+            let mut seek = None;
+
+            ui.horizontal(|ui| {
+                let previous = iterations[..current]
+                    .iter()
+                    .rev()
+                    .find(|iteration| iteration.step < tracer.step);
+                let next = iterations.get(current);
+
+                if ui
+                    .add_enabled(previous.is_some(), egui::Button::new("Previous iteration"))
+                    .clicked()
+                {
+                    seek = previous.map(|iteration| iteration.step);
+                }
+
+                if ui
+                    .add_enabled(next.is_some(), egui::Button::new("Next iteration"))
+                    .clicked()
+                {
+                    seek = next.map(|iteration| iteration.step);
+                }
+
+                if current == 0 {
+                    ui.label(format!("Before the first of {0}", iterations.len()));
+                } else {
+                    ui.label(format!(
+                        "Iteration {current} of {0}, {1} since entering",
+                        iterations.len(),
+                        current - entered
+                    ));
+                }
+            });
+
+            ui.collapsing("Registers", |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for (i, register) in tracer.initial_registers().iter().enumerate() {
+                        let mut chosen = self.registers.contains(&i);
+
+                        if ui.checkbox(&mut chosen, register.name()).changed() {
+                            if chosen {
+                                self.registers.insert(i);
+                            } else {
+                                self.registers.remove(&i);
+                            }
+                        }
+                    }
+                });
+            });
+
+            let stale = self.history.as_ref().is_none_or(|history| {
+                history.header != natural.header
+                    || history.thread != thread
+                    || history.registers != self.registers
+                    || history.steps != tracer.instructions.len()
+            });
+
+            if stale {
+                let steps: Vec<usize> = iterations.iter().map(|iteration| iteration.step).collect();
+                let values = tracer
+                    .registers_at(thread, &steps)
+                    .iter()
+                    .map(|registers| {
+                        registers
+                            .iter()
+                            .enumerate()
+                            .filter(|(i, _)| self.registers.contains(i))
+                            .map(|(_, register)| register.hex())
+                            .collect()
+                    })
+                    .collect();
+
+                self.history = Some(History {
+                    header: natural.header,
+                    thread,
+                    registers: self.registers.clone(),
+                    steps: tracer.instructions.len(),
+                    values,
+                });
+            }
+
+            let Some(history) = &self.history else {
+                return;
+            };
+
+            let names: Vec<&str> = tracer
+                .initial_registers()
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| self.registers.contains(i))
+                .map(|(_, register)| register.name())
+                .collect();
+            let height = ui.text_style_height(&egui::TextStyle::Body);
+            let changed_color = ui.visuals().warn_fg_color;
+
+            TableBuilder::new(ui)
+                .striped(true)
+                .columns(Column::auto().resizable(true), names.len() + 2)
+                .header(height, |mut header| {
+                    for heading in ["Iteration", "Step"].into_iter().chain(names) {
+                        header.col(|ui| {
+                            ui.strong(heading);
+                        });
+                    }
+                })
+                .body(|body| {
+                    body.rows(height, iterations.len(), |mut row| {
+                        let i = row.index();
+                        let iteration = &iterations[i];
+
+                        // The iteration being run is in bold, entries into the loop are marked.
+                        let text = |text: String| {
+                            if i + 1 == current {
+                                RichText::new(text).strong()
+                            } else {
+                                RichText::new(text)
+                            }
+                        };
+                        let mark = if iteration.entry { " ↪" } else { "" };
+
+                        row.col(|ui| {
+                            ui.label(text(format!("{0}{mark}", i + 1)));
+                        });
+                        row.col(|ui| {
+                            if ui.link(text(iteration.step.to_string())).clicked() {
+                                seek = Some(iteration.step);
+                            }
+                        });
+
+                        for (j, value) in history.values[i].iter().enumerate() {
+                            // Values that changed since the previous iteration stand out.
+                            let changed = i > 0 && history.values[i - 1][j] != *value;
+                            let value = if changed {
+                                text(value.clone()).color(changed_color)
+                            } else {
+                                text(value.clone())
+                            };
+
+                            row.col(|ui| {
+                                ui.label(value);
+                            });
+                        }
+                    });
+                });
+
+            if let Some(step) = seek {
+                tracer.seek(step);
+            }
+        });
+
+        self.open = open;
+    }
+}
@@ -0,0 +1,95 @@
+use super::{
+    dataflow::{self, Follow},
+    taint,
+};
+use crate::trace::{Location, Tracer};
+use egui::{Context, RichText};
+use egui_extras::{Column, TableBuilder};
+use std::collections::BTreeSet;
+
+/// Shows the memory as it is at the current step, with the tainted cells marked.
+#[derive(Default)]
+pub struct MemoryWindow {
+    pub open: bool,
+    /// Only the cells whose address contains this in hexadecimal are shown.
+    filter: String,
+    /// Whether only the tainted cells are shown.
+    tainted_only: bool,
+}
+
+impl MemoryWindow {
+    /// Shows the window, returning which way to follow the data of a cell if one was chosen.
+    pub fn window(
+        &mut self,
+        ctx: &Context,
+        tracer: &Tracer,
+        tainted: Option<&BTreeSet<Location>>,
+    ) -> Option<Follow> {
+        let mut open = self.open;
+        let mut follow = None;
+
+        egui::Window::new("Memory").open(&mut open).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Address 0x");
+                ui.text_edit_singleline(&mut self.filter);
+                ui.checkbox(&mut self.tainted_only, "Tainted only");
+            });
+
+            let filter = self.filter.trim().to_uppercase();
+            let is_tainted = |address: u64| {
+                tainted.is_some_and(|tainted| tainted.contains(&Location::Memory(address)))
+            };
+
+            let cells: Vec<(u64, u64)> = tracer
+                .memory
+                .iter()
+                .filter(|(address, _)| format!("{address:X}").contains(&filter))
+                .filter(|(address, _)| !self.tainted_only || is_tainted(**address))
+                .map(|(address, value)| (*address, *value))
+                .collect();
+
+            let height = ui.text_style_height(&egui::TextStyle::Body);
+
+            TableBuilder::new(ui)
+                .striped(true)
+                .columns(Column::auto().resizable(true), 2)
+                .header(height, |mut header| {
+                    for heading in ["Address", "Value"] {
+                        header.col(|ui| {
+                            ui.strong(heading);
+                        });
+                    }
+                })
+                .body(|body| {
+                    body.rows(height, cells.len(), |mut row| {
+                        let (address, value) = cells[row.index()];
+
+                        let text = |text: String| {
+                            if is_tainted(address) {
+                                RichText::new(text).color(taint::COLOR)
+                            } else {
+                                RichText::new(text)
+                            }
+                        };
+
+                        row.col(|ui| {
+                            let label = egui::Label::new(text(format!("0x{address:X}")))
+                                .sense(egui::Sense::click());
+
+                            let location = Location::Memory(address);
+                            if let Some(chosen) = dataflow::menu(ui.add(label), location) {
+                                follow = Some(chosen);
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.label(text(format!("0x{value:X}")));
+                        });
+                    });
+                });
+        });
+
+        self.open = open;
+
+        follow
+    }
+}
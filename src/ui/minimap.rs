@@ -0,0 +1,310 @@
+use crate::trace::Tracer;
+use egui::{ecolor::Hsva, pos2, vec2, Color32, Rect, Sense, Stroke, Ui};
+use std::collections::BTreeSet;
+
+/// How tall the timeline is, in points.
+const HEIGHT: f32 = 48.0;
+const BREAKPOINT_COLOR: Color32 = Color32::from_rgb(220, 50, 50);
+const WATCHPOINT_COLOR: Color32 = Color32::from_rgb(230, 160, 30);
+
+/// A timeline of the whole trace showing how deep in calls every step was, with the function
+/// executing colored and the hits of breakpoints and watchpoints ticked.
+pub struct Minimap {
+    pub open: bool,
+    /// The call depth and function of every step, along with how many instructions the trace
+    /// had, as live traces grow.
+    depths: Option<(usize, Vec<(usize, u64)>)>,
+    /// The deepest call of every column of the timeline and the function making it.
+    columns: Vec<(usize, u64)>,
+    /// Where the hits of the breakpoints and then of the watchpoints are ticked, from the left
+    /// of the timeline.
+    ticks: [Vec<f32>; 2],
+    /// How many instructions the trace had and how wide the timeline was when the columns
+    /// and ticks were laid out.
+    laid_out: Option<(usize, f32)>,
+    /// Instruction addresses whose executions are ticked.
+    breakpoints: BTreeSet<u64>,
+    /// Memory addresses whose accesses are ticked, with the steps of those accesses.
+    watchpoints: Vec<(u64, Vec<usize>)>,
+    breakpoint: String,
+    watchpoint: String,
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Minimap {
+            open: true,
+            depths: None,
+            columns: Vec::new(),
+            ticks: [Vec::new(), Vec::new()],
+            laid_out: None,
+            breakpoints: BTreeSet::new(),
+            watchpoints: Vec::new(),
+            breakpoint: String::new(),
+            watchpoint: String::new(),
+        }
+    }
+}
+
+impl Minimap {
+    /// Forgets the previous trace, keeping the breakpoints and watchpoints.
+    pub fn reset(&mut self) {
+        self.depths = None;
+        self.laid_out = None;
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, tracer: &mut Tracer) {
+        let len = tracer.instructions.len();
+
+        if self.depths.as_ref().map(|(depths, _)| *depths) != Some(len) {
+            let mut depths = Vec::with_capacity(len);
+            tracer.call_stacks(|_, _, stack| {
+                let function = stack.last().map_or(0, |frame| frame.function);
+                depths.push((stack.len(), function));
+            });
+
+            for (address, steps) in &mut self.watchpoints {
+                *steps = tracer.memory_accesses(*address);
+            }
+
+            self.depths = Some((len, depths));
+            self.laid_out = None;
+        }
+
+        let mut seek = None;
+
+        ui.horizontal(|ui| {
+            let (previous, next) = self.hits_around(tracer);
+
+            if ui
+                .add_enabled(previous.is_some(), egui::Button::new("Previous hit"))
+                .clicked()
+            {
+                seek = previous;
+            }
+            if ui
+                .add_enabled(next.is_some(), egui::Button::new("Next hit"))
+                .clicked()
+            {
+                seek = next;
+            }
+
+            ui.separator();
+
+            ui.label("Break at 0x");
+            ui.add(egui::TextEdit::singleline(&mut self.breakpoint).desired_width(80.0));
+            if ui.button("Add").clicked() {
+                if let Ok(address) = u64::from_str_radix(self.breakpoint.trim(), 16) {
+                    self.breakpoints.insert(address);
+                    self.breakpoint.clear();
+                    self.laid_out = None;
+                }
+            }
+
+            ui.label("Watch 0x");
+            ui.add(egui::TextEdit::singleline(&mut self.watchpoint).desired_width(80.0));
+            if ui.button("Add").clicked() {
+                if let Ok(address) = u64::from_str_radix(self.watchpoint.trim(), 16) {
+                    if !self
+                        .watchpoints
+                        .iter()
+                        .any(|(watched, _)| *watched == address)
+                    {
+                        self.watchpoints
+                            .push((address, tracer.memory_accesses(address)));
+                        self.laid_out = None;
+                    }
+                    self.watchpoint.clear();
+                }
+            }
+
+            ui.separator();
+
+            let mut removed = None;
+            for address in &self.breakpoints {
+                let text = egui::RichText::new(format!("0x{address:X} ❌")).color(BREAKPOINT_COLOR);
+                if ui.button(text).clicked() {
+                    removed = Some(*address);
+                }
+            }
+            if let Some(address) = removed {
+                self.breakpoints.remove(&address);
+                self.laid_out = None;
+            }
+
+            let mut removed = None;
+            for (i, (address, _)) in self.watchpoints.iter().enumerate() {
+                let text = egui::RichText::new(format!("0x{address:X} ❌")).color(WATCHPOINT_COLOR);
+                if ui.button(text).clicked() {
+                    removed = Some(i);
+                }
+            }
+            if let Some(i) = removed {
+                self.watchpoints.remove(i);
+                self.laid_out = None;
+            }
+        });
+
+        let (rect, response) =
+            ui.allocate_exact_size(vec2(ui.available_width(), HEIGHT), Sense::click_and_drag());
+        self.layout(tracer, len, rect.width());
+        let columns = &self.columns;
+        let width = rect.width() / columns.len().max(1) as f32;
+        let deepest = columns
+            .iter()
+            .map(|(depth, _)| *depth)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let painter = ui.painter_at(rect);
+
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+        for (x, (depth, function)) in columns.iter().enumerate() {
+            if *depth == 0 {
+                continue;
+            }
+
+            let height = rect.height() * *depth as f32 / deepest as f32;
+            let left = rect.left() + x as f32 * width;
+            painter.rect_filled(
+                Rect::from_min_max(
+                    pos2(left, rect.bottom() - height),
+                    pos2(left + width, rect.bottom()),
+                ),
+                0.0,
+                color(*function),
+            );
+        }
+
+        for (ticks, color) in self.ticks.iter().zip([BREAKPOINT_COLOR, WATCHPOINT_COLOR]) {
+            for tick in ticks {
+                let x = rect.left() + tick;
+                painter.line_segment(
+                    [
+                        pos2(x, rect.top()),
+                        pos2(x, rect.top() + rect.height() / 4.0),
+                    ],
+                    Stroke::new(1.0, color),
+                );
+            }
+        }
+
+        let current = rect.left() + tick(tracer.step, len, rect.width());
+        painter.line_segment(
+            [pos2(current, rect.top()), pos2(current, rect.bottom())],
+            Stroke::new(2.0, ui.visuals().strong_text_color()),
+        );
+
+        // The step under a point along the timeline.
+        let step = |pointer: egui::Pos2| {
+            let fraction = ((pointer.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+            ((fraction * len as f32) as usize).min(len.saturating_sub(1))
+        };
+
+        if let Some(pointer) = response.hover_pos() {
+            let hovered = step(pointer);
+            let (depth, function) = self.depths.as_ref().unwrap().1[hovered];
+            let name = tracer
+                .cfg
+                .function(function)
+                .map_or_else(|| format!("sub_{function:X}"), |f| f.name.clone());
+
+            response.clone().on_hover_ui_at_pointer(|ui| {
+                ui.label(format!("Step {hovered}"));
+                ui.label(format!("{name} at depth {depth}"));
+            });
+        }
+
+        // Clicking or dragging along the timeline seeks there.
+        if response.clicked() || response.dragged() {
+            if let Some(pointer) = response.interact_pointer_pos() {
+                seek = Some(step(pointer));
+            }
+        }
+
+        if let Some(step) = seek {
+            tracer.seek(step);
+        }
+    }
+
+    /// The steps at which each breakpoint and watchpoint was hit, in ascending order.
+    fn hits<'a>(&'a self, tracer: &'a Tracer) -> impl Iterator<Item = &'a Vec<usize>> {
+        self.breakpoints
+            .iter()
+            .filter_map(|address| tracer.steps.get(address))
+            .chain(self.watchpoints.iter().map(|(_, steps)| steps))
+    }
+
+    /// The last hit before the current step and the first one after it.
+    fn hits_around(&self, tracer: &Tracer) -> (Option<usize>, Option<usize>) {
+        let mut previous = None;
+        let mut next: Option<usize> = None;
+
+        for steps in self.hits(tracer) {
+            let before = steps.partition_point(|step| *step < tracer.step);
+            if let Some(step) = before.checked_sub(1).map(|i| steps[i]) {
+                previous = previous.max(Some(step));
+            }
+
+            let after = steps.partition_point(|step| *step <= tracer.step);
+            if let Some(step) = steps.get(after) {
+                next = Some(next.map_or(*step, |next| next.min(*step)));
+            }
+        }
+
+        (previous, next)
+    }
+
+    /// Splits the steps into a column for every point of a timeline of the given width, or
+    /// one for every step when there are fewer, keeping the deepest call of each, and works
+    /// out where the hits are ticked.
+    fn layout(&mut self, tracer: &Tracer, len: usize, width: f32) {
+        if self.laid_out != Some((len, width)) {
+            let depths = &self.depths.as_ref().unwrap().1;
+            let count = (width.max(1.0) as usize).min(len.max(1));
+            self.columns = (0..count)
+                .map(|column| {
+                    let start = column * len / count;
+                    let end = ((column + 1) * len / count).max(start + 1).min(len);
+
+                    depths[start..end]
+                        .iter()
+                        .copied()
+                        .max_by_key(|(depth, _)| *depth)
+                        .unwrap_or((0, 0))
+                })
+                .collect();
+
+            // Hits which land on the same point are ticked once.
+            let ticks = |steps: &mut dyn Iterator<Item = &usize>| -> Vec<f32> {
+                let points: BTreeSet<i32> =
+                    steps.map(|step| tick(*step, len, width) as i32).collect();
+                points.into_iter().map(|point| point as f32).collect()
+            };
+
+            self.ticks = [
+                ticks(
+                    &mut self
+                        .breakpoints
+                        .iter()
+                        .filter_map(|address| tracer.steps.get(address))
+                        .flatten(),
+                ),
+                ticks(&mut self.watchpoints.iter().flat_map(|(_, steps)| steps)),
+            ];
+            self.laid_out = Some((len, width));
+        }
+    }
+}
+
+/// Where a step is along a timeline of the given width.
+fn tick(step: usize, len: usize, width: f32) -> f32 {
+    width * step as f32 / len.max(1) as f32
+}
+
+/// A color of its own for every function, spread around the hues.
+fn color(function: u64) -> Color32 {
+    let hue = (function as f64 * 0.618_033_988_75).fract() as f32;
+    Hsva::new(hue, 0.55, 0.8, 1.0).into()
+}
@@ -0,0 +1,118 @@
+use super::dataflow::{describe, LocationPicker};
+use crate::trace::{Location, Tracer};
+use egui::Context;
+use egui_plot::{Legend, Line, Plot, PlotPoints, VLine};
+
+/// Plots the values of registers and memory over the steps of the trace.
+#[derive(Default)]
+pub struct PlotWindow {
+    pub open: bool,
+    picker: LocationPicker,
+    /// What is plotted, with the values it held and from which step.
+    plotted: Vec<(Location, Vec<(usize, u64)>)>,
+    /// How many instructions the trace had when the values were gathered, as live traces grow.
+    instructions: usize,
+}
+
+impl PlotWindow {
+    /// Stops plotting the locations of the previous trace.
+    pub fn reset(&mut self) {
+        self.plotted.clear();
+    }
+
+    /// Plots a location, unless it already is.
+    pub fn add(&mut self, tracer: &Tracer, location: Location) {
+        if !self.plotted.iter().any(|(plotted, _)| *plotted == location) {
+            self.plotted.push((location, tracer.history(location)));
+        }
+
+        self.open = true;
+    }
+
+    /// Shows the window, picking registers from the given thread or otherwise the one at the
+    /// current step.
+    pub fn window(&mut self, ctx: &Context, tracer: &mut Tracer, thread: Option<u32>) {
+        let mut open = self.open;
+        let thread = thread.unwrap_or_else(|| tracer.thread());
+
+        if self.instructions != tracer.instructions.len() {
+            for (location, history) in &mut self.plotted {
+                *history = tracer.history(*location);
+            }
+            self.instructions = tracer.instructions.len();
+        }
+
+        egui::Window::new("Values").open(&mut open).show(ctx, |ui| {
+            let mut added = None;
+            let mut removed = None;
+
+            ui.horizontal(|ui| {
+                let location = self.picker.ui(ui, tracer, thread);
+
+                if ui
+                    .add_enabled(location.is_some(), egui::Button::new("Plot"))
+                    .clicked()
+                {
+                    added = location;
+                }
+            });
+
+            ui.horizontal_wrapped(|ui| {
+                for (i, (location, _)) in self.plotted.iter().enumerate() {
+                    if ui
+                        .button(format!("{0} ❌", describe(tracer, *location)))
+                        .clicked()
+                    {
+                        removed = Some(i);
+                    }
+                }
+            });
+
+            let last = tracer.instructions.len();
+            let plot = Plot::new("values").legend(Legend::default());
+
+            let clicked = plot.show(ui, |plot_ui| {
+                for (location, history) in &self.plotted {
+                    // Values hold until the next change, so they are drawn as steps.
+                    let mut points = Vec::with_capacity(history.len() * 2);
+                    for (i, (step, value)) in history.iter().enumerate() {
+                        if i > 0 {
+                            points.push([*step as f64, history[i - 1].1 as f64]);
+                        }
+                        points.push([*step as f64, *value as f64]);
+                    }
+                    if let Some((_, value)) = history.last() {
+                        points.push([last as f64, *value as f64]);
+                    }
+
+                    plot_ui
+                        .line(Line::new(PlotPoints::new(points)).name(describe(tracer, *location)));
+                }
+
+                plot_ui.vline(VLine::new(tracer.step as f64).name("current step"));
+
+                if !plot_ui.response().clicked() {
+                    return None;
+                }
+
+                plot_ui
+                    .pointer_coordinate()
+                    .map(|pointer| pointer.x.round().max(0.0) as usize)
+            });
+
+            if let Some(step) = clicked.inner {
+                tracer.seek(step);
+            }
+
+            if let Some(location) = added {
+                self.add(tracer, location);
+            }
+
+            if let Some(i) = removed {
+                self.plotted.remove(i);
+            }
+        });
+
+        self.open = open;
+    }
+}
@@ -0,0 +1,255 @@
+use crate::trace::{Profile, Tracer};
+use egui::Context;
+use egui_extras::{Column, TableBuilder};
+
+/// What is profiled.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum View {
+    #[default]
+    Instructions,
+    Blocks,
+    Functions,
+}
+
+impl View {
+    /// The titles of the columns between the address and the share of the trace.
+    fn columns(self) -> &'static [&'static str] {
+        match self {
+            View::Instructions => &["Instruction", "Executions"],
+            View::Blocks => &["Function", "Size", "Executions", "Instructions"],
+            View::Functions => &["Name", "Calls", "Incl.", "Excl."],
+        }
+    }
+}
+
+/// A row of the table, the same for every view.
+struct Row {
+    address: u64,
+    text: String,
+    counts: Vec<u64>,
+    /// How many of the instructions of the trace it accounts for.
+    share: u64,
+    /// The function whose graph clicking the address opens, otherwise it seeks to the step.
+    function: Option<u64>,
+    step: Option<usize>,
+}
+
+/// Profiles how many times every instruction, block and function was executed, and heats the
+/// nodes of the graphs accordingly.
+#[derive(Default)]
+pub struct ProfileWindow {
+    pub open: bool,
+    /// Whether the graphs are colored by how hot their nodes are.
+    pub heat: bool,
+    view: View,
+    /// The column the table is sorted by, starting from the address.
+    sort: usize,
+    descending: bool,
+    /// The profile along with how many instructions the trace had, as live traces grow.
+    profile: Option<(usize, Profile)>,
+    /// The rows of the table in order, along with the view and sorting they are for.
+    rows: Option<((View, usize, bool), Vec<Row>)>,
+}
+
+impl ProfileWindow {
+    /// The profile of the trace as it is now.
+    pub fn profile(&mut self, tracer: &Tracer) -> &Profile {
+        let len = tracer.instructions.len();
+        if self
+            .profile
+            .as_ref()
+            .is_none_or(|(profiled, _)| *profiled != len)
+        {
+            self.profile = Some((len, tracer.profile()));
+            self.rows = None;
+        }
+
+        &self.profile.as_ref().unwrap().1
+    }
+
+    /// Profiles again, as functions or their names changed.
+    pub fn invalidate(&mut self) {
+        self.profile = None;
+        self.rows = None;
+    }
+
+    /// Shows the window, returning the function whose graph should be opened if any.
+    pub fn window(&mut self, ctx: &Context, tracer: &mut Tracer) -> Option<u64> {
+        let mut open = self.open;
+        let mut opened = None;
+        let mut seek = None;
+
+        egui::Window::new("Profile")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for (view, name) in [
+                        (View::Instructions, "Instructions"),
+                        (View::Blocks, "Blocks"),
+                        (View::Functions, "Functions"),
+                    ] {
+                        if ui.selectable_value(&mut self.view, view, name).clicked() {
+                            self.sort = 0;
+                            self.descending = false;
+                        }
+                    }
+
+                    ui.checkbox(&mut self.heat, "Color the graphs");
+                });
+
+                let view = self.view;
+                let key = (view, self.sort, self.descending);
+                let total = self.profile(tracer).total.max(1) as f64;
+
+                if self.rows.as_ref().is_none_or(|(sorted, _)| *sorted != key) {
+                    let profile = &self.profile.as_ref().unwrap().1;
+                    let mut rows = rows(view, tracer, profile);
+                    sort(&mut rows, self.sort, self.descending);
+                    self.rows = Some((key, rows));
+                }
+
+                let titles: Vec<&str> = ["Address"]
+                    .into_iter()
+                    .chain(view.columns().iter().copied())
+                    .chain(["Share"])
+                    .collect();
+                let height = ui.text_style_height(&egui::TextStyle::Body);
+
+                TableBuilder::new(ui)
+                    .striped(true)
+                    .columns(Column::auto().resizable(true), titles.len())
+                    .header(height, |mut header| {
+                        for (column, title) in titles.iter().enumerate() {
+                            header.col(|ui| {
+                                let arrow = match (self.sort == column, self.descending) {
+                                    (false, _) => "",
+                                    (true, false) => " ⬆",
+                                    (true, true) => " ⬇",
+                                };
+
+                                if ui.button(format!("{title}{arrow}")).clicked() {
+                                    self.descending = self.sort == column && !self.descending;
+                                    self.sort = column;
+                                }
+                            });
+                        }
+                    })
+                    .body(|body| {
+                        let rows = &self.rows.as_ref().unwrap().1;
+                        body.rows(height, rows.len(), |mut row| {
+                            let profiled = &rows[row.index()];
+
+                            row.col(|ui| {
+                                if ui.link(format!("0x{0:X}", profiled.address)).clicked() {
+                                    opened = profiled.function;
+                                    seek = profiled.step;
+                                }
+                            });
+                            row.col(|ui| {
+                                ui.label(&profiled.text);
+                            });
+                            for count in &profiled.counts {
+                                row.col(|ui| {
+                                    ui.label(count.to_string());
+                                });
+                            }
+                            row.col(|ui| {
+                                ui.label(format!("{0:.2}%", profiled.share as f64 * 100.0 / total));
+                            });
+                        });
+                    });
+            });
+
+        if let Some(step) = seek {
+            tracer.seek(step);
+        }
+
+        self.open = open;
+
+        opened
+    }
+}
+
+/// Sorts rows by a column, starting from the address.
+fn sort(rows: &mut [Row], column: usize, descending: bool) {
+    rows.sort_by(|a, b| {
+        let ordering = match column {
+            0 => a.address.cmp(&b.address),
+            1 => a.text.cmp(&b.text),
+            column if column - 2 < a.counts.len() => {
+                a.counts[column - 2].cmp(&b.counts[column - 2])
+            }
+            _ => a.share.cmp(&b.share),
+        };
+
+        // Ties fall back to the address so that the order is always the same.
+        let ordering = ordering.then(a.address.cmp(&b.address));
+
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+/// The rows of a view of the profile, in no particular order.
+fn rows(view: View, tracer: &Tracer, profile: &Profile) -> Vec<Row> {
+    let function_name = |address: u64| {
+        tracer
+            .cfg
+            .function(address)
+            .map_or_else(String::new, |function| function.name.clone())
+    };
+
+    match view {
+        View::Instructions => profile
+            .instructions
+            .iter()
+            .map(|hotspot| {
+                let insn = &tracer.instructions[hotspot.first_step].insn;
+                let text = match tracer.cfg.symbols().get(hotspot.address) {
+                    Some(name) => format!("{name}: {insn}"),
+                    None => insn.clone(),
+                };
+
+                Row {
+                    address: hotspot.address,
+                    text,
+                    counts: vec![hotspot.executions],
+                    share: hotspot.executions,
+                    function: None,
+                    step: Some(hotspot.first_step),
+                }
+            })
+            .collect(),
+        View::Blocks => profile
+            .blocks
+            .iter()
+            .map(|block| Row {
+                address: block.start,
+                text: function_name(block.function),
+                counts: vec![block.size as u64, block.executions, block.instructions],
+                share: block.instructions,
+                function: Some(block.function),
+                step: None,
+            })
+            .collect(),
+        View::Functions => tracer
+            .cfg
+            .functions()
+            .map(|function| Row {
+                address: function.start,
+                text: function.name.clone(),
+                counts: vec![
+                    function.stats.calls,
+                    function.stats.inclusive,
+                    function.stats.exclusive,
+                ],
+                share: function.stats.exclusive,
+                function: Some(function.start),
+                step: None,
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,92 @@
+use super::dataflow::{self, describe, LocationPicker};
+use crate::trace::{Location, Slice, Tracer};
+use egui::{Color32, Context};
+use std::collections::BTreeSet;
+
+/// What the instructions of a slice are marked with.
+pub const COLOR: Color32 = Color32::from_rgb(150, 90, 220);
+
+/// Slices the value in a register or memory location at the current step and lists the
+/// instructions it came from.
+#[derive(Default)]
+pub struct SliceWindow {
+    pub open: bool,
+    picker: LocationPicker,
+    slice: Option<Slice>,
+}
+
+impl SliceWindow {
+    /// Slices the value in a location at the current step.
+    pub fn start(&mut self, tracer: &Tracer, target: Location) {
+        self.slice = Some(tracer.slice(target, tracer.step));
+        self.open = true;
+    }
+
+    /// The addresses of the instructions in the slice, if there is one.
+    pub fn addresses(&self) -> Option<&BTreeSet<u64>> {
+        self.slice.as_ref().map(|slice| &slice.addresses)
+    }
+
+    /// Shows the window, picking registers from the given thread or otherwise the one at the
+    /// current step.
+    pub fn window(&mut self, ctx: &Context, tracer: &mut Tracer, thread: Option<u32>) {
+        let mut open = self.open;
+        let thread = thread.unwrap_or_else(|| tracer.thread());
+
+        egui::Window::new("Slice").open(&mut open).show(ctx, |ui| {
+            let mut target = None;
+
+            ui.horizontal(|ui| {
+                let location = self.picker.ui(ui, tracer, thread);
+
+                let button = egui::Button::new(format!("Slice at step {0}", tracer.step));
+                if ui.add_enabled(location.is_some(), button).clicked() {
+                    target = location;
+                }
+            });
+
+            if let Some(target) = target {
+                self.start(tracer, target);
+            }
+
+            let Some(slice) = &self.slice else {
+                ui.label("Nothing is sliced.");
+                return;
+            };
+
+            ui.separator();
+            ui.label(format!(
+                "{0} at step {1} came from {2} instructions",
+                describe(tracer, slice.target),
+                slice.step,
+                slice.steps.len()
+            ));
+
+            if !slice.origins.is_empty() {
+                let origins: Vec<String> = slice
+                    .origins
+                    .iter()
+                    .map(|location| describe(tracer, *location))
+                    .collect();
+
+                ui.label(format!(
+                    "And from what was in {0} before the trace began",
+                    origins.join(", ")
+                ));
+            }
+
+            let clear = ui.button("Clear").clicked();
+            let seek = dataflow::listing(ui, tracer, &slice.steps, COLOR);
+
+            if let Some(step) = seek {
+                tracer.seek(step);
+            }
+
+            if clear {
+                self.slice = None;
+            }
+        });
+
+        self.open = open;
+    }
+}
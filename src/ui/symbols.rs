@@ -0,0 +1,146 @@
+use super::{file::FileDialog, graph::Graph};
+use crate::trace::{Error, Symbols, Tracer};
+use egui::Context;
+
+/// Imports symbols and lets functions be renamed and addresses labelled, saving the names to
+/// the project sidecar of the trace when there is one.
+#[derive(Default)]
+pub struct SymbolsWindow {
+    pub open: bool,
+    /// The trace the names are saved next to.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub trace: Option<std::path::PathBuf>,
+    fd: FileDialog,
+    /// The address being named, in hexadecimal.
+    address: String,
+    name: String,
+    error: Option<String>,
+}
+
+impl SymbolsWindow {
+    /// Starts naming an address, which is the start of a function when one is selected.
+    pub fn select(&mut self, address: u64, tracer: &Tracer) {
+        self.address = format!("{address:X}");
+        self.name = tracer.cfg.symbols().get(address).unwrap_or("").to_owned();
+    }
+
+    /// Shows the window, returning whether any function or name changed.
+    pub fn window(
+        &mut self,
+        ctx: &Context,
+        tracer: &mut Tracer,
+        graph: Option<&mut Graph>,
+    ) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        let imported = self
+            .fd
+            .get()
+            .map(|path| std::fs::read(path).map_err(Error::from));
+
+        #[cfg(target_arch = "wasm32")]
+        let imported = self.fd.get().map(Ok);
+
+        let mut changed = false;
+
+        if let Some(bytes) = imported {
+            match bytes.and_then(|bytes| Symbols::import(&bytes)) {
+                Ok(imported) => {
+                    let mut symbols = tracer.cfg.symbols().clone();
+                    symbols.extend(imported);
+                    tracer.cfg.set_symbols(symbols);
+                    changed = true;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
+        let mut open = self.open;
+
+        egui::Window::new("Symbols")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if ui.button("Import from file").clicked() {
+                    self.fd.open();
+                }
+                ui.label("An ELF file, a linker map, or a text file of address name pairs.");
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label("Address 0x");
+                    ui.text_edit_singleline(&mut self.address);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Name");
+                    ui.text_edit_singleline(&mut self.name);
+                });
+
+                let address = u64::from_str_radix(self.address.trim(), 16).ok();
+
+                ui.horizontal(|ui| {
+                    let Some(address) = address else {
+                        ui.label("Enter an address in hexadecimal.");
+                        return;
+                    };
+
+                    // Resetting is how a name is removed, a blank one would leave nothing to
+                    // click in the lists.
+                    if self.name.trim().is_empty() {
+                        ui.add_enabled(false, egui::Button::new("Rename"));
+                    } else if ui.button("Rename").clicked() {
+                        tracer.cfg.rename(address, Some(&self.name));
+                        changed = true;
+                    }
+
+                    if ui.button("Reset").clicked() {
+                        tracer.cfg.rename(address, None);
+                        self.name.clear();
+                        changed = true;
+                    }
+                });
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+
+                ui.separator();
+
+                let mut select = None;
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (address, name) in tracer.cfg.symbols().iter() {
+                        if ui.link(format!("0x{address:X} {name}")).clicked() {
+                            select = Some(*address);
+                        }
+                    }
+                });
+
+                if let Some(address) = select {
+                    self.select(address, tracer);
+                }
+            });
+
+        self.open = open;
+
+        if changed {
+            self.error = self.refresh(tracer, graph).err().map(|e| e.to_string());
+        }
+
+        changed
+    }
+
+    /// Saves the names and renders the graphs they show up in again.
+    fn refresh(&self, tracer: &mut Tracer, graph: Option<&mut Graph>) -> Result<(), Error> {
+        let changes = tracer.cfg.take_changes();
+        if let Some(graph) = graph {
+            graph.invalidate(&changes);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(trace) = &self.trace {
+            tracer.cfg.symbols().save(trace)?;
+        }
+
+        Ok(())
+    }
+}
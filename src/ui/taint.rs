@@ -0,0 +1,126 @@
+use super::dataflow::{self, describe, LocationPicker};
+use crate::trace::{Location, Taint, Tracer};
+use egui::{Color32, Context};
+use std::collections::BTreeSet;
+
+/// What tainted instructions, registers and memory are marked with.
+pub const COLOR: Color32 = Color32::from_rgb(230, 120, 30);
+
+/// Taints a register or memory location at the current step and lists the instructions its
+/// data flowed through.
+#[derive(Default)]
+pub struct TaintWindow {
+    pub open: bool,
+    picker: LocationPicker,
+    taint: Option<Taint>,
+    /// How many instructions the trace had when the taint was followed, as live traces grow.
+    instructions: usize,
+    /// The locations tainted at a step, kept until the tracer moves.
+    current: Option<(usize, BTreeSet<Location>)>,
+}
+
+impl TaintWindow {
+    /// Stops tainting the location of the previous trace.
+    pub fn reset(&mut self) {
+        self.taint = None;
+        self.current = None;
+    }
+
+    /// Taints a location at the current step.
+    pub fn start(&mut self, tracer: &Tracer, source: Location) {
+        self.taint = Some(tracer.taint(source, tracer.step));
+        self.instructions = tracer.instructions.len();
+        self.current = None;
+        self.open = true;
+    }
+
+    /// The locations tainted at the current step, if anything is being tainted.
+    pub fn tainted(&mut self, tracer: &Tracer) -> Option<&BTreeSet<Location>> {
+        let taint = self.taint.as_mut()?;
+
+        // Instructions that came in since are followed as well.
+        if self.instructions != tracer.instructions.len() {
+            *taint = tracer.taint(taint.source, taint.step);
+            self.instructions = tracer.instructions.len();
+            self.current = None;
+        }
+
+        if self.current.as_ref().map(|(step, _)| *step) != Some(tracer.step) {
+            self.current = Some((tracer.step, taint.at(tracer.step)));
+        }
+
+        self.current.as_ref().map(|(_, tainted)| tainted)
+    }
+
+    /// The addresses of the instructions which read tainted data, if anything is tainted.
+    pub fn addresses(&self) -> Option<&BTreeSet<u64>> {
+        self.taint.as_ref().map(|taint| &taint.addresses)
+    }
+
+    /// Shows the window, picking registers from the given thread or otherwise the one at the
+    /// current step.
+    pub fn window(&mut self, ctx: &Context, tracer: &mut Tracer, thread: Option<u32>) {
+        let mut open = self.open;
+        let thread = thread.unwrap_or_else(|| tracer.thread());
+
+        egui::Window::new("Taint").open(&mut open).show(ctx, |ui| {
+            let mut source = None;
+
+            ui.horizontal(|ui| {
+                let location = self.picker.ui(ui, tracer, thread);
+
+                let button = egui::Button::new(format!("Taint at step {0}", tracer.step));
+                if ui.add_enabled(location.is_some(), button).clicked() {
+                    source = location;
+                }
+            });
+
+            if let Some(source) = source {
+                self.start(tracer, source);
+            }
+
+            let tainted: Vec<String> = self
+                .tainted(tracer)
+                .into_iter()
+                .flatten()
+                .map(|location| describe(tracer, *location))
+                .collect();
+
+            let Some(taint) = &self.taint else {
+                ui.label("Nothing is tainted.");
+                return;
+            };
+
+            ui.separator();
+            ui.label(format!(
+                "{0} tainted at step {1}, read by {2} instructions afterwards",
+                describe(tracer, taint.source),
+                taint.step,
+                taint.steps.len()
+            ));
+
+            if tainted.is_empty() {
+                ui.label("Nothing is tainted at the current step.");
+            } else {
+                ui.label(format!(
+                    "Tainted at the current step: {0}",
+                    tainted.join(", ")
+                ));
+            }
+
+            let clear = ui.button("Clear").clicked();
+            let seek = dataflow::listing(ui, tracer, &taint.steps, COLOR);
+
+            if let Some(step) = seek {
+                tracer.seek(step);
+            }
+
+            if clear {
+                self.taint = None;
+                self.current = None;
+            }
+        });
+
+        self.open = open;
+    }
+}
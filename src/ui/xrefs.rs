@@ -0,0 +1,82 @@
+use crate::trace::Tracer;
+use egui::Context;
+
+/// Lists every call and jump to the selected function or address, and seeks to where they were
+/// taken.
+#[derive(Default)]
+pub struct Xrefs {
+    pub open: bool,
+    /// The address being cross referenced.
+    target: Option<u64>,
+    /// The address as it is being typed in, in hexadecimal.
+    input: String,
+}
+
+impl Xrefs {
+    /// Cross references an address, which is the start of a function when one is selected.
+    pub fn select(&mut self, address: u64) {
+        self.target = Some(address);
+        self.input = format!("{address:X}");
+    }
+
+    pub fn window(&mut self, ctx: &Context, tracer: &mut Tracer) {
+        let mut open = self.open;
+
+        egui::Window::new("Cross references")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Address 0x");
+
+                    if ui.text_edit_singleline(&mut self.input).changed() {
+                        self.target = u64::from_str_radix(self.input.trim(), 16).ok();
+                    }
+                });
+
+                let Some(target) = self.target else {
+                    ui.label("Select a function or a block, or enter an address.");
+                    return;
+                };
+
+                if let Some(function) = tracer.cfg.function(target) {
+                    ui.label(format!("Function {0}", function.name));
+                }
+
+                let Some(xrefs) = tracer.xrefs.get(&target) else {
+                    ui.label("Nothing calls or jumps here.");
+                    return;
+                };
+
+                let mut seek = None;
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for xref in xrefs {
+                        let header = format!(
+                            "0x{0:X} {1:?}, taken {2} times",
+                            xref.from,
+                            xref.kind,
+                            xref.steps.len()
+                        );
+
+                        egui::CollapsingHeader::new(header)
+                            .id_source((target, xref.from, xref.kind as u8))
+                            .show(ui, |ui| {
+                                ui.horizontal_wrapped(|ui| {
+                                    for step in &xref.steps {
+                                        if ui.button(format!("step {step}")).clicked() {
+                                            seek = Some(*step);
+                                        }
+                                    }
+                                });
+                            });
+                    }
+                });
+
+                if let Some(step) = seek {
+                    tracer.seek(step);
+                }
+            });
+
+        self.open = open;
+    }
+}